@@ -17,7 +17,7 @@
  */
 use serde::{Deserialize, Serialize};
 
-use db_core::Repository;
+use db_core::{CrawlRun, CrawlSchedule, Repository, SearchQuery};
 
 pub const ROUTES: Api = Api::new();
 
@@ -62,6 +62,7 @@ pub struct MiniIndex {
 pub struct Api {
     pub get_latest: &'static str,
     pub forges: &'static str,
+    pub forge_crawls: &'static str,
     pub search: Search,
     pub introducer: Introducer,
 }
@@ -70,12 +71,14 @@ impl Api {
     const fn new() -> Api {
         let get_latest = "/api/v1/federated/latest";
         let forges = "/api/v1/forges/list";
+        let forge_crawls = "/api/v1/forges/crawls";
         let search = Search::new();
         let introducer = Introducer::new();
         Api {
             get_latest,
             search,
             forges,
+            forge_crawls,
             introducer,
         }
     }
@@ -89,9 +92,29 @@ pub struct LatestResp {
 #[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
 pub struct SearchRepositoryReq {
     pub query: String,
+    /// page of results, starting from 1
+    pub page: Option<u32>,
+    /// parsed form of `query`, sent by Starchart instances when federating searches. Takes
+    /// precedence over `query`
+    #[serde(default)]
+    pub structured: Option<SearchQuery>,
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
 pub struct SearchRepositoryResp {
     pub repositories: Vec<Repository>,
 }
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct ForgeCrawlsReq {
+    /// url of the forge
+    pub url: String,
+    pub page: Option<u32>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct ForgeCrawlsResp {
+    pub schedule: CrawlSchedule,
+    /// latest crawl first
+    pub crawls: Vec<CrawlRun>,
+}
@@ -31,6 +31,7 @@
 //! - [errors](crate::auth): error data structures used in this crate
 //! - [ops](crate::ops): meta operations like connection pool creation, migrations and getting
 //! connection from pool
+//! - [search](crate::search): structured repository search queries
 use std::str::FromStr;
 
 use serde::{Deserialize, Serialize};
@@ -38,11 +39,13 @@ use url::Url;
 
 pub mod errors;
 pub mod ops;
+pub mod search;
 #[cfg(feature = "test")]
 pub mod tests;
 
 use dev::*;
 pub use ops::GetConnection;
+pub use search::SearchQuery;
 
 pub mod prelude {
     //! useful imports for users working with a supported database
@@ -88,6 +91,16 @@ pub fn clean_url(url: &Url) -> String {
     url.as_str().to_string()
 }
 
+/// Split a full-text search query into lowercase words. Wildcards and punctuation are dropped:
+/// implementations match every word as a prefix of a word in the searched fields
+pub fn search_terms(query: &str) -> Vec<String> {
+    query
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|term| !term.is_empty())
+        .map(|term| term.to_lowercase())
+        .collect()
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 /// user data
 pub struct User {
@@ -140,6 +153,35 @@ pub struct AddRepository<'a> {
     pub website: Option<&'a str>,
     /// is this repository an import
     pub import: bool,
+    /// repository metadata reported by the forge
+    pub metadata: RepositoryMetadata,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+/// Repository metadata reported by forges. Forges that don't report a field leave it unset
+pub struct RepositoryMetadata {
+    /// number of stars
+    pub stars: Option<u32>,
+    /// number of forks
+    pub forks: Option<u32>,
+    /// is this repository a fork
+    pub fork: bool,
+    /// html link to the repository this repository was forked from, if any
+    pub parent: Option<String>,
+    /// is this repository a mirror
+    pub mirror: bool,
+    /// is this repository archived
+    pub archived: bool,
+    /// default branch of the repository
+    pub default_branch: Option<String>,
+    /// primary programming language of the repository
+    pub language: Option<String>,
+    /// HTTP clone link of the repository
+    pub clone_url: Option<String>,
+    /// creation time on the forge, as unix timestamp
+    pub created_at: Option<i64>,
+    /// last update time on the forge, as unix timestamp
+    pub updated_at: Option<i64>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
@@ -155,6 +197,139 @@ pub struct Forge {
     pub forge_type: ForgeImplementation,
     /// last crawl
     pub last_crawl_on: Option<i64>,
+    /// last crawl that went through all repositories on the forge
+    pub last_full_crawl_on: Option<i64>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+/// Rate limit state of a forge, persisted so that restarts don't hammer the forge
+pub struct ForgeRateLimit {
+    /// delay between API calls, in milliseconds
+    pub delay: u64,
+    /// the forge must not be called before this unix timestamp
+    pub blocked_until: Option<i64>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+/// Progress of a forge crawl that hasn't finished, so that it can be resumed
+pub struct CrawlCheckpoint {
+    /// token of the next page to crawl
+    pub token: String,
+    /// number of the next page to crawl
+    pub page: u64,
+    /// start of the crawl
+    pub started_on: i64,
+    /// the crawl only fetches repositories updated since this unix timestamp. None = full crawl
+    pub since: Option<i64>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+/// create a challenge that the admin of a forge must solve to prove ownership of the forge
+pub struct CreateDNSChallenge<'a> {
+    /// url of the forge instance
+    pub url: &'a Url,
+    /// key of the TXT record
+    pub key: &'a str,
+    /// value of the TXT record, random for every challenge
+    pub value: &'a str,
+    /// creation time of the challenge, unix timestamp
+    pub created: i64,
+    /// the challenge can't be solved after this unix timestamp
+    pub expires_on: i64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+/// challenge that the admin of a forge must solve to prove ownership of the forge
+pub struct DNSChallenge {
+    /// url of the forge instance: with scheme but remove trailing slash
+    pub url: String,
+    /// key of the TXT record
+    pub key: String,
+    /// value of the TXT record
+    pub value: String,
+    /// creation time of the challenge, unix timestamp
+    pub created: i64,
+    /// the challenge can't be solved after this unix timestamp
+    pub expires_on: i64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+/// Outcome of a forge crawl
+pub enum CrawlOutcome {
+    /// crawl went through
+    Success,
+    /// crawl failed with this error
+    Failed(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+/// Crawl schedule of a forge
+pub struct CrawlSchedule {
+    /// url of the forge
+    pub url: String,
+    /// the forge is due for a crawl at this unix timestamp
+    pub next_crawl_on: i64,
+    /// due forges with higher priority are crawled first
+    pub priority: u32,
+    /// seconds between crawls of this forge, overrides the configured default
+    pub interval: Option<u64>,
+    /// start of the last crawl
+    pub last_started_on: Option<i64>,
+    /// end of the last crawl
+    pub last_finished_on: Option<i64>,
+    /// outcome of the last crawl
+    pub last_outcome: Option<CrawlOutcome>,
+    /// number of crawls that failed since the last one that went through
+    pub consecutive_failures: u32,
+    /// disabled forges aren't crawled until they are scheduled again
+    pub disabled: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+/// What a forge crawl fetched and changed
+pub struct CrawlStats {
+    /// pages fetched from the forge
+    pub pages: u64,
+    /// repositories that weren't known before
+    pub repositories_added: u64,
+    /// repositories that were known before
+    pub repositories_updated: u64,
+    /// repositories that the forge no longer lists
+    pub repositories_removed: u64,
+    /// users that weren't known before
+    pub users_added: u64,
+    /// users that were known before
+    pub users_updated: u64,
+    /// users that the forge no longer lists
+    pub users_removed: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+/// Crawl in the crawl history of a forge
+pub struct CrawlRun {
+    /// start of the crawl
+    pub started_on: i64,
+    /// end of the crawl
+    pub finished_on: i64,
+    /// what the crawl fetched and changed
+    pub stats: CrawlStats,
+    /// outcome of the crawl
+    pub outcome: CrawlOutcome,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// Crawl of a forge that finished
+pub struct FinishedCrawl<'a> {
+    /// start of the crawl
+    pub started_on: i64,
+    /// end of the crawl
+    pub finished_on: i64,
+    /// error, if the crawl failed
+    pub error: Option<&'a str>,
+    /// what the crawl fetched and changed
+    pub stats: CrawlStats,
+    /// the forge is due for its next crawl at this unix timestamp
+    pub next_crawl_on: i64,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
@@ -177,6 +352,8 @@ pub struct Repository {
     pub website: Option<String>,
     /// is this repository an import
     pub import: bool,
+    /// repository metadata reported by the forge
+    pub metadata: RepositoryMetadata,
 }
 
 #[async_trait]
@@ -201,6 +378,74 @@ pub trait SCDatabase: std::marker::Send + std::marker::Sync + CloneSPDatabase {
     /// check if forge type exists
     async fn forge_type_exists(&self, forge_type: &ForgeImplementation) -> DBResult<bool>;
 
+    /// create DNS challenge
+    async fn create_dns_challenge(&self, c: &CreateDNSChallenge) -> DBResult<()>;
+
+    /// check if a DNS challenge with `key` exists
+    async fn dns_challenge_exists(&self, key: &str) -> DBResult<bool>;
+
+    /// get DNS challenge with `key`
+    async fn get_dns_challenge(&self, key: &str) -> DBResult<DNSChallenge>;
+
+    /// delete DNS challenge with `key`
+    async fn delete_dns_challenge(&self, key: &str) -> DBResult<()>;
+
+    /// delete DNS challenges that expired at or before `now`
+    async fn rm_expired_dns_challenges(&self, now: i64) -> DBResult<()>;
+
+    /// Record completed crawl of a forge, started at `crawled_on`. Full crawls went through all
+    /// repositories on the forge, instead of only those updated since the last crawl
+    async fn record_forge_crawl(&self, url: &Url, crawled_on: i64, full: bool) -> DBResult<()>;
+
+    /// Get persisted rate limit state of a forge, if any
+    async fn get_forge_rate_limit(&self, url: &Url) -> DBResult<Option<ForgeRateLimit>>;
+
+    /// Persist rate limit state of a forge
+    async fn set_forge_rate_limit(&self, url: &Url, rate_limit: &ForgeRateLimit) -> DBResult<()>;
+
+    /// Get progress of the unfinished crawl of a forge, if any
+    async fn get_crawl_checkpoint(&self, url: &Url) -> DBResult<Option<CrawlCheckpoint>>;
+
+    /// Persist progress of the crawl of a forge, once the pages before the checkpoint are stored
+    async fn set_crawl_checkpoint(&self, url: &Url, checkpoint: &CrawlCheckpoint) -> DBResult<()>;
+
+    /// Remove progress of the crawl of a forge, once the crawl finished
+    async fn rm_crawl_checkpoint(&self, url: &Url) -> DBResult<()>;
+
+    /// Schedule crawl of a forge at `next_crawl_on`. Forges that aren't known yet are scheduled
+    /// too, so that they can be crawled for the first time. Disabled forges are enabled again.
+    async fn schedule_forge_crawl(
+        &self,
+        url: &Url,
+        next_crawl_on: i64,
+        priority: u32,
+    ) -> DBResult<()>;
+
+    /// Set seconds between crawls of a scheduled forge. `None` uses the configured default
+    async fn set_forge_crawl_interval(&self, url: &Url, interval: Option<u64>) -> DBResult<()>;
+
+    /// Disable or enable crawls of a scheduled forge
+    async fn set_forge_crawl_disabled(&self, url: &Url, disabled: bool) -> DBResult<()>;
+
+    /// Get crawl schedule of a forge, if it is scheduled
+    async fn get_forge_crawl_schedule(&self, url: &Url) -> DBResult<Option<CrawlSchedule>>;
+
+    /// Get forges that are due for a crawl at `now`, highest priority and longest overdue first.
+    /// Disabled forges are skipped
+    async fn get_due_forge_crawls(&self, now: i64, limit: u32) -> DBResult<Vec<CrawlSchedule>>;
+
+    /// Record finished crawl of a scheduled forge in its crawl history and schedule its next
+    /// crawl
+    async fn record_forge_crawl_run(&self, url: &Url, crawl: &FinishedCrawl) -> DBResult<()>;
+
+    /// Get crawl history of a forge, latest crawl first
+    async fn get_forge_crawl_runs(
+        &self,
+        url: &Url,
+        offset: u32,
+        limit: u32,
+    ) -> DBResult<Vec<CrawlRun>>;
+
     /// Get all forges
     async fn get_all_forges(
         &self,
@@ -212,6 +457,9 @@ pub trait SCDatabase: std::marker::Send + std::marker::Sync + CloneSPDatabase {
     /// add new user to database
     async fn add_user(&self, u: &AddUser) -> DBResult<()>;
 
+    /// update data of an existing user
+    async fn update_user(&self, u: &AddUser) -> DBResult<()>;
+
     /// get user data
     async fn get_user(&self, username: &str, url: &Url) -> DBResult<User>;
 
@@ -228,14 +476,53 @@ pub trait SCDatabase: std::marker::Send + std::marker::Sync + CloneSPDatabase {
     /// check if a repository exists.
     async fn repository_exists(&self, name: &str, owner: &str, url: &Url) -> DBResult<bool>;
 
+    /// Get repositories of a forge that weren't seen by a crawl since `seen_since`. Imported
+    /// repositories are ignored
+    async fn get_unseen_repositories(
+        &self,
+        url: &Url,
+        seen_since: i64,
+    ) -> DBResult<Vec<Repository>>;
+
+    /// Get users of a forge that weren't seen by a crawl since `seen_since`. Imported users are
+    /// ignored
+    async fn get_unseen_users(&self, url: &Url, seen_since: i64) -> DBResult<Vec<User>>;
+
     /// Get all repositories
     async fn get_all_repositories(&self, offset: u32, limit: u32) -> DBResult<Vec<Repository>>;
 
     /// add new repository to database.
     async fn create_repository(&self, r: &AddRepository) -> DBResult<()>;
 
-    /// Search all repositories
-    async fn search_repository(&self, query: &str) -> DBResult<Vec<Repository>>;
+    /// update metadata of an existing repository, identified by its name, owner and forge. Search
+    /// indexes and topics are refreshed to match the new metadata
+    async fn update_repository(&self, r: &AddRepository) -> DBResult<()>;
+
+    /// Search names, descriptions, websites, topics and owner usernames of all repositories.
+    /// Every word of the query must match, see [search_terms]. Results are ranked by relevance,
+    /// best match first
+    async fn search_repository(
+        &self,
+        query: &str,
+        offset: u32,
+        limit: u32,
+    ) -> DBResult<Vec<Repository>> {
+        let query = SearchQuery {
+            terms: search_terms(query),
+            ..Default::default()
+        };
+        self.query_repositories(&query, offset, limit).await
+    }
+
+    /// Get repositories that match a [SearchQuery]. Results are ranked by the relevance of the
+    /// query's free text, best match first, and repositories that rank equally are ordered by
+    /// age, oldest first. Empty queries match nothing
+    async fn query_repositories(
+        &self,
+        query: &SearchQuery,
+        offset: u32,
+        limit: u32,
+    ) -> DBResult<Vec<Repository>>;
 
     /// Add Starchart instance to introducer
     async fn add_starchart_to_introducer(&self, url: &Url) -> DBResult<()>;
@@ -303,12 +590,19 @@ impl Clone for Box<dyn SCDatabase> {
     }
 }
 
-/// Forge type: Gitea, Sourcehut, GitLab, etc. Support is currently only available for Gitea
+/// Forge type: Gitea, Sourcehut, GitLab, etc. Support is currently available for Gitea, Forgejo,
+/// GitLab and Sourcehut
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum ForgeImplementation {
     /// [Gitea](https://gitea.io) softare forge
     Gitea,
+    /// [GitLab](https://gitlab.com) software forge
+    GitLab,
+    /// [Forgejo](https://forgejo.org) software forge
+    Forgejo,
+    /// [Sourcehut](https://sourcehut.org) software forge
+    SourceHut,
 }
 
 impl ForgeImplementation {
@@ -316,6 +610,9 @@ impl ForgeImplementation {
     pub const fn to_str(&self) -> &'static str {
         match self {
             ForgeImplementation::Gitea => "gitea",
+            ForgeImplementation::GitLab => "gitlab",
+            ForgeImplementation::Forgejo => "forgejo",
+            ForgeImplementation::SourceHut => "sourcehut",
         }
     }
 }
@@ -326,9 +623,15 @@ impl FromStr for ForgeImplementation {
     /// Convert [str] to [ForgeImplementation]
     fn from_str(s: &str) -> DBResult<Self> {
         const GITEA: &str = ForgeImplementation::Gitea.to_str();
+        const GITLAB: &str = ForgeImplementation::GitLab.to_str();
+        const FORGEJO: &str = ForgeImplementation::Forgejo.to_str();
+        const SOURCEHUT: &str = ForgeImplementation::SourceHut.to_str();
         let s = s.trim();
         match s {
             GITEA => Ok(Self::Gitea),
+            GITLAB => Ok(Self::GitLab),
+            FORGEJO => Ok(Self::Forgejo),
+            SOURCEHUT => Ok(Self::SourceHut),
             _ => Err(DBError::UnknownForgeType(s.to_owned())),
         }
     }
@@ -0,0 +1,218 @@
+/*
+ * ForgeFlux StarChart - A federated software forge spider
+ * Copyright (C) 2022  Aravinth Manivannan <realaravinth@batsense.net>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+//! Structured repository search queries
+//!
+//! Queries are free text mixed with `field:value` filters, like
+//! `spider topic:rust forge:codeberg.org owner:alice lang:go archived:false`.
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::search_terms;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+/// Repository search query. Repositories must match the free text and every filter that is set
+pub struct SearchQuery {
+    /// lowercase words matched against names, descriptions, websites, topics and owner
+    /// usernames of repositories, see [search_terms]
+    pub terms: Vec<String>,
+    /// lowercase topics that repositories must all be tagged with
+    pub topics: Vec<String>,
+    /// hostname of the forge that repositories are hosted on, with port if any
+    pub forge: Option<String>,
+    /// lowercase username of the repositories' owner
+    pub owner: Option<String>,
+    /// lowercase primary language of repositories
+    pub lang: Option<String>,
+    /// is the repository archived
+    pub archived: Option<bool>,
+}
+
+impl SearchQuery {
+    /// Parse a search query. Filters are words of the form `field:value`, where `field` is one
+    /// of `topic`, `forge`, `owner`, `lang` and `archived`. Repeated `topic` filters must all
+    /// match, while other repeated filters replace earlier ones. Words with unknown fields or
+    /// invalid values are searched for as free text
+    pub fn parse(query: &str) -> Self {
+        let mut q = Self::default();
+        for word in query.split_whitespace() {
+            if !q.add_filter(word) {
+                q.terms.extend(search_terms(word));
+            }
+        }
+        q
+    }
+
+    /// Try to read `word` as a filter. Returns `false` if `word` isn't a valid filter
+    fn add_filter(&mut self, word: &str) -> bool {
+        let (field, value) = match word.split_once(':') {
+            Some((field, value)) if !value.is_empty() => {
+                (field.to_lowercase(), value.to_lowercase())
+            }
+            _ => return false,
+        };
+
+        match field.as_str() {
+            "topic" => {
+                if !self.topics.contains(&value) {
+                    self.topics.push(value);
+                }
+            }
+            "forge" => match forge_hostname(&value) {
+                Some(hostname) => self.forge = Some(hostname),
+                None => return false,
+            },
+            "owner" => self.owner = Some(value),
+            "lang" => self.lang = Some(value),
+            "archived" => match value.as_str() {
+                "true" | "yes" => self.archived = Some(true),
+                "false" | "no" => self.archived = Some(false),
+                _ => return false,
+            },
+            _ => return false,
+        }
+        true
+    }
+
+    /// Does the query have neither free text nor filters? Empty queries match nothing
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+            && self.topics.is_empty()
+            && self.forge.is_none()
+            && self.owner.is_none()
+            && self.lang.is_none()
+            && self.archived.is_none()
+    }
+
+    /// Words to look up in the mini-indexes of other Starchart instances, which hold topics,
+    /// usernames and forge URLs. Peers apply every filter themselves, so languages and archival
+    /// status aren't looked up
+    pub fn mini_index_words(&self) -> Vec<String> {
+        let mut words = self.terms.clone();
+        for value in self.topics.iter().chain(&self.owner).chain(&self.forge) {
+            words.extend(search_terms(value));
+        }
+        words
+    }
+
+    /// Does a forge, identified by its [clean_url](crate::clean_url), match the `forge` filter?
+    pub fn forge_matches(&self, forge_url: &str) -> bool {
+        match &self.forge {
+            Some(hostname) => {
+                forge_url == format!("https://{hostname}/")
+                    || forge_url == format!("http://{hostname}/")
+            }
+            None => true,
+        }
+    }
+}
+
+/// Get hostname, with port if any, from a forge URL or hostname
+fn forge_hostname(forge: &str) -> Option<String> {
+    let url = if forge.contains("://") {
+        Url::parse(forge)
+    } else {
+        Url::parse(&format!("https://{forge}"))
+    }
+    .ok()?;
+    let host = url.host_str()?;
+    match url.port() {
+        Some(port) => Some(format!("{host}:{port}")),
+        None => Some(host.to_owned()),
+    }
+}
+
+impl fmt::Display for SearchQuery {
+    /// Write the query in the syntax read by [SearchQuery::parse]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut words = self.terms.clone();
+        words.extend(self.topics.iter().map(|topic| format!("topic:{topic}")));
+        if let Some(forge) = &self.forge {
+            words.push(format!("forge:{forge}"));
+        }
+        if let Some(owner) = &self.owner {
+            words.push(format!("owner:{owner}"));
+        }
+        if let Some(lang) = &self.lang {
+            words.push(format!("lang:{lang}"));
+        }
+        if let Some(archived) = self.archived {
+            words.push(format!("archived:{archived}"));
+        }
+        write!(f, "{}", words.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_works() {
+        let q = SearchQuery::parse(
+            "Spider topic:Rust forge:codeberg.org owner:alice lang:go archived:false topic:web",
+        );
+        assert_eq!(
+            q,
+            SearchQuery {
+                terms: vec!["spider".into()],
+                topics: vec!["rust".into(), "web".into()],
+                forge: Some("codeberg.org".into()),
+                owner: Some("alice".into()),
+                lang: Some("go".into()),
+                archived: Some(false),
+            }
+        );
+        assert_eq!(SearchQuery::parse(&q.to_string()), q);
+
+        // repeated filters
+        let q = SearchQuery::parse("topic:rust topic:rust owner:alice owner:bob");
+        assert_eq!(q.topics, vec!["rust".to_string()]);
+        assert_eq!(q.owner, Some("bob".into()));
+
+        // forge filters take URLs and keep ports
+        let q = SearchQuery::parse("forge:https://git.example.com:3000/");
+        assert_eq!(q.forge, Some("git.example.com:3000".into()));
+        assert!(q.forge_matches("https://git.example.com:3000/"));
+        assert!(!q.forge_matches("https://git.example.com/"));
+
+        // unknown fields and invalid values are free text
+        let q = SearchQuery::parse("license:mit archived:maybe topic: *spi*");
+        assert_eq!(
+            q.terms,
+            vec!["license", "mit", "archived", "maybe", "topic", "spi"]
+        );
+        assert!(q.topics.is_empty());
+        assert!(q.archived.is_none());
+
+        // filters are looked up in mini-indexes too
+        let q = SearchQuery::parse("spider topic:rust forge:codeberg.org owner:alice lang:go");
+        assert_eq!(
+            q.mini_index_words(),
+            vec!["spider", "rust", "alice", "codeberg", "org"]
+        );
+        assert!(SearchQuery::parse("lang:go archived:true")
+            .mini_index_words()
+            .is_empty());
+
+        assert!(SearchQuery::parse(" * ").is_empty());
+        assert!(!SearchQuery::parse("archived:true").is_empty());
+    }
+}
@@ -16,6 +16,8 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 //! Test utilities
+use std::str::FromStr;
+
 use crate::prelude::*;
 
 /// adding forge works
@@ -42,6 +44,178 @@ pub async fn adding_forge_works<'a, T: SCDatabase>(
 
         assert_eq!(forge.forge_type, create_forge_msg.forge_type);
         assert_eq!(forge.url, crate::clean_url(&create_forge_msg.url));
+        assert_eq!(forge.last_crawl_on, None);
+        assert_eq!(forge.last_full_crawl_on, None);
+    }
+
+    // record crawls
+    db.record_forge_crawl(&create_forge_msg.url, 100, true)
+        .await
+        .unwrap();
+    db.record_forge_crawl(&create_forge_msg.url, 200, false)
+        .await
+        .unwrap();
+    {
+        let forge = db.get_forge(&create_forge_msg.url).await.unwrap();
+        assert_eq!(forge.last_crawl_on, Some(200));
+        assert_eq!(forge.last_full_crawl_on, Some(100));
+    }
+
+    // persist rate limit state
+    assert_eq!(
+        db.get_forge_rate_limit(&create_forge_msg.url)
+            .await
+            .unwrap(),
+        None
+    );
+    let rate_limit = ForgeRateLimit {
+        delay: 4000,
+        blocked_until: Some(300),
+    };
+    db.set_forge_rate_limit(&create_forge_msg.url, &rate_limit)
+        .await
+        .unwrap();
+    assert_eq!(
+        db.get_forge_rate_limit(&create_forge_msg.url)
+            .await
+            .unwrap(),
+        Some(rate_limit)
+    );
+
+    // persist crawl progress
+    assert_eq!(
+        db.get_crawl_checkpoint(&create_forge_msg.url)
+            .await
+            .unwrap(),
+        None
+    );
+    let checkpoint = CrawlCheckpoint {
+        token: "3".into(),
+        page: 3,
+        started_on: 400,
+        since: Some(200),
+    };
+    db.set_crawl_checkpoint(&create_forge_msg.url, &checkpoint)
+        .await
+        .unwrap();
+    assert_eq!(
+        db.get_crawl_checkpoint(&create_forge_msg.url)
+            .await
+            .unwrap(),
+        Some(checkpoint)
+    );
+    db.rm_crawl_checkpoint(&create_forge_msg.url).await.unwrap();
+    assert_eq!(
+        db.get_crawl_checkpoint(&create_forge_msg.url)
+            .await
+            .unwrap(),
+        None
+    );
+
+    // crawl schedule
+    if create_forge_msg.starchart_url.is_none() {
+        let url = &create_forge_msg.url;
+        let schedule = db.get_forge_crawl_schedule(url).await.unwrap().unwrap();
+        assert_eq!(schedule.url, crate::clean_url(url));
+        assert_eq!(schedule.priority, 0);
+        assert_eq!(schedule.interval, None);
+        assert_eq!(schedule.last_outcome, None);
+        assert_eq!(schedule.consecutive_failures, 0);
+        assert!(!schedule.disabled);
+        assert!(db
+            .get_due_forge_crawls(i64::MAX, 100)
+            .await
+            .unwrap()
+            .iter()
+            .any(|s| s.url == schedule.url));
+
+        db.schedule_forge_crawl(url, 10, 5).await.unwrap();
+        db.set_forge_crawl_interval(url, Some(60)).await.unwrap();
+        let schedule = db.get_forge_crawl_schedule(url).await.unwrap().unwrap();
+        assert_eq!(schedule.next_crawl_on, 10);
+        assert_eq!(schedule.priority, 5);
+        assert_eq!(schedule.interval, Some(60));
+        assert!(!db
+            .get_due_forge_crawls(9, 100)
+            .await
+            .unwrap()
+            .iter()
+            .any(|s| s.url == schedule.url));
+        assert_eq!(
+            db.get_due_forge_crawls(10, 100).await.unwrap().first(),
+            Some(&schedule)
+        );
+
+        let crawl = FinishedCrawl {
+            started_on: 20,
+            finished_on: 30,
+            error: Some("forge is down"),
+            stats: CrawlStats {
+                pages: 2,
+                repositories_added: 3,
+                repositories_updated: 4,
+                repositories_removed: 5,
+                users_added: 6,
+                users_updated: 7,
+                users_removed: 8,
+            },
+            next_crawl_on: 90,
+        };
+        db.record_forge_crawl_run(url, &crawl).await.unwrap();
+        let schedule = db.get_forge_crawl_schedule(url).await.unwrap().unwrap();
+        assert_eq!(schedule.next_crawl_on, 90);
+        assert_eq!(schedule.priority, 0);
+        assert_eq!(schedule.interval, Some(60));
+        assert_eq!(schedule.last_started_on, Some(20));
+        assert_eq!(schedule.last_finished_on, Some(30));
+        assert_eq!(
+            schedule.last_outcome,
+            Some(CrawlOutcome::Failed("forge is down".into()))
+        );
+        assert_eq!(schedule.consecutive_failures, 1);
+        db.record_forge_crawl_run(url, &crawl).await.unwrap();
+        let schedule = db.get_forge_crawl_schedule(url).await.unwrap().unwrap();
+        assert_eq!(schedule.consecutive_failures, 2);
+
+        // disabled forges aren't due
+        db.set_forge_crawl_disabled(url, true).await.unwrap();
+        let schedule = db.get_forge_crawl_schedule(url).await.unwrap().unwrap();
+        assert!(schedule.disabled);
+        assert!(!db
+            .get_due_forge_crawls(i64::MAX, 100)
+            .await
+            .unwrap()
+            .iter()
+            .any(|s| s.url == schedule.url));
+        db.schedule_forge_crawl(url, 90, 0).await.unwrap();
+        let schedule = db.get_forge_crawl_schedule(url).await.unwrap().unwrap();
+        assert!(!schedule.disabled);
+        assert_eq!(schedule.consecutive_failures, 0);
+
+        db.record_forge_crawl_run(
+            url,
+            &FinishedCrawl {
+                error: None,
+                ..crawl.clone()
+            },
+        )
+        .await
+        .unwrap();
+        let schedule = db.get_forge_crawl_schedule(url).await.unwrap().unwrap();
+        assert_eq!(schedule.last_outcome, Some(CrawlOutcome::Success));
+
+        // crawl history
+        let runs = db.get_forge_crawl_runs(url, 0, 10).await.unwrap();
+        assert_eq!(runs.len(), 3);
+        assert_eq!(runs[0].outcome, CrawlOutcome::Success);
+        assert_eq!(
+            runs[1].outcome,
+            CrawlOutcome::Failed("forge is down".into())
+        );
+        assert_eq!(runs[0].started_on, crawl.started_on);
+        assert_eq!(runs[0].finished_on, crawl.finished_on);
+        assert_eq!(runs[0].stats, crawl.stats);
+        assert_eq!(db.get_forge_crawl_runs(url, 2, 10).await.unwrap().len(), 1);
     }
 
     // add user
@@ -71,6 +245,23 @@ pub async fn adding_forge_works<'a, T: SCDatabase>(
         .await
         .unwrap());
 
+    // update user
+    {
+        let mut update_user_msg = add_user_msg.clone();
+        update_user_msg.html_link = "https://updated.example.org/user";
+        update_user_msg.profile_photo = Some("https://updated.example.org/user.png");
+        db.update_user(&update_user_msg).await.unwrap();
+        let db_user = db
+            .get_user(add_user_msg.username, &add_user_msg.url)
+            .await
+            .unwrap();
+        assert_eq!(db_user.html_link, update_user_msg.html_link);
+        assert_eq!(
+            db_user.profile_photo,
+            update_user_msg.profile_photo.map(|s| s.to_owned())
+        );
+    }
+
     // add repository
     db.create_repository(&add_repo_msg).await.unwrap();
     // verify repo exists
@@ -89,12 +280,72 @@ pub async fn adding_forge_works<'a, T: SCDatabase>(
         .await
         .unwrap());
 
-    assert!(!db.get_all_repositories(00, 1000).await.unwrap().is_empty());
-    let repo_search = db.search_repository(add_repo_msg.name).await.unwrap();
+    {
+        let repos = db.get_all_repositories(00, 1000).await.unwrap();
+        let repo = repos
+            .iter()
+            .find(|r| r.html_url == add_repo_msg.html_link)
+            .unwrap();
+        assert_eq!(repo.metadata, add_repo_msg.metadata);
+    }
+    let repo_search = db
+        .search_repository(add_repo_msg.name, 0, 100)
+        .await
+        .unwrap();
 
     assert!(!repo_search.is_empty());
     assert_eq!(repo_search.first().unwrap().url, add_repo_msg.url.as_str());
 
+    // update repository
+    {
+        let mut update_repo_msg = add_repo_msg.clone();
+        update_repo_msg.description = Some("updated description");
+        update_repo_msg.tags = Some(vec!["updated-topic"]);
+        db.update_repository(&update_repo_msg).await.unwrap();
+        let repos = db.get_all_repositories(0, 1000).await.unwrap();
+        let repo = repos
+            .iter()
+            .find(|r| r.html_url == update_repo_msg.html_link)
+            .unwrap();
+        assert_eq!(repo.description.as_deref(), update_repo_msg.description);
+        assert_eq!(repo.tags, Some(vec!["updated-topic".to_owned()]));
+        assert!(db
+            .is_word_mini_indexed(update_repo_msg.description.unwrap())
+            .await
+            .unwrap());
+        assert_eq!(
+            db.search_repository(add_repo_msg.name, 0, 100)
+                .await
+                .unwrap()
+                .len(),
+            repo_search.len()
+        );
+    }
+
+    // records not seen by crawls since a point in time
+    assert!(db
+        .get_unseen_repositories(&add_repo_msg.url, 0)
+        .await
+        .unwrap()
+        .is_empty());
+    assert!(db
+        .get_unseen_repositories(&add_repo_msg.url, i64::MAX)
+        .await
+        .unwrap()
+        .iter()
+        .any(|r| r.name == add_repo_msg.name && r.username == add_repo_msg.owner));
+    assert!(db
+        .get_unseen_users(&add_user_msg.url, 0)
+        .await
+        .unwrap()
+        .is_empty());
+    assert!(db
+        .get_unseen_users(&add_user_msg.url, i64::MAX)
+        .await
+        .unwrap()
+        .iter()
+        .any(|u| u.username == add_user_msg.username));
+
     // delete repository
     db.delete_repository(add_repo_msg.owner, add_repo_msg.name, &add_repo_msg.url)
         .await
@@ -103,6 +354,11 @@ pub async fn adding_forge_works<'a, T: SCDatabase>(
         .repository_exists(add_repo_msg.name, add_repo_msg.owner, &add_repo_msg.url)
         .await
         .unwrap());
+    assert!(db
+        .search_repository(add_repo_msg.name, 0, 100)
+        .await
+        .unwrap()
+        .is_empty());
 
     // delete user
     db.delete_user(add_user_msg.username, &add_user_msg.url)
@@ -116,11 +372,18 @@ pub async fn adding_forge_works<'a, T: SCDatabase>(
 
 /// test if all forge type implementations are loaded into DB
 pub async fn forge_type_exists_helper<T: SCDatabase>(db: &T) {
-    //for f in [ForgeImplementation::Gitea].iter() {
-    //let f = For
-    let f = ForgeImplementation::Gitea;
-    println!("Testing forge implementation exists for: {}", f.to_str());
-    assert!(db.forge_type_exists(&f).await.unwrap());
+    for f in [
+        ForgeImplementation::Gitea,
+        ForgeImplementation::GitLab,
+        ForgeImplementation::Forgejo,
+        ForgeImplementation::SourceHut,
+    ]
+    .iter()
+    {
+        println!("Testing forge implementation exists for: {}", f.to_str());
+        assert_eq!(&ForgeImplementation::from_str(f.to_str()).unwrap(), f);
+        assert!(db.forge_type_exists(f).await.unwrap());
+    }
 }
 
 /// test if all instance introducer methods work
@@ -168,3 +431,611 @@ pub async fn mini_index_helper<T: SCDatabase>(db: &T) {
     let mini_index = db.export_mini_index().await.unwrap();
     assert!(mini_index.contains(expected_mini_index));
 }
+
+/// test if all DNS challenge methods work
+pub async fn dns_challenge_helper<T: SCDatabase>(db: &T, url: &Url) {
+    let key = format!("starchart-dns-challenge-helper.{}", url.host_str().unwrap());
+    let msg = CreateDNSChallenge {
+        url,
+        key: &key,
+        value: "dns_challenge_helper value",
+        created: 100,
+        expires_on: 200,
+    };
+
+    let _ = db.delete_dns_challenge(&key).await;
+    assert!(!db.dns_challenge_exists(&key).await.unwrap());
+    db.create_dns_challenge(&msg).await.unwrap();
+    assert!(db.dns_challenge_exists(&key).await.unwrap());
+    // duplicates violate several unique columns, backends report the first one they check
+    assert!(matches!(
+        db.create_dns_challenge(&msg).await,
+        Err(DBError::DuplicateChallengeHostname | DBError::DuplicateChallengeText)
+    ));
+
+    let challenge = db.get_dns_challenge(&key).await.unwrap();
+    assert_eq!(
+        challenge,
+        DNSChallenge {
+            url: crate::clean_url(url),
+            key: key.clone(),
+            value: msg.value.into(),
+            created: msg.created,
+            expires_on: msg.expires_on,
+        }
+    );
+
+    db.delete_dns_challenge(&key).await.unwrap();
+    assert!(!db.dns_challenge_exists(&key).await.unwrap());
+
+    // expire
+    db.create_dns_challenge(&msg).await.unwrap();
+    db.rm_expired_dns_challenges(msg.expires_on - 1)
+        .await
+        .unwrap();
+    assert!(db.dns_challenge_exists(&key).await.unwrap());
+    db.rm_expired_dns_challenges(msg.expires_on).await.unwrap();
+    assert!(!db.dns_challenge_exists(&key).await.unwrap());
+}
+
+/// create a forge that is crawled by this instance, removing leftovers of earlier runs
+async fn fresh_forge<T: SCDatabase>(db: &T, url: &Url) {
+    let _ = db.delete_forge_instance(url).await;
+    let msg = CreateForge {
+        url: url.clone(),
+        forge_type: ForgeImplementation::Gitea,
+        starchart_url: None,
+    };
+    db.create_forge_instance(&msg).await.unwrap();
+}
+
+async fn add_test_user<T: SCDatabase>(db: &T, url: &Url, username: &str, import: bool) {
+    let html_link = format!("{}{username}", crate::clean_url(url));
+    let msg = AddUser {
+        url: url.clone(),
+        username,
+        html_link: &html_link,
+        profile_photo: None,
+        import,
+    };
+    db.add_user(&msg).await.unwrap();
+}
+
+async fn add_test_repository<T: SCDatabase>(
+    db: &T,
+    url: &Url,
+    owner: &str,
+    name: &str,
+    tags: &[&str],
+    import: bool,
+) {
+    let html_link = format!("{}{owner}/{name}", crate::clean_url(url));
+    let msg = AddRepository {
+        html_link: &html_link,
+        tags: if tags.is_empty() {
+            None
+        } else {
+            Some(tags.to_vec())
+        },
+        url: url.clone(),
+        name,
+        owner,
+        description: None,
+        website: None,
+        import,
+        metadata: RepositoryMetadata::default(),
+    };
+    db.create_repository(&msg).await.unwrap();
+}
+
+/// test if listings respect offset and limit
+pub async fn pagination_helper<T: SCDatabase>(db: &T, url: &Url) {
+    const OWNER: &str = "paginationhelperowner";
+    const REPOS: [&str; 3] = [
+        "paginationhelperone",
+        "paginationhelpertwo",
+        "paginationhelperthree",
+    ];
+
+    fresh_forge(db, url).await;
+    add_test_user(db, url, OWNER, false).await;
+    for name in REPOS.iter() {
+        add_test_repository(db, url, OWNER, name, &[], false).await;
+    }
+    let hostname = crate::clean_url(url);
+
+    // repositories are listed in the order they were added
+    assert_eq!(db.get_all_repositories(0, 2).await.unwrap().len(), 2);
+    let repos = db.get_all_repositories(0, 1000).await.unwrap();
+    let names: Vec<&str> = repos
+        .iter()
+        .filter(|r| r.url == hostname)
+        .map(|r| r.name.as_str())
+        .collect();
+    assert_eq!(names, REPOS);
+    assert!(db.get_all_repositories(0, 0).await.unwrap().is_empty());
+    assert!(db
+        .get_all_repositories(u32::MAX, 10)
+        .await
+        .unwrap()
+        .is_empty());
+
+    assert_eq!(db.get_all_forges(true, 0, 1).await.unwrap().len(), 1);
+    assert!(db.get_all_forges(true, 0, 0).await.unwrap().is_empty());
+    assert!(db
+        .get_all_forges(true, u32::MAX, 10)
+        .await
+        .unwrap()
+        .is_empty());
+    assert!(db
+        .get_forge_crawl_runs(url, u32::MAX, 10)
+        .await
+        .unwrap()
+        .is_empty());
+
+    // makes sure that there's at least one introduced instance to list
+    db.add_starchart_to_introducer(url).await.unwrap();
+    assert_eq!(
+        db.get_all_introduced_starchart_instances(0, 1)
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+    assert!(db
+        .get_all_introduced_starchart_instances(0, 0)
+        .await
+        .unwrap()
+        .is_empty());
+    assert!(db
+        .get_all_introduced_starchart_instances(u32::MAX, 10)
+        .await
+        .unwrap()
+        .is_empty());
+
+    db.delete_forge_instance(url).await.unwrap();
+}
+
+/// test if forges, users and repositories imported from other Starchart instances are
+/// recorded as such
+pub async fn imports_helper<T: SCDatabase>(db: &T, instance_url: &Url, url: &Url) {
+    const OWNER: &str = "importshelperowner";
+    const REPO: &str = "importshelperrepo";
+
+    let _ = db.delete_forge_instance(url).await;
+    db.add_starchart_to_introducer(instance_url).await.unwrap();
+    let msg = CreateForge {
+        url: url.clone(),
+        forge_type: ForgeImplementation::Gitea,
+        starchart_url: Some(instance_url.as_str()),
+    };
+    db.create_forge_instance(&msg).await.unwrap();
+    // importing a forge that is already known is a no-op
+    db.create_forge_instance(&msg).await.unwrap();
+
+    let hostname = crate::clean_url(url);
+    let forge = db.get_forge(url).await.unwrap();
+    assert_eq!(forge.starchart_url.as_deref(), Some(instance_url.as_str()));
+    // imported forges are crawled by the instance they were imported from
+    assert_eq!(db.get_forge_crawl_schedule(url).await.unwrap(), None);
+    assert!(db
+        .get_all_forges(true, 0, 1000)
+        .await
+        .unwrap()
+        .iter()
+        .any(|f| f.url == hostname));
+    assert!(!db
+        .get_all_forges(false, 0, 1000)
+        .await
+        .unwrap()
+        .iter()
+        .any(|f| f.url == hostname));
+
+    add_test_user(db, url, OWNER, true).await;
+    add_test_repository(db, url, OWNER, REPO, &[], true).await;
+    assert!(db.get_user(OWNER, url).await.unwrap().import);
+    assert!(
+        db.get_all_repositories(0, 1000)
+            .await
+            .unwrap()
+            .iter()
+            .find(|r| r.url == hostname && r.name == REPO)
+            .unwrap()
+            .import
+    );
+    // imported records are never stale, they are updated by imports
+    assert!(db.get_unseen_users(url, i64::MAX).await.unwrap().is_empty());
+    assert!(db
+        .get_unseen_repositories(url, i64::MAX)
+        .await
+        .unwrap()
+        .is_empty());
+
+    db.delete_forge_instance(url).await.unwrap();
+}
+
+/// test if deleting a forge or an user deletes everything that belongs to it
+pub async fn delete_cascade_helper<T: SCDatabase>(db: &T, url: &Url) {
+    const OWNER: &str = "deletecascadehelperowner";
+    const OWNER2: &str = "deletecascadehelperowner2";
+    const REPO: &str = "deletecascadehelperrepo";
+    const REPO2: &str = "deletecascadehelperrepo2";
+    const TOPIC: &str = "deletecascadehelpertopic";
+
+    fresh_forge(db, url).await;
+    add_test_user(db, url, OWNER, false).await;
+    add_test_user(db, url, OWNER2, false).await;
+    add_test_repository(db, url, OWNER, REPO, &[TOPIC], false).await;
+    add_test_repository(db, url, OWNER2, REPO2, &[TOPIC], false).await;
+    let hostname = crate::clean_url(url);
+
+    // user
+    db.delete_user(OWNER2, url).await.unwrap();
+    assert!(!db.user_exists(OWNER2, Some(url)).await.unwrap());
+    assert!(!db.repository_exists(REPO2, OWNER2, url).await.unwrap());
+    assert!(db
+        .search_repository(REPO2, 0, 100)
+        .await
+        .unwrap()
+        .is_empty());
+    assert!(db.repository_exists(REPO, OWNER, url).await.unwrap());
+
+    // repository topics
+    db.delete_repository(OWNER, REPO, url).await.unwrap();
+    add_test_repository(db, url, OWNER, REPO, &[], false).await;
+    let repo = db
+        .get_all_repositories(0, 1000)
+        .await
+        .unwrap()
+        .drain(0..)
+        .find(|r| r.url == hostname && r.name == REPO)
+        .unwrap();
+    assert_eq!(repo.tags, None);
+
+    // forge
+    db.delete_forge_instance(url).await.unwrap();
+    assert!(!db.forge_exists(url).await.unwrap());
+    assert!(!db.user_exists(OWNER, Some(url)).await.unwrap());
+    assert!(!db.repository_exists(REPO, OWNER, url).await.unwrap());
+    assert!(!db
+        .get_all_repositories(0, 1000)
+        .await
+        .unwrap()
+        .iter()
+        .any(|r| r.url == hostname));
+    assert!(db.search_repository(REPO, 0, 100).await.unwrap().is_empty());
+    assert_eq!(db.get_forge_crawl_schedule(url).await.unwrap(), None);
+    assert!(db
+        .get_forge_crawl_runs(url, 0, 10)
+        .await
+        .unwrap()
+        .is_empty());
+    assert!(!db.is_word_mini_indexed(&hostname).await.unwrap());
+
+    // nothing is left behind to clash with a forge that is added again
+    fresh_forge(db, url).await;
+    add_test_user(db, url, OWNER, false).await;
+    add_test_repository(db, url, OWNER, REPO, &[TOPIC], false).await;
+    assert!(db.repository_exists(REPO, OWNER, url).await.unwrap());
+    db.delete_forge_instance(url).await.unwrap();
+}
+
+/// test if unique constraints are reported with the matching [DBError] variant
+pub async fn duplicate_errors_helper<T: SCDatabase>(db: &T, url: &Url) {
+    const OWNER: &str = "duplicateerrorshelperowner";
+    const OWNER2: &str = "duplicateerrorshelperowner2";
+    const REPO: &str = "duplicateerrorshelperrepo";
+    const REPO2: &str = "duplicateerrorshelperrepo2";
+
+    fresh_forge(db, url).await;
+    let msg = CreateForge {
+        url: url.clone(),
+        forge_type: ForgeImplementation::Gitea,
+        starchart_url: None,
+    };
+    assert!(matches!(
+        db.create_forge_instance(&msg).await,
+        Err(DBError::DuplicateHostname)
+    ));
+
+    add_test_user(db, url, OWNER, false).await;
+    let user_link = format!("{}{OWNER}", crate::clean_url(url));
+    let msg = AddUser {
+        url: url.clone(),
+        username: OWNER2,
+        html_link: &user_link,
+        profile_photo: None,
+        import: false,
+    };
+    assert!(matches!(
+        db.add_user(&msg).await,
+        Err(DBError::DuplicateUserLink)
+    ));
+
+    add_test_repository(db, url, OWNER, REPO, &[], false).await;
+    let repo_link = format!("{}{OWNER}/{REPO}", crate::clean_url(url));
+    let msg = AddRepository {
+        html_link: &repo_link,
+        tags: None,
+        url: url.clone(),
+        name: REPO2,
+        owner: OWNER,
+        description: None,
+        website: None,
+        import: false,
+        metadata: RepositoryMetadata::default(),
+    };
+    assert!(matches!(
+        db.create_repository(&msg).await,
+        Err(DBError::DuplicateRepositoryLink)
+    ));
+
+    let host = url.host_str().unwrap();
+    let key = format!("starchart-duplicate-errors-helper.{host}");
+    let msg = CreateDNSChallenge {
+        url,
+        key: &key,
+        value: "duplicate_errors_helper value",
+        created: 100,
+        // outlives the challenges that other helpers expire, they can share the database
+        expires_on: i64::MAX,
+    };
+    let other_url = Url::parse(&format!("https://other.{host}")).unwrap();
+    let other_key = format!("starchart-duplicate-errors-helper.other.{host}");
+    let _ = db.delete_dns_challenge(&key).await;
+    let _ = db.delete_dns_challenge(&other_key).await;
+    db.create_dns_challenge(&msg).await.unwrap();
+    let other_msg = CreateDNSChallenge {
+        url: &other_url,
+        key: &other_key,
+        ..msg.clone()
+    };
+    assert!(matches!(
+        db.create_dns_challenge(&other_msg).await,
+        Err(DBError::DuplicateChallengeText)
+    ));
+
+    db.delete_dns_challenge(&key).await.unwrap();
+    db.delete_forge_instance(url).await.unwrap();
+}
+
+/// test if repositories can be searched by every field and if results are ranked and paginated
+pub async fn search_helper<T: SCDatabase>(db: &T, url: &Url) {
+    const OWNER: &str = "searchhelperowner";
+    const REPO: &str = "searchhelperalpha";
+    const REPO2: &str = "searchhelperbeta";
+    const TOPIC: &str = "searchhelpertopic";
+
+    fresh_forge(db, url).await;
+    add_test_user(db, url, OWNER, false).await;
+    add_test_repository(db, url, OWNER, REPO, &[TOPIC], false).await;
+    let html_link = format!("{}{OWNER}/{REPO2}", crate::clean_url(url));
+    let msg = AddRepository {
+        html_link: &html_link,
+        tags: None,
+        url: url.clone(),
+        name: REPO2,
+        owner: OWNER,
+        description: Some("mentions searchhelperalpha and searchhelperdescription"),
+        website: Some("https://searchhelperwebsite.example.org"),
+        import: false,
+        metadata: RepositoryMetadata::default(),
+    };
+    db.create_repository(&msg).await.unwrap();
+
+    let search = |query: &'static str| async move {
+        db.search_repository(query, 0, 100)
+            .await
+            .unwrap()
+            .drain(0..)
+            .map(|r| r.name)
+            .collect::<Vec<String>>()
+    };
+
+    // fields
+    assert_eq!(search(OWNER).await.len(), 2);
+    assert_eq!(search(TOPIC).await, vec![REPO]);
+    assert_eq!(search("searchhelperdescription").await, vec![REPO2]);
+    assert_eq!(search("searchhelperwebsite").await, vec![REPO2]);
+    assert!(search("searchhelpernomatch").await.is_empty());
+
+    // prefixes of words match, every word must match
+    assert_eq!(search("searchhelperdesc").await, vec![REPO2]);
+    assert_eq!(search("*searchhelperbe*").await, vec![REPO2]);
+    assert_eq!(
+        search("searchhelperowner searchhelpertopic").await,
+        vec![REPO]
+    );
+    assert!(search("searchhelpertopic searchhelpernomatch")
+        .await
+        .is_empty());
+
+    // names rank above descriptions
+    assert_eq!(search(REPO).await, vec![REPO, REPO2]);
+    let repo = db
+        .search_repository(REPO, 1, 1)
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    assert_eq!(repo.name, REPO2);
+    assert_eq!(repo.username, OWNER);
+    assert_eq!(repo.url, crate::clean_url(url));
+    assert_eq!(repo.description.as_deref(), msg.description);
+    assert!(db.search_repository(REPO, 2, 1).await.unwrap().is_empty());
+
+    // updates are searchable
+    let mut update_msg = msg.clone();
+    update_msg.tags = Some(vec!["searchhelperupdated"]);
+    db.update_repository(&update_msg).await.unwrap();
+    assert_eq!(search("searchhelperupdated").await, vec![REPO2]);
+
+    db.delete_repository(OWNER, REPO, url).await.unwrap();
+    assert_eq!(search(REPO).await, vec![REPO2]);
+    assert!(search(TOPIC).await.is_empty());
+
+    db.delete_forge_instance(url).await.unwrap();
+}
+
+/// test if every filter of structured search queries works
+pub async fn query_helper<T: SCDatabase>(db: &T, url: &Url) {
+    const OWNER: &str = "queryhelperowner";
+    const OWNER2: &str = "queryhelperowner2";
+    const REPO: &str = "queryhelperalpha";
+    const REPO2: &str = "queryhelperbeta";
+    const REPO3: &str = "queryhelpergamma";
+    const TOPIC: &str = "queryhelpertopic";
+    const TOPIC2: &str = "QueryHelperTopic2";
+
+    let url2 = Url::parse(&format!("https://queryhelper.{}", url.host_str().unwrap())).unwrap();
+    fresh_forge(db, url).await;
+    fresh_forge(db, &url2).await;
+    add_test_user(db, url, OWNER, false).await;
+    add_test_user(db, &url2, OWNER2, false).await;
+
+    let repos = [
+        (url, OWNER, REPO, vec![TOPIC, TOPIC2], Some("Rust"), false),
+        (url, OWNER, REPO2, vec![TOPIC], Some("Go"), true),
+        (&url2, OWNER2, REPO3, vec![TOPIC], None, false),
+    ];
+    for (url, owner, name, tags, language, archived) in repos {
+        let html_link = format!("{}{owner}/{name}", crate::clean_url(url));
+        let msg = AddRepository {
+            html_link: &html_link,
+            tags: Some(tags),
+            url: url.clone(),
+            name,
+            owner,
+            description: None,
+            website: None,
+            import: false,
+            metadata: RepositoryMetadata {
+                language: language.map(|l| l.to_owned()),
+                archived,
+                ..Default::default()
+            },
+        };
+        db.create_repository(&msg).await.unwrap();
+    }
+
+    let search = |query: String| async move {
+        db.query_repositories(&SearchQuery::parse(&query), 0, 100)
+            .await
+            .unwrap()
+            .drain(0..)
+            .map(|r| r.name)
+            .collect::<Vec<String>>()
+    };
+
+    // filters match case-insensitively and are combined with free text
+    assert_eq!(
+        search(format!("topic:{TOPIC}")).await,
+        vec![REPO, REPO2, REPO3]
+    );
+    assert_eq!(
+        search(format!("topic:{TOPIC} topic:{}", TOPIC2.to_lowercase())).await,
+        vec![REPO]
+    );
+    assert_eq!(search(format!("topic:{TOPIC2}")).await, vec![REPO]);
+    assert_eq!(
+        search(format!("topic:{TOPIC} forge:{}", url2.host_str().unwrap())).await,
+        vec![REPO3]
+    );
+    assert_eq!(
+        search(format!("topic:{TOPIC} owner:{}", OWNER.to_uppercase())).await,
+        vec![REPO, REPO2]
+    );
+    assert_eq!(search(format!("topic:{TOPIC} lang:go")).await, vec![REPO2]);
+    assert_eq!(
+        search(format!("topic:{TOPIC} archived:false")).await,
+        vec![REPO, REPO3]
+    );
+    assert_eq!(search(format!("{REPO2} topic:{TOPIC}")).await, vec![REPO2]);
+    assert_eq!(search("queryhelper lang:rust".into()).await, vec![REPO]);
+    assert!(search("queryhelper archived:true lang:rust".into())
+        .await
+        .is_empty());
+    assert!(search(format!("{REPO3} forge:{}", url.host_str().unwrap()))
+        .await
+        .is_empty());
+
+    // pagination
+    let query = SearchQuery::parse(&format!("topic:{TOPIC}"));
+    let repo = db
+        .query_repositories(&query, 1, 1)
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    assert_eq!(repo.name, REPO2);
+    assert_eq!(repo.metadata.language.as_deref(), Some("Go"));
+    assert!(db
+        .query_repositories(&query, 3, 1)
+        .await
+        .unwrap()
+        .is_empty());
+
+    assert!(db
+        .query_repositories(&SearchQuery::default(), 0, 100)
+        .await
+        .unwrap()
+        .is_empty());
+
+    db.delete_forge_instance(url).await.unwrap();
+    db.delete_forge_instance(&url2).await.unwrap();
+}
+
+/// test if imports of Starchart instances and their mini-indexes are tracked
+pub async fn starchart_import_helper<T: SCDatabase>(db: &T, instance_url: &Url) {
+    const MINI_INDEX: &str = "starchart_import_helper old mini index uniquebx7391";
+    const NEW_MINI_INDEX: &str = "starchart_import_helper new mini index uniquecy8402";
+
+    // introducing an instance twice doesn't list it twice
+    db.add_starchart_to_introducer(instance_url).await.unwrap();
+    db.add_starchart_to_introducer(instance_url).await.unwrap();
+    assert_eq!(
+        db.get_all_introduced_starchart_instances(0, 1000)
+            .await
+            .unwrap()
+            .iter()
+            .filter(|i| i.instance_url == instance_url.as_str())
+            .count(),
+        1
+    );
+
+    db.rm_starchart_import(instance_url).await.unwrap();
+    assert!(!db.is_starchart_imported(instance_url).await.unwrap());
+    db.record_starchart_imports(instance_url).await.unwrap();
+    db.record_starchart_imports(instance_url).await.unwrap();
+    assert!(db.is_starchart_imported(instance_url).await.unwrap());
+    db.rm_starchart_import(instance_url).await.unwrap();
+    assert!(!db.is_starchart_imported(instance_url).await.unwrap());
+
+    // a fresh mini-index replaces the old one
+    db.import_mini_index(instance_url, MINI_INDEX)
+        .await
+        .unwrap();
+    assert_eq!(
+        db.search_mini_index("uniquebx7391").await.unwrap(),
+        vec![instance_url.to_string()]
+    );
+    db.import_mini_index(instance_url, NEW_MINI_INDEX)
+        .await
+        .unwrap();
+    assert!(db
+        .search_mini_index("uniquebx7391")
+        .await
+        .unwrap()
+        .is_empty());
+    assert_eq!(
+        db.search_mini_index("uniquecy8402").await.unwrap(),
+        vec![instance_url.to_string()]
+    );
+
+    db.rm_imported_mini_index(instance_url).await.unwrap();
+    assert!(db
+        .search_mini_index("uniquecy8402")
+        .await
+        .unwrap()
+        .is_empty());
+}
@@ -0,0 +1,35 @@
+/*
+ * Copyright (C) 2022  Aravinth Manivannan <realaravinth@batsense.net>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+use db_core::dev::*;
+use thiserror::Error;
+
+/// Errors specific to the in-memory database
+#[derive(Debug, Error)]
+pub enum MemoryError {
+    /// the record doesn't exist
+    #[error("no record matched the query")]
+    RowNotFound,
+    /// the record refers to a forge, user or Starchart instance that doesn't exist
+    #[error("{0} doesn't exist")]
+    MissingReference(&'static str),
+}
+
+impl From<MemoryError> for DBError {
+    fn from(e: MemoryError) -> Self {
+        DBError::DBError(Box::new(e))
+    }
+}
@@ -0,0 +1,1019 @@
+/*
+ * ForgeFlux StarChart - A federated software forge spider
+ * Copyright (C) 2022  Aravinth Manivannan <realaravinth@batsense.net>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+//! In-memory implementation of [SCDatabase], for tests, demos and mirror-only nodes that don't
+//! need to persist anything. All data is lost when the last clone of [Database] is dropped.
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use db_core::dev::*;
+use url::Url;
+
+pub mod errors;
+#[cfg(test)]
+pub mod tests;
+
+use errors::*;
+
+/// In-memory database. Clones share the same data
+#[derive(Clone, Default)]
+pub struct Database {
+    store: Arc<RwLock<Store>>,
+}
+
+impl Database {
+    /// create an empty database
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl Migrate for Database {
+    /// nothing to migrate, the schema is defined by [Store]
+    async fn migrate(&self) -> DBResult<()> {
+        Ok(())
+    }
+}
+
+struct ForgeRecord {
+    id: u64,
+    forge_type: ForgeImplementation,
+    starchart_url: Option<String>,
+    last_crawl_on: Option<i64>,
+    last_full_crawl_on: Option<i64>,
+    rate_limit: Option<ForgeRateLimit>,
+    checkpoint: Option<CrawlCheckpoint>,
+}
+
+struct ScheduleRecord {
+    id: u64,
+    schedule: CrawlSchedule,
+}
+
+struct CrawlRunRecord {
+    hostname: String,
+    run: CrawlRun,
+}
+
+struct UserRecord {
+    hostname: String,
+    username: String,
+    html_url: String,
+    profile_photo: Option<String>,
+    last_crawl_on: i64,
+    import: bool,
+}
+
+struct RepositoryRecord {
+    hostname: String,
+    owner_id: u64,
+    name: String,
+    description: Option<String>,
+    website: Option<String>,
+    html_url: String,
+    last_crawl: i64,
+    import: bool,
+    metadata: RepositoryMetadata,
+    topics: Vec<String>,
+}
+
+/// Tables of the in-memory database. IDs are handed out in insertion order, so that listings
+/// are ordered the same way as in the SQL implementations
+#[derive(Default)]
+struct Store {
+    next_id: u64,
+    /// keyed by hostname
+    forges: HashMap<String, ForgeRecord>,
+    /// keyed by hostname
+    schedules: HashMap<String, ScheduleRecord>,
+    crawl_runs: BTreeMap<u64, CrawlRunRecord>,
+    /// keyed by key of the TXT record
+    dns_challenges: HashMap<String, DNSChallenge>,
+    users: BTreeMap<u64, UserRecord>,
+    repositories: BTreeMap<u64, RepositoryRecord>,
+    introducer: Vec<String>,
+    mini_index: Vec<String>,
+    /// keyed by URL of the Starchart instance
+    federated_mini_index: HashMap<String, String>,
+    imported_starcharts: HashSet<String>,
+}
+
+impl Store {
+    fn next_id(&mut self) -> u64 {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    fn find_user(&self, username: &str, hostname: &str) -> Option<u64> {
+        self.users
+            .iter()
+            .find(|(_, u)| u.username == username && u.hostname == hostname)
+            .map(|(id, _)| *id)
+    }
+
+    fn find_repository(&self, name: &str, owner: &str, hostname: &str) -> Option<u64> {
+        let owner_id = self.find_user(owner, hostname)?;
+        self.repositories
+            .iter()
+            .find(|(_, r)| r.name == name && r.owner_id == owner_id && r.hostname == hostname)
+            .map(|(id, _)| *id)
+    }
+
+    fn add_word_to_mini_index(&mut self, word: &str) {
+        if !self.mini_index.iter().any(|w| w == word) {
+            self.mini_index.push(word.to_owned());
+        }
+    }
+
+    fn rm_word_from_mini_index(&mut self, word: &str) {
+        self.mini_index.retain(|w| w != word);
+    }
+
+    fn add_repository_words(&mut self, r: &AddRepository) {
+        if let Some(description) = r.description {
+            self.add_word_to_mini_index(description);
+        }
+        self.add_word_to_mini_index(r.name);
+        if let Some(website) = r.website {
+            self.add_word_to_mini_index(website);
+        }
+        if let Some(topics) = &r.tags {
+            topics
+                .iter()
+                .for_each(|topic| self.add_word_to_mini_index(topic));
+        }
+    }
+
+    fn delete_user(&mut self, id: u64) {
+        self.users.remove(&id);
+        self.repositories.retain(|_, r| r.owner_id != id);
+    }
+
+    fn repository(&self, r: &RepositoryRecord) -> Repository {
+        Repository {
+            html_url: r.html_url.clone(),
+            tags: if r.topics.is_empty() {
+                None
+            } else {
+                Some(r.topics.clone())
+            },
+            url: r.hostname.clone(),
+            name: r.name.clone(),
+            username: self.users[&r.owner_id].username.clone(),
+            description: r.description.clone(),
+            website: r.website.clone(),
+            import: r.import,
+            metadata: r.metadata.clone(),
+        }
+    }
+
+    fn user(u: &UserRecord) -> User {
+        User {
+            url: u.hostname.clone(),
+            username: u.username.clone(),
+            html_link: u.html_url.clone(),
+            profile_photo: u.profile_photo.clone(),
+            import: u.import,
+        }
+    }
+}
+
+/// Paginate records that are already in listing order
+fn paginate<T>(items: impl Iterator<Item = T>, offset: u32, limit: u32) -> Vec<T> {
+    items.skip(offset as usize).take(limit as usize).collect()
+}
+
+#[async_trait]
+impl SCDatabase for Database {
+    /// ping DB
+    async fn ping(&self) -> bool {
+        self.store.read().is_ok()
+    }
+
+    /// delete forge instance
+    async fn delete_forge_instance(&self, url: &Url) -> DBResult<()> {
+        let url = db_core::clean_url(url);
+        let mut store = self.store.write().unwrap();
+        store.forges.remove(&url);
+        let users: Vec<u64> = store
+            .users
+            .iter()
+            .filter(|(_, u)| u.hostname == url)
+            .map(|(id, _)| *id)
+            .collect();
+        users.iter().for_each(|id| store.delete_user(*id));
+        store.schedules.remove(&url);
+        store.crawl_runs.retain(|_, r| r.hostname != url);
+        store.rm_word_from_mini_index(&url);
+        Ok(())
+    }
+
+    /// create forge instance DB
+    async fn create_forge_instance(&self, f: &CreateForge) -> DBResult<()> {
+        let now = now_unix_time_stamp();
+        let url = db_core::clean_url(&f.url);
+        let mut store = self.store.write().unwrap();
+        if store.forges.contains_key(&url) {
+            if f.starchart_url.is_some() {
+                // imports of forges that are already known are ignored
+                return Ok(());
+            }
+            return Err(DBError::DuplicateHostname);
+        }
+
+        let starchart_url = f.starchart_url.and_then(|instance_url| {
+            store
+                .introducer
+                .iter()
+                .find(|i| *i == instance_url)
+                .cloned()
+        });
+        let id = store.next_id();
+        store.forges.insert(
+            url.clone(),
+            ForgeRecord {
+                id,
+                forge_type: f.forge_type.clone(),
+                starchart_url,
+                last_crawl_on: None,
+                last_full_crawl_on: None,
+                rate_limit: None,
+                checkpoint: None,
+            },
+        );
+
+        // forges that aren't imported are crawled by this instance
+        if f.starchart_url.is_none() && !store.schedules.contains_key(&url) {
+            let id = store.next_id();
+            store.schedules.insert(
+                url.clone(),
+                ScheduleRecord {
+                    id,
+                    schedule: CrawlSchedule {
+                        url: url.clone(),
+                        next_crawl_on: now,
+                        priority: 0,
+                        interval: None,
+                        last_started_on: None,
+                        last_finished_on: None,
+                        last_outcome: None,
+                        consecutive_failures: 0,
+                        disabled: false,
+                    },
+                },
+            );
+        }
+
+        store.add_word_to_mini_index(&url);
+        Ok(())
+    }
+
+    /// get forge instance data
+    async fn get_forge(&self, url: &Url) -> DBResult<Forge> {
+        let url = db_core::clean_url(url);
+        let store = self.store.read().unwrap();
+        let f = store.forges.get(&url).ok_or(MemoryError::RowNotFound)?;
+        Ok(Forge {
+            starchart_url: f.starchart_url.clone(),
+            url,
+            forge_type: f.forge_type.clone(),
+            last_crawl_on: f.last_crawl_on,
+            last_full_crawl_on: f.last_full_crawl_on,
+        })
+    }
+
+    /// Record completed crawl of a forge
+    async fn record_forge_crawl(&self, url: &Url, crawled_on: i64, full: bool) -> DBResult<()> {
+        let url = db_core::clean_url(url);
+        let mut store = self.store.write().unwrap();
+        if let Some(f) = store.forges.get_mut(&url) {
+            f.last_crawl_on = Some(crawled_on);
+            if full {
+                f.last_full_crawl_on = Some(crawled_on);
+            }
+        }
+        Ok(())
+    }
+
+    /// Get persisted rate limit state of a forge, if any
+    async fn get_forge_rate_limit(&self, url: &Url) -> DBResult<Option<ForgeRateLimit>> {
+        let url = db_core::clean_url(url);
+        let store = self.store.read().unwrap();
+        let f = store.forges.get(&url).ok_or(MemoryError::RowNotFound)?;
+        Ok(f.rate_limit.clone())
+    }
+
+    /// Persist rate limit state of a forge
+    async fn set_forge_rate_limit(&self, url: &Url, rate_limit: &ForgeRateLimit) -> DBResult<()> {
+        let url = db_core::clean_url(url);
+        let mut store = self.store.write().unwrap();
+        if let Some(f) = store.forges.get_mut(&url) {
+            f.rate_limit = Some(rate_limit.clone());
+        }
+        Ok(())
+    }
+
+    /// Get progress of the unfinished crawl of a forge, if any
+    async fn get_crawl_checkpoint(&self, url: &Url) -> DBResult<Option<CrawlCheckpoint>> {
+        let url = db_core::clean_url(url);
+        let store = self.store.read().unwrap();
+        let f = store.forges.get(&url).ok_or(MemoryError::RowNotFound)?;
+        Ok(f.checkpoint.clone())
+    }
+
+    /// Persist progress of the crawl of a forge
+    async fn set_crawl_checkpoint(&self, url: &Url, checkpoint: &CrawlCheckpoint) -> DBResult<()> {
+        let url = db_core::clean_url(url);
+        let mut store = self.store.write().unwrap();
+        if let Some(f) = store.forges.get_mut(&url) {
+            f.checkpoint = Some(checkpoint.clone());
+        }
+        Ok(())
+    }
+
+    /// Remove progress of the crawl of a forge
+    async fn rm_crawl_checkpoint(&self, url: &Url) -> DBResult<()> {
+        let url = db_core::clean_url(url);
+        let mut store = self.store.write().unwrap();
+        if let Some(f) = store.forges.get_mut(&url) {
+            f.checkpoint = None;
+        }
+        Ok(())
+    }
+
+    /// Schedule crawl of a forge
+    async fn schedule_forge_crawl(
+        &self,
+        url: &Url,
+        next_crawl_on: i64,
+        priority: u32,
+    ) -> DBResult<()> {
+        let url = db_core::clean_url(url);
+        let mut store = self.store.write().unwrap();
+        if let Some(s) = store.schedules.get_mut(&url) {
+            s.schedule.next_crawl_on = next_crawl_on;
+            s.schedule.priority = priority;
+            s.schedule.consecutive_failures = 0;
+            s.schedule.disabled = false;
+        } else {
+            let id = store.next_id();
+            store.schedules.insert(
+                url.clone(),
+                ScheduleRecord {
+                    id,
+                    schedule: CrawlSchedule {
+                        url,
+                        next_crawl_on,
+                        priority,
+                        interval: None,
+                        last_started_on: None,
+                        last_finished_on: None,
+                        last_outcome: None,
+                        consecutive_failures: 0,
+                        disabled: false,
+                    },
+                },
+            );
+        }
+        Ok(())
+    }
+
+    /// Set seconds between crawls of a scheduled forge
+    async fn set_forge_crawl_interval(&self, url: &Url, interval: Option<u64>) -> DBResult<()> {
+        let url = db_core::clean_url(url);
+        let mut store = self.store.write().unwrap();
+        let s = store
+            .schedules
+            .get_mut(&url)
+            .ok_or(MemoryError::RowNotFound)?;
+        s.schedule.interval = interval;
+        Ok(())
+    }
+
+    /// Disable or enable crawls of a scheduled forge
+    async fn set_forge_crawl_disabled(&self, url: &Url, disabled: bool) -> DBResult<()> {
+        let url = db_core::clean_url(url);
+        let mut store = self.store.write().unwrap();
+        let s = store
+            .schedules
+            .get_mut(&url)
+            .ok_or(MemoryError::RowNotFound)?;
+        s.schedule.disabled = disabled;
+        Ok(())
+    }
+
+    /// Get crawl schedule of a forge, if it is scheduled
+    async fn get_forge_crawl_schedule(&self, url: &Url) -> DBResult<Option<CrawlSchedule>> {
+        let url = db_core::clean_url(url);
+        let store = self.store.read().unwrap();
+        Ok(store.schedules.get(&url).map(|s| s.schedule.clone()))
+    }
+
+    /// Get forges that are due for a crawl
+    async fn get_due_forge_crawls(&self, now: i64, limit: u32) -> DBResult<Vec<CrawlSchedule>> {
+        let store = self.store.read().unwrap();
+        let mut due: Vec<&ScheduleRecord> = store
+            .schedules
+            .values()
+            .filter(|s| s.schedule.next_crawl_on <= now && !s.schedule.disabled)
+            .collect();
+        due.sort_by_key(|s| {
+            (
+                std::cmp::Reverse(s.schedule.priority),
+                s.schedule.next_crawl_on,
+                s.id,
+            )
+        });
+        Ok(paginate(due.iter().map(|s| s.schedule.clone()), 0, limit))
+    }
+
+    /// Record finished crawl of a scheduled forge
+    async fn record_forge_crawl_run(&self, url: &Url, crawl: &FinishedCrawl) -> DBResult<()> {
+        let url = db_core::clean_url(url);
+        let outcome = match crawl.error {
+            Some(e) => CrawlOutcome::Failed(e.to_owned()),
+            None => CrawlOutcome::Success,
+        };
+        let mut store = self.store.write().unwrap();
+        if let Some(s) = store.schedules.get_mut(&url) {
+            s.schedule.last_started_on = Some(crawl.started_on);
+            s.schedule.last_finished_on = Some(crawl.finished_on);
+            s.schedule.last_outcome = Some(outcome.clone());
+            s.schedule.next_crawl_on = crawl.next_crawl_on;
+            s.schedule.priority = 0;
+            s.schedule.consecutive_failures = if crawl.error.is_some() {
+                s.schedule.consecutive_failures + 1
+            } else {
+                0
+            };
+        }
+
+        let id = store.next_id();
+        store.crawl_runs.insert(
+            id,
+            CrawlRunRecord {
+                hostname: url,
+                run: CrawlRun {
+                    started_on: crawl.started_on,
+                    finished_on: crawl.finished_on,
+                    stats: crawl.stats.clone(),
+                    outcome,
+                },
+            },
+        );
+        Ok(())
+    }
+
+    /// Get crawl history of a forge, latest crawl first
+    async fn get_forge_crawl_runs(
+        &self,
+        url: &Url,
+        offset: u32,
+        limit: u32,
+    ) -> DBResult<Vec<CrawlRun>> {
+        let url = db_core::clean_url(url);
+        let store = self.store.read().unwrap();
+        let runs = store
+            .crawl_runs
+            .values()
+            .rev()
+            .filter(|r| r.hostname == url)
+            .map(|r| r.run.clone());
+        Ok(paginate(runs, offset, limit))
+    }
+
+    /// Get all forges
+    async fn get_all_forges(
+        &self,
+        with_imports: bool,
+        offset: u32,
+        limit: u32,
+    ) -> DBResult<Vec<Forge>> {
+        let store = self.store.read().unwrap();
+        let mut forges: Vec<(&String, &ForgeRecord)> = store
+            .forges
+            .iter()
+            .filter(|(_, f)| with_imports || f.starchart_url.is_none())
+            .collect();
+        forges.sort_by_key(|(_, f)| f.id);
+        let forges = forges.drain(0..).map(|(url, f)| Forge {
+            starchart_url: f.starchart_url.clone(),
+            url: url.clone(),
+            forge_type: f.forge_type.clone(),
+            last_crawl_on: f.last_crawl_on,
+            last_full_crawl_on: f.last_full_crawl_on,
+        });
+        Ok(paginate(forges, offset, limit))
+    }
+
+    /// check if a forge instance exists
+    async fn forge_exists(&self, url: &Url) -> DBResult<bool> {
+        let url = db_core::clean_url(url);
+        Ok(self.store.read().unwrap().forges.contains_key(&url))
+    }
+
+    async fn forge_type_exists(&self, _forge_type: &ForgeImplementation) -> DBResult<bool> {
+        // all forge types are known
+        Ok(true)
+    }
+
+    async fn create_dns_challenge(&self, c: &CreateDNSChallenge) -> DBResult<()> {
+        let url = db_core::clean_url(c.url);
+        let mut store = self.store.write().unwrap();
+        for existing in store.dns_challenges.values() {
+            if existing.url == url {
+                return Err(DBError::DuplicateChallengeHostname);
+            }
+            if existing.value == c.value {
+                return Err(DBError::DuplicateChallengeText);
+            }
+        }
+        if store.dns_challenges.contains_key(c.key) {
+            return Err(DBError::DuplicateChallengeHostname);
+        }
+        store.dns_challenges.insert(
+            c.key.to_owned(),
+            DNSChallenge {
+                url,
+                key: c.key.to_owned(),
+                value: c.value.to_owned(),
+                created: c.created,
+                expires_on: c.expires_on,
+            },
+        );
+        Ok(())
+    }
+
+    async fn dns_challenge_exists(&self, key: &str) -> DBResult<bool> {
+        Ok(self.store.read().unwrap().dns_challenges.contains_key(key))
+    }
+
+    async fn get_dns_challenge(&self, key: &str) -> DBResult<DNSChallenge> {
+        let store = self.store.read().unwrap();
+        let c = store
+            .dns_challenges
+            .get(key)
+            .ok_or(MemoryError::RowNotFound)?;
+        Ok(c.clone())
+    }
+
+    async fn delete_dns_challenge(&self, key: &str) -> DBResult<()> {
+        self.store.write().unwrap().dns_challenges.remove(key);
+        Ok(())
+    }
+
+    async fn rm_expired_dns_challenges(&self, now: i64) -> DBResult<()> {
+        self.store
+            .write()
+            .unwrap()
+            .dns_challenges
+            .retain(|_, c| c.expires_on > now);
+        Ok(())
+    }
+
+    /// add new user to database
+    async fn add_user(&self, u: &AddUser) -> DBResult<()> {
+        let now = now_unix_time_stamp();
+        let url = db_core::clean_url(&u.url);
+        let mut store = self.store.write().unwrap();
+        if !store.forges.contains_key(&url) {
+            return Err(MemoryError::MissingReference("forge").into());
+        }
+        if store
+            .users
+            .values()
+            .any(|user| user.html_url == u.html_link)
+        {
+            return Err(DBError::DuplicateUserLink);
+        }
+        let id = store.next_id();
+        store.users.insert(
+            id,
+            UserRecord {
+                hostname: url,
+                username: u.username.to_owned(),
+                html_url: u.html_link.to_owned(),
+                profile_photo: u.profile_photo.map(|p| p.to_owned()),
+                last_crawl_on: now,
+                import: u.import,
+            },
+        );
+        store.add_word_to_mini_index(u.username);
+        Ok(())
+    }
+
+    /// update data of an existing user
+    async fn update_user(&self, u: &AddUser) -> DBResult<()> {
+        let now = now_unix_time_stamp();
+        let url = db_core::clean_url(&u.url);
+        let mut store = self.store.write().unwrap();
+        let id = store
+            .find_user(u.username, &url)
+            .ok_or(MemoryError::RowNotFound)?;
+        if store
+            .users
+            .iter()
+            .any(|(other, user)| *other != id && user.html_url == u.html_link)
+        {
+            return Err(DBError::DuplicateUserLink);
+        }
+        let user = store.users.get_mut(&id).unwrap();
+        user.html_url = u.html_link.to_owned();
+        user.profile_photo = u.profile_photo.map(|p| p.to_owned());
+        user.last_crawl_on = now;
+        store.add_word_to_mini_index(u.username);
+        Ok(())
+    }
+
+    /// get user data
+    async fn get_user(&self, username: &str, url: &Url) -> DBResult<User> {
+        let url = db_core::clean_url(url);
+        let store = self.store.read().unwrap();
+        let id = store
+            .find_user(username, &url)
+            .ok_or(MemoryError::RowNotFound)?;
+        Ok(Store::user(&store.users[&id]))
+    }
+
+    /// check if an user exists. When url of a forge instace is provided, username search is
+    /// done only on that forge
+    async fn user_exists(&self, username: &str, url: Option<&Url>) -> DBResult<bool> {
+        let store = self.store.read().unwrap();
+        match url {
+            Some(url) => Ok(store
+                .find_user(username, &db_core::clean_url(url))
+                .is_some()),
+            None => Ok(store.users.values().any(|u| u.username == username)),
+        }
+    }
+
+    /// check if a repo exists.
+    async fn repository_exists(&self, name: &str, owner: &str, url: &Url) -> DBResult<bool> {
+        let url = db_core::clean_url(url);
+        let store = self.store.read().unwrap();
+        Ok(store.find_repository(name, owner, &url).is_some())
+    }
+
+    /// add new repository to database.
+    async fn create_repository(&self, r: &AddRepository) -> DBResult<()> {
+        let now = now_unix_time_stamp();
+        let url = db_core::clean_url(&r.url);
+        let mut store = self.store.write().unwrap();
+        if !store.forges.contains_key(&url) {
+            return Err(MemoryError::MissingReference("forge").into());
+        }
+        let owner_id = store
+            .find_user(r.owner, &url)
+            .ok_or(MemoryError::MissingReference("owner"))?;
+        if store
+            .repositories
+            .values()
+            .any(|repo| repo.html_url == r.html_link)
+        {
+            return Err(DBError::DuplicateRepositoryLink);
+        }
+
+        let mut topics: Vec<String> = Vec::default();
+        if let Some(tags) = &r.tags {
+            for tag in tags.iter() {
+                if !topics.iter().any(|t| t == tag) {
+                    topics.push(tag.to_string());
+                }
+            }
+        }
+        let id = store.next_id();
+        store.repositories.insert(
+            id,
+            RepositoryRecord {
+                hostname: url,
+                owner_id,
+                name: r.name.to_owned(),
+                description: r.description.map(|d| d.to_owned()),
+                website: r.website.map(|w| w.to_owned()),
+                html_url: r.html_link.to_owned(),
+                last_crawl: now,
+                import: r.import,
+                metadata: r.metadata.clone(),
+                topics,
+            },
+        );
+        store.add_repository_words(r);
+        Ok(())
+    }
+
+    /// update metadata of an existing repository
+    async fn update_repository(&self, r: &AddRepository) -> DBResult<()> {
+        let now = now_unix_time_stamp();
+        let url = db_core::clean_url(&r.url);
+        let mut store = self.store.write().unwrap();
+        let id = store
+            .find_repository(r.name, r.owner, &url)
+            .ok_or(MemoryError::RowNotFound)?;
+        if store
+            .repositories
+            .iter()
+            .any(|(other, repo)| *other != id && repo.html_url == r.html_link)
+        {
+            return Err(DBError::DuplicateRepositoryLink);
+        }
+
+        let mut topics: Vec<String> = Vec::default();
+        if let Some(tags) = &r.tags {
+            for tag in tags.iter() {
+                if !topics.iter().any(|t| t == tag) {
+                    topics.push(tag.to_string());
+                }
+            }
+        }
+        let repo = store.repositories.get_mut(&id).unwrap();
+        repo.description = r.description.map(|d| d.to_owned());
+        repo.html_url = r.html_link.to_owned();
+        repo.website = r.website.map(|w| w.to_owned());
+        repo.last_crawl = now;
+        repo.metadata = r.metadata.clone();
+        repo.topics = topics;
+        store.add_repository_words(r);
+        Ok(())
+    }
+
+    /// delete user
+    async fn delete_user(&self, username: &str, url: &Url) -> DBResult<()> {
+        let url = db_core::clean_url(url);
+        let mut store = self.store.write().unwrap();
+        let id = store
+            .find_user(username, &url)
+            .ok_or(MemoryError::RowNotFound)?;
+        store.rm_word_from_mini_index(username);
+        store.delete_user(id);
+        Ok(())
+    }
+
+    /// delete repository
+    async fn delete_repository(&self, owner: &str, name: &str, url: &Url) -> DBResult<()> {
+        let url = db_core::clean_url(url);
+        let mut store = self.store.write().unwrap();
+        if let Some(id) = store.find_repository(name, owner, &url) {
+            store.repositories.remove(&id);
+        }
+        Ok(())
+    }
+
+    /// Get repositories of a forge that weren't seen by a crawl since `seen_since`
+    async fn get_unseen_repositories(
+        &self,
+        url: &Url,
+        seen_since: i64,
+    ) -> DBResult<Vec<Repository>> {
+        let url = db_core::clean_url(url);
+        let store = self.store.read().unwrap();
+        Ok(store
+            .repositories
+            .values()
+            .filter(|r| r.hostname == url && !r.import && r.last_crawl < seen_since)
+            .map(|r| store.repository(r))
+            .collect())
+    }
+
+    /// Get users of a forge that weren't seen by a crawl since `seen_since`
+    async fn get_unseen_users(&self, url: &Url, seen_since: i64) -> DBResult<Vec<User>> {
+        let url = db_core::clean_url(url);
+        let store = self.store.read().unwrap();
+        Ok(store
+            .users
+            .values()
+            .filter(|u| u.hostname == url && !u.import && u.last_crawl_on < seen_since)
+            .map(Store::user)
+            .collect())
+    }
+
+    /// Get all repositories
+    async fn get_all_repositories(&self, offset: u32, limit: u32) -> DBResult<Vec<Repository>> {
+        let store = self.store.read().unwrap();
+        let repos = store.repositories.values().map(|r| store.repository(r));
+        Ok(paginate(repos, offset, limit))
+    }
+
+    /// Get repositories that match a search query
+    async fn query_repositories(
+        &self,
+        query: &SearchQuery,
+        offset: u32,
+        limit: u32,
+    ) -> DBResult<Vec<Repository>> {
+        if query.is_empty() {
+            return Ok(Vec::default());
+        }
+        let store = self.store.read().unwrap();
+        let mut matches: Vec<(u32, u64)> = Vec::default();
+        'repositories: for (id, r) in store.repositories.iter() {
+            let username = store.users[&r.owner_id].username.as_str();
+            let owner = username.to_lowercase();
+            let language = r.metadata.language.as_deref().map(str::to_lowercase);
+            if !query.forge_matches(&r.hostname)
+                || query.owner.as_ref().is_some_and(|o| *o != owner)
+                || query.lang.is_some() && query.lang != language
+                || query.archived.is_some_and(|a| a != r.metadata.archived)
+                || !query
+                    .topics
+                    .iter()
+                    .all(|topic| r.topics.iter().any(|t| t.to_lowercase() == *topic))
+            {
+                continue;
+            }
+
+            let topics = r.topics.join(" ");
+            // fields and their weights, like in the SQL implementations names matter the most
+            let fields = [
+                (r.name.as_str(), 10),
+                (topics.as_str(), 5),
+                (username, 3),
+                (r.description.as_deref().unwrap_or_default(), 2),
+                (r.website.as_deref().unwrap_or_default(), 1),
+            ];
+            let fields: Vec<(Vec<String>, u32)> = fields
+                .iter()
+                .map(|(text, weight)| (db_core::search_terms(text), *weight))
+                .collect();
+
+            let mut score = 0;
+            for term in query.terms.iter() {
+                let term_score: u32 = fields
+                    .iter()
+                    .filter(|(words, _)| words.iter().any(|w| w.starts_with(term.as_str())))
+                    .map(|(_, weight)| weight)
+                    .sum();
+                if term_score == 0 {
+                    continue 'repositories;
+                }
+                score += term_score;
+            }
+            matches.push((score, *id));
+        }
+
+        matches.sort_by_key(|(score, id)| (std::cmp::Reverse(*score), *id));
+        let repos = matches
+            .iter()
+            .map(|(_, id)| store.repository(&store.repositories[id]));
+        Ok(paginate(repos, offset, limit))
+    }
+
+    /// Add Starchart instance to introducer
+    async fn add_starchart_to_introducer(&self, url: &Url) -> DBResult<()> {
+        let mut store = self.store.write().unwrap();
+        if !store.introducer.iter().any(|i| i == url.as_str()) {
+            store.introducer.push(url.as_str().to_owned());
+        }
+        Ok(())
+    }
+
+    /// Get all introduced Starchart instances
+    async fn get_all_introduced_starchart_instances(
+        &self,
+        offset: u32,
+        limit: u32,
+    ) -> DBResult<Vec<Starchart>> {
+        let store = self.store.read().unwrap();
+        let instances = store.introducer.iter().map(|instance_url| Starchart {
+            instance_url: instance_url.clone(),
+        });
+        Ok(paginate(instances, offset, limit))
+    }
+
+    /// Add word to mini index
+    async fn add_word_to_mini_index(&self, word: &str) -> DBResult<()> {
+        self.store.write().unwrap().add_word_to_mini_index(word);
+        Ok(())
+    }
+
+    /// Remove word from mini index
+    async fn rm_word_from_mini_index(&self, word: &str) -> DBResult<()> {
+        self.store.write().unwrap().rm_word_from_mini_index(word);
+        Ok(())
+    }
+
+    /// Check if word exists in mini index
+    async fn is_word_mini_indexed(&self, word: &str) -> DBResult<bool> {
+        Ok(self
+            .store
+            .read()
+            .unwrap()
+            .mini_index
+            .iter()
+            .any(|w| w == word))
+    }
+
+    /// consolidate and export mini index
+    async fn export_mini_index(&self) -> DBResult<String> {
+        Ok(self.store.read().unwrap().mini_index.join(" "))
+    }
+
+    /// Import mini-index
+    async fn import_mini_index(
+        &self,
+        starchart_instance_url: &Url,
+        mini_index: &str,
+    ) -> DBResult<()> {
+        let url = db_core::clean_url(starchart_instance_url);
+        let mut store = self.store.write().unwrap();
+        if !store.introducer.contains(&url) {
+            return Err(MemoryError::MissingReference("Starchart instance").into());
+        }
+        // replaces old index
+        store
+            .federated_mini_index
+            .insert(url, mini_index.to_owned());
+        Ok(())
+    }
+
+    /// Delete imported mini-index
+    async fn rm_imported_mini_index(&self, starchart_instance_url: &Url) -> DBResult<()> {
+        let url = db_core::clean_url(starchart_instance_url);
+        self.store
+            .write()
+            .unwrap()
+            .federated_mini_index
+            .remove(&url);
+        Ok(())
+    }
+
+    /// Search mini index
+    async fn search_mini_index(&self, query: &str) -> DBResult<Vec<String>> {
+        let terms = db_core::search_terms(query);
+        if terms.is_empty() {
+            return Ok(Vec::default());
+        }
+        let store = self.store.read().unwrap();
+        Ok(store
+            .introducer
+            .iter()
+            .filter(|instance_url| {
+                store
+                    .federated_mini_index
+                    .get(*instance_url)
+                    .map(|mini_index| {
+                        let words: Vec<String> = mini_index
+                            .split_whitespace()
+                            .map(|w| w.to_lowercase())
+                            .collect();
+                        terms
+                            .iter()
+                            .all(|term| words.iter().any(|w| w.contains(term.as_str())))
+                    })
+                    .unwrap_or(false)
+            })
+            .cloned()
+            .collect())
+    }
+
+    /// Mark a Starchart instance as imported
+    async fn record_starchart_imports(&self, starchart_url: &Url) -> DBResult<()> {
+        let url = db_core::clean_url(starchart_url);
+        let mut store = self.store.write().unwrap();
+        if !store.introducer.contains(&url) {
+            return Err(MemoryError::MissingReference("Starchart instance").into());
+        }
+        store.imported_starcharts.insert(url);
+        Ok(())
+    }
+
+    /// Unmark a Starchart instance as imported
+    async fn rm_starchart_import(&self, starchart_url: &Url) -> DBResult<()> {
+        let url = db_core::clean_url(starchart_url);
+        self.store.write().unwrap().imported_starcharts.remove(&url);
+        Ok(())
+    }
+
+    /// Check if Starchart instance is imported
+    async fn is_starchart_imported(&self, starchart_url: &Url) -> DBResult<bool> {
+        let url = db_core::clean_url(starchart_url);
+        Ok(self
+            .store
+            .read()
+            .unwrap()
+            .imported_starcharts
+            .contains(&url))
+    }
+}
+
+fn now_unix_time_stamp() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64
+}
@@ -0,0 +1,178 @@
+/*
+ * Copyright (C) 2022  Aravinth Manivannan <realaravinth@batsense.net>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+use url::Url;
+
+use crate::*;
+
+use db_core::tests::*;
+
+#[actix_rt::test]
+async fn everything_works() {
+    const URL: &str = "https://test-gitea.example.com";
+    const HTML_PROFILE_URL: &str = "https://test-gitea.example.com/user1";
+    const HTML_PROFILE_PHOTO_URL_2: &str = "https://test-gitea.example.com/profile-photo/user2";
+    const USERNAME: &str = "user1";
+    const USERNAME2: &str = "user2";
+
+    const REPO_NAME: &str = "starchart";
+    const HTML_REPO_URL: &str = "https://test-gitea.example.com/user1/starchart";
+    const TAGS: [&str; 3] = ["test", "starchart", "spider"];
+
+    let url = Url::parse(URL).unwrap();
+
+    let create_forge_msg = CreateForge {
+        url: url.clone(),
+        forge_type: ForgeImplementation::Gitea,
+        starchart_url: None,
+    };
+
+    let add_user_msg = AddUser {
+        url: url.clone(),
+        html_link: HTML_PROFILE_URL,
+        profile_photo: None,
+        username: USERNAME,
+        import: false,
+    };
+
+    let add_user_msg_2 = AddUser {
+        url: url.clone(),
+        html_link: HTML_PROFILE_PHOTO_URL_2,
+        profile_photo: Some(HTML_PROFILE_PHOTO_URL_2),
+        username: USERNAME2,
+        import: false,
+    };
+
+    let db = Database::new();
+    db.migrate().await.unwrap();
+
+    let add_repo_msg = AddRepository {
+        html_link: HTML_REPO_URL,
+        name: REPO_NAME,
+        tags: Some(TAGS.into()),
+        owner: USERNAME,
+        website: "https://starcahrt-memory-test.example.org".into(),
+        description: "starchart in-memory test repo sescription".into(),
+        url,
+        import: false,
+        metadata: RepositoryMetadata {
+            stars: Some(42),
+            forks: Some(3),
+            fork: true,
+            parent: Some("https://upstream.example.org/starchart".into()),
+            mirror: false,
+            archived: true,
+            default_branch: Some("master".into()),
+            language: Some("Rust".into()),
+            clone_url: Some(format!("{HTML_REPO_URL}.git")),
+            created_at: Some(1_650_000_000),
+            updated_at: Some(1_680_000_000),
+        },
+    };
+
+    adding_forge_works(
+        &db,
+        create_forge_msg,
+        add_user_msg,
+        add_user_msg_2,
+        add_repo_msg,
+    )
+    .await;
+}
+
+#[actix_rt::test]
+async fn dns_challenge_works() {
+    let db = Database::new();
+    let forge_url = Url::parse("https://dns_challenge_works_memory.example.com").unwrap();
+    dns_challenge_helper(&db, &forge_url).await;
+}
+
+#[actix_rt::test]
+async fn introducer_works() {
+    let db = Database::new();
+    let instance_url = Url::parse("https://introducer_works_memory.example.com").unwrap();
+    instance_introducer_helper(&db, &instance_url).await;
+}
+
+#[actix_rt::test]
+async fn forge_type_exists() {
+    let db = Database::new();
+    forge_type_exists_helper(&db).await;
+}
+
+#[actix_rt::test]
+async fn mini_index_test() {
+    let db = Database::new();
+    mini_index_helper(&db).await;
+}
+
+#[actix_rt::test]
+async fn pagination_works() {
+    let db = Database::new();
+    let forge_url = Url::parse("https://pagination_works_memory.example.com").unwrap();
+    pagination_helper(&db, &forge_url).await;
+}
+
+#[actix_rt::test]
+async fn imports_work() {
+    let db = Database::new();
+    let instance_url = Url::parse("https://imports_work_instance_memory.example.com").unwrap();
+    let forge_url = Url::parse("https://imports_work_memory.example.com").unwrap();
+    imports_helper(&db, &instance_url, &forge_url).await;
+}
+
+#[actix_rt::test]
+async fn delete_cascade_works() {
+    let db = Database::new();
+    let forge_url = Url::parse("https://delete_cascade_works_memory.example.com").unwrap();
+    delete_cascade_helper(&db, &forge_url).await;
+}
+
+#[actix_rt::test]
+async fn duplicate_errors_work() {
+    let db = Database::new();
+    let forge_url = Url::parse("https://duplicate_errors_work_memory.example.com").unwrap();
+    duplicate_errors_helper(&db, &forge_url).await;
+}
+
+#[actix_rt::test]
+async fn search_works() {
+    let db = Database::new();
+    let forge_url = Url::parse("https://search_works_memory.example.com").unwrap();
+    search_helper(&db, &forge_url).await;
+}
+
+#[actix_rt::test]
+async fn query_works() {
+    let db = Database::new();
+    let forge_url = Url::parse("https://query_works_memory.example.com").unwrap();
+    query_helper(&db, &forge_url).await;
+}
+
+#[actix_rt::test]
+async fn starchart_import_works() {
+    let db = Database::new();
+    let instance_url = Url::parse("https://starchart_import_works_memory.example.com").unwrap();
+    starchart_import_helper(&db, &instance_url).await;
+}
+
+#[actix_rt::test]
+async fn clones_share_data() {
+    let db = Database::new();
+    let clone = db.clone();
+    db.add_word_to_mini_index("shared").await.unwrap();
+    assert!(clone.is_word_mini_indexed("shared").await.unwrap());
+}
@@ -0,0 +1,144 @@
+CREATE TABLE IF NOT EXISTS starchart_forge_type (
+	name VARCHAR(30) NOT NULL UNIQUE,
+	ID BIGSERIAL PRIMARY KEY NOT NULL
+);
+
+INSERT INTO starchart_forge_type (name) VALUES
+	('gitea'), ('gitlab'), ('forgejo'), ('sourcehut')
+ON CONFLICT DO NOTHING;
+
+CREATE TABLE IF NOT EXISTS starchart_introducer (
+	ID BIGSERIAL PRIMARY KEY NOT NULL,
+	instance_url TEXT NOT NULL UNIQUE
+);
+
+CREATE TABLE IF NOT EXISTS starchart_forges (
+	forge_type BIGINT NOT NULL REFERENCES starchart_forge_type(ID) ON DELETE CASCADE,
+	hostname TEXT NOT NULL UNIQUE,
+	verified_on BIGINT NOT NULL,
+	last_crawl_on BIGINT DEFAULT NULL,
+	last_full_crawl_on BIGINT DEFAULT NULL,
+	imported BOOLEAN NOT NULL DEFAULT false,
+	starchart_instance BIGINT REFERENCES starchart_introducer(ID) ON DELETE CASCADE DEFAULT NULL,
+	rate_limit_delay BIGINT DEFAULT NULL,
+	rate_limit_blocked_until BIGINT DEFAULT NULL,
+	checkpoint_token TEXT DEFAULT NULL,
+	checkpoint_page BIGINT DEFAULT NULL,
+	checkpoint_started_on BIGINT DEFAULT NULL,
+	checkpoint_since BIGINT DEFAULT NULL,
+	ID BIGSERIAL PRIMARY KEY NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS starchart_dns_challenges (
+	hostname TEXT NOT NULL UNIQUE,
+	key TEXT NOT NULL UNIQUE,
+	value TEXT NOT NULL UNIQUE,
+	created BIGINT NOT NULL,
+	expires_on BIGINT NOT NULL,
+	ID BIGSERIAL PRIMARY KEY NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS starchart_users (
+	hostname_id BIGINT NOT NULL REFERENCES starchart_forges(ID) ON DELETE CASCADE,
+	username TEXT NOT NULL,
+	html_url TEXT NOT NULL UNIQUE,
+	profile_photo_html_url TEXT DEFAULT NULL,
+	added_on BIGINT NOT NULL,
+	last_crawl_on BIGINT NOT NULL,
+	imported BOOLEAN NOT NULL DEFAULT false,
+	ID BIGSERIAL PRIMARY KEY NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS starchart_project_topics (
+	name VARCHAR(50) NOT NULL UNIQUE,
+	ID BIGSERIAL PRIMARY KEY NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS starchart_repositories (
+	ID BIGSERIAL PRIMARY KEY NOT NULL,
+	hostname_id BIGINT NOT NULL REFERENCES starchart_forges(ID) ON DELETE CASCADE,
+	owner_id BIGINT NOT NULL REFERENCES starchart_users(ID) ON DELETE CASCADE,
+	name TEXT NOT NULL,
+	description TEXT DEFAULT NULL,
+	website TEXT DEFAULT NULL,
+	html_url TEXT NOT NULL UNIQUE,
+	created BIGINT NOT NULL,
+	last_crawl BIGINT NOT NULL,
+	imported BOOLEAN NOT NULL DEFAULT false,
+	stars BIGINT DEFAULT NULL,
+	forks BIGINT DEFAULT NULL,
+	fork BOOLEAN NOT NULL DEFAULT false,
+	parent_html_url TEXT DEFAULT NULL,
+	mirror BOOLEAN NOT NULL DEFAULT false,
+	archived BOOLEAN NOT NULL DEFAULT false,
+	default_branch TEXT DEFAULT NULL,
+	clone_url TEXT DEFAULT NULL,
+	created_on_forge BIGINT DEFAULT NULL,
+	updated_on_forge BIGINT DEFAULT NULL,
+	-- replaces SQLite's fts_repositories table
+	search_vector tsvector GENERATED ALWAYS AS (
+		to_tsvector('english',
+			name || ' ' ||
+			coalesce(description, '') || ' ' ||
+			coalesce(website, '') || ' ' ||
+			html_url
+		)
+	) STORED
+);
+
+CREATE INDEX IF NOT EXISTS starchart_repositories_search_vector
+	ON starchart_repositories USING GIN (search_vector);
+
+CREATE TABLE IF NOT EXISTS starchart_repository_topic_mapping (
+	repository_id BIGINT NOT NULL REFERENCES starchart_repositories(ID) ON DELETE CASCADE,
+	topic_id BIGINT NOT NULL REFERENCES starchart_project_topics(ID) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS starchart_mini_index (
+	word TEXT NOT NULL UNIQUE,
+	ID BIGSERIAL PRIMARY KEY NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS starchart_federated_mini_index (
+	starchart_instance BIGINT REFERENCES starchart_introducer(ID) ON DELETE CASCADE,
+	mini_index TEXT NOT NULL,
+	ID BIGSERIAL PRIMARY KEY NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS starchart_federated_mini_index_search
+	ON starchart_federated_mini_index USING GIN (to_tsvector('simple', mini_index));
+
+CREATE TABLE IF NOT EXISTS starchart_imported_starcharts (
+	starchart_instance BIGINT REFERENCES starchart_introducer(ID) ON DELETE CASCADE UNIQUE,
+	ID BIGSERIAL PRIMARY KEY NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS starchart_crawl_schedule (
+	hostname TEXT NOT NULL UNIQUE,
+	next_crawl_on BIGINT NOT NULL,
+	priority BIGINT NOT NULL DEFAULT 0,
+	crawl_interval BIGINT DEFAULT NULL,
+	last_started_on BIGINT DEFAULT NULL,
+	last_finished_on BIGINT DEFAULT NULL,
+	last_error TEXT DEFAULT NULL,
+	consecutive_failures BIGINT NOT NULL DEFAULT 0,
+	disabled BOOLEAN NOT NULL DEFAULT false,
+	ID BIGSERIAL PRIMARY KEY NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS starchart_crawl_runs (
+	hostname TEXT NOT NULL,
+	started_on BIGINT NOT NULL,
+	finished_on BIGINT NOT NULL,
+	pages BIGINT NOT NULL DEFAULT 0,
+	repositories_added BIGINT NOT NULL DEFAULT 0,
+	repositories_updated BIGINT NOT NULL DEFAULT 0,
+	repositories_removed BIGINT NOT NULL DEFAULT 0,
+	users_added BIGINT NOT NULL DEFAULT 0,
+	users_updated BIGINT NOT NULL DEFAULT 0,
+	users_removed BIGINT NOT NULL DEFAULT 0,
+	error TEXT DEFAULT NULL,
+	ID BIGSERIAL PRIMARY KEY NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS starchart_crawl_runs_hostname ON starchart_crawl_runs(hostname);
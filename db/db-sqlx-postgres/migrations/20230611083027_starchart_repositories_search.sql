@@ -0,0 +1,23 @@
+-- topics and owners of repositories are stored in other tables, so the search vector can't be a
+-- generated column anymore. It is maintained by Starchart instead.
+ALTER TABLE starchart_repositories DROP COLUMN search_vector;
+ALTER TABLE starchart_repositories ADD COLUMN search_vector tsvector NOT NULL DEFAULT ''::tsvector;
+
+CREATE INDEX IF NOT EXISTS starchart_repositories_search_vector
+	ON starchart_repositories USING GIN (search_vector);
+
+UPDATE starchart_repositories SET search_vector =
+	setweight(to_tsvector('english', name), 'A') ||
+	setweight(to_tsvector('english', coalesce((
+		SELECT string_agg(starchart_project_topics.name, ' ')
+		FROM starchart_project_topics
+		INNER JOIN starchart_repository_topic_mapping
+		ON starchart_repository_topic_mapping.topic_id = starchart_project_topics.ID
+		WHERE starchart_repository_topic_mapping.repository_id = starchart_repositories.ID
+	), '')), 'B') ||
+	setweight(to_tsvector('english', (
+		SELECT username FROM starchart_users
+		WHERE starchart_users.ID = starchart_repositories.owner_id
+	)), 'B') ||
+	setweight(to_tsvector('english', coalesce(description, '')), 'C') ||
+	setweight(to_tsvector('english', coalesce(website, '')), 'D');
@@ -0,0 +1,46 @@
+/*
+ * Copyright (C) 2022  Aravinth Manivannan <realaravinth@batsense.net>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+use std::borrow::Cow;
+
+use db_core::dev::*;
+use sqlx::Error;
+
+/// Postgres error code for unique constraint violations
+const UNIQUE_VIOLATION: &str = "23505";
+
+pub fn map_register_err(e: Error) -> DBError {
+    if let Error::Database(err) = e {
+        if err.code() == Some(Cow::from(UNIQUE_VIOLATION)) {
+            match err.constraint() {
+                Some("starchart_dns_challenges_hostname_key") => {
+                    DBError::DuplicateChallengeHostname
+                }
+                Some("starchart_dns_challenges_value_key") => DBError::DuplicateChallengeText,
+                Some("starchart_forges_hostname_key") => DBError::DuplicateHostname,
+                Some("starchart_users_html_url_key") => DBError::DuplicateUserLink,
+                Some("starchart_project_topics_name_key") => DBError::DuplicateTopic,
+                Some("starchart_repositories_html_url_key") => DBError::DuplicateRepositoryLink,
+                Some("starchart_forge_type_name_key") => DBError::DuplicateForgeType,
+                _ => DBError::DBError(Box::new(Error::Database(err))),
+            }
+        } else {
+            DBError::DBError(Box::new(Error::Database(err)))
+        }
+    } else {
+        DBError::DBError(Box::new(e))
+    }
+}
@@ -0,0 +1,1797 @@
+/*
+ * ForgeFlux StarChart - A federated software forge spider
+ * Copyright (C) 2022  Aravinth Manivannan <realaravinth@batsense.net>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+use std::str::FromStr;
+
+use db_core::dev::*;
+
+use sqlx::postgres::PgPool;
+use sqlx::postgres::PgPoolOptions;
+use sqlx::types::time::OffsetDateTime;
+use url::Url;
+
+pub mod errors;
+#[cfg(test)]
+pub mod tests;
+
+#[derive(Clone)]
+pub struct Database {
+    pub pool: PgPool,
+}
+
+/// Use an existing database pool
+pub struct Conn(pub PgPool);
+
+/// Connect to databse
+pub enum ConnectionOptions {
+    /// fresh connection
+    Fresh(Fresh),
+    /// existing connection
+    Existing(Conn),
+}
+
+pub struct Fresh {
+    pub pool_options: PgPoolOptions,
+    pub url: String,
+}
+
+pub mod dev {
+    pub use super::errors::*;
+    pub use super::Database;
+    pub use db_core::dev::*;
+    pub use prelude::*;
+    pub use sqlx::Error;
+}
+
+pub mod prelude {
+    pub use super::*;
+    pub use db_core::prelude::*;
+}
+
+#[async_trait]
+impl Connect for ConnectionOptions {
+    type Pool = Database;
+    async fn connect(self) -> DBResult<Self::Pool> {
+        let pool = match self {
+            Self::Fresh(fresh) => fresh
+                .pool_options
+                .connect(&fresh.url)
+                .await
+                .map_err(|e| DBError::DBError(Box::new(e)))?,
+            Self::Existing(conn) => conn.0,
+        };
+        Ok(Database { pool })
+    }
+}
+
+use dev::*;
+
+#[async_trait]
+impl Migrate for Database {
+    async fn migrate(&self) -> DBResult<()> {
+        sqlx::migrate!("./migrations/")
+            .run(&self.pool)
+            .await
+            .map_err(|e| DBError::DBError(Box::new(e)))?;
+        Ok(())
+    }
+}
+
+impl Database {
+    async fn get_federated_mini_index_matrches(&self, query: &str) -> DBResult<Vec<String>> {
+        struct Match {
+            instance_url: String,
+        }
+        let mut fts_mini_index_matches = sqlx::query_as!(
+            Match,
+            "SELECT
+                starchart_introducer.instance_url
+            FROM
+                starchart_federated_mini_index
+            INNER JOIN
+                starchart_introducer
+            ON
+                starchart_introducer.ID = starchart_instance
+            WHERE
+                to_tsvector('simple', mini_index) @@ plainto_tsquery('simple', $1)",
+            query
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| DBError::DBError(Box::new(e)))?;
+
+        let mut res = Vec::with_capacity(fts_mini_index_matches.len());
+        fts_mini_index_matches
+            .drain(0..)
+            .for_each(|m| res.push(m.instance_url));
+
+        Ok(res)
+    }
+
+    /// Index a repository for full-text search. Must be called after changes to the repository
+    /// or its topics
+    async fn index_repository(&self, html_url: &str) -> DBResult<()> {
+        sqlx::query!(
+            "UPDATE starchart_repositories SET search_vector =
+                setweight(to_tsvector('english', name), 'A') ||
+                setweight(to_tsvector('english', coalesce((
+                    SELECT string_agg(starchart_project_topics.name, ' ')
+                    FROM starchart_project_topics
+                    INNER JOIN starchart_repository_topic_mapping
+                    ON starchart_repository_topic_mapping.topic_id = starchart_project_topics.ID
+                    WHERE
+                        starchart_repository_topic_mapping.repository_id
+                            = starchart_repositories.ID
+                ), '')), 'B') ||
+                setweight(to_tsvector('english', (
+                    SELECT username FROM starchart_users
+                    WHERE starchart_users.ID = starchart_repositories.owner_id
+                )), 'B') ||
+                setweight(to_tsvector('english', coalesce(description, '')), 'C') ||
+                setweight(to_tsvector('english', coalesce(website, '')), 'D')
+            WHERE html_url = $1",
+            html_url
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| DBError::DBError(Box::new(e)))?;
+        Ok(())
+    }
+
+    async fn add_repository_topics(&self, html_url: &str, topics: &[&str]) -> DBResult<()> {
+        for topic in topics.iter() {
+            sqlx::query!(
+                "INSERT INTO starchart_project_topics ( name ) VALUES ( $1 )
+                ON CONFLICT DO NOTHING;",
+                topic,
+            )
+            .execute(&self.pool)
+            .await
+            .map_err(map_register_err)?;
+
+            self.add_word_to_mini_index(topic).await?;
+
+            sqlx::query!(
+                "
+                    INSERT INTO starchart_repository_topic_mapping ( topic_id, repository_id )
+                    VALUES (
+                        (SELECT ID FROM starchart_project_topics WHERE name = $1),
+                        (SELECT ID FROM starchart_repositories WHERE html_url = $2)
+                    );",
+                topic,
+                html_url,
+            )
+            .execute(&self.pool)
+            .await
+            .map_err(map_register_err)?;
+        }
+        Ok(())
+    }
+
+    async fn get_repository_topics(&self, repository_id: i64) -> DBResult<Option<Vec<String>>> {
+        struct Topic {
+            name: String,
+        }
+
+        let mut db_topics = sqlx::query_as!(
+            Topic,
+            "SELECT name FROM starchart_project_topics WHERE ID IN (
+                SELECT topic_id FROM starchart_repository_topic_mapping WHERE repository_id = $1
+            )",
+            repository_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_register_err)?;
+
+        if db_topics.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(db_topics.drain(0..).map(|t| t.name).collect()))
+        }
+    }
+}
+
+#[async_trait]
+impl SCDatabase for Database {
+    /// ping DB
+    async fn ping(&self) -> bool {
+        use sqlx::Connection;
+
+        if let Ok(mut con) = self.pool.acquire().await {
+            con.ping().await.is_ok()
+        } else {
+            false
+        }
+    }
+
+    /// delete forge instance
+    async fn delete_forge_instance(&self, url: &Url) -> DBResult<()> {
+        let url = db_core::clean_url(url);
+        sqlx::query!("DELETE FROM starchart_forges WHERE hostname = ($1)", url,)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| DBError::DBError(Box::new(e)))?;
+        sqlx::query!(
+            "DELETE FROM starchart_crawl_schedule WHERE hostname = ($1)",
+            url,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| DBError::DBError(Box::new(e)))?;
+        sqlx::query!(
+            "DELETE FROM starchart_crawl_runs WHERE hostname = ($1)",
+            url,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| DBError::DBError(Box::new(e)))?;
+        self.rm_word_from_mini_index(&url).await?;
+        Ok(())
+    }
+
+    /// create forge instance DB
+    async fn create_forge_instance(&self, f: &CreateForge) -> DBResult<()> {
+        let now = now_unix_time_stamp();
+        let url = db_core::clean_url(&f.url);
+        let forge_type = f.forge_type.to_str();
+        if let Some(instance_url) = f.starchart_url {
+            sqlx::query!(
+                "INSERT INTO starchart_forges
+                    (hostname, verified_on, forge_type, starchart_instance, imported)
+                VALUES (
+                        $1,
+                        $2,
+                        (SELECT ID FROM starchart_forge_type WHERE name = $3),
+                        (SELECT ID FROM starchart_introducer WHERE instance_url = $4),
+                        true
+                    )
+                ON CONFLICT DO NOTHING",
+                url,
+                now,
+                forge_type,
+                instance_url
+            )
+            .execute(&self.pool)
+            .await
+            .map_err(map_register_err)?;
+        } else {
+            sqlx::query!(
+                "INSERT INTO starchart_forges
+                    (hostname, verified_on, forge_type)
+                VALUES
+                    (
+                        $1, $2,
+                     (SELECT ID FROM starchart_forge_type WHERE name = $3))",
+                url,
+                now,
+                forge_type,
+            )
+            .execute(&self.pool)
+            .await
+            .map_err(map_register_err)?;
+
+            // forges that aren't imported are crawled by this instance
+            sqlx::query!(
+                "INSERT INTO starchart_crawl_schedule (hostname, next_crawl_on)
+                VALUES ($1, $2)
+                ON CONFLICT DO NOTHING",
+                url,
+                now
+            )
+            .execute(&self.pool)
+            .await
+            .map_err(|e| DBError::DBError(Box::new(e)))?;
+        }
+
+        self.add_word_to_mini_index(&url).await?;
+
+        Ok(())
+    }
+
+    /// get forge instance data
+    async fn get_forge(&self, url: &Url) -> DBResult<Forge> {
+        let url = db_core::clean_url(url);
+        let f = sqlx::query_as!(
+            InnerForge,
+            "SELECT
+                hostname,
+                last_crawl_on,
+                last_full_crawl_on,
+                starchart_introducer.instance_url,
+                starchart_forge_type.name
+            FROM
+                starchart_forges
+            INNER JOIN
+                starchart_forge_type
+            ON
+                starchart_forges.forge_type = starchart_forge_type.id
+            LEFT JOIN
+                starchart_introducer
+            ON
+                starchart_introducer.ID = starchart_forges.starchart_instance
+            WHERE
+                hostname = $1;
+            ",
+            url,
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| DBError::DBError(Box::new(e)))?;
+
+        Ok(f.into())
+    }
+
+    /// Record completed crawl of a forge
+    async fn record_forge_crawl(&self, url: &Url, crawled_on: i64, full: bool) -> DBResult<()> {
+        let url = db_core::clean_url(url);
+        if full {
+            sqlx::query!(
+                "UPDATE starchart_forges
+                SET last_crawl_on = $1, last_full_crawl_on = $1
+                WHERE hostname = $2",
+                crawled_on,
+                url
+            )
+            .execute(&self.pool)
+            .await
+            .map_err(|e| DBError::DBError(Box::new(e)))?;
+        } else {
+            sqlx::query!(
+                "UPDATE starchart_forges SET last_crawl_on = $1 WHERE hostname = $2",
+                crawled_on,
+                url
+            )
+            .execute(&self.pool)
+            .await
+            .map_err(|e| DBError::DBError(Box::new(e)))?;
+        }
+        Ok(())
+    }
+
+    /// Get persisted rate limit state of a forge, if any
+    async fn get_forge_rate_limit(&self, url: &Url) -> DBResult<Option<ForgeRateLimit>> {
+        struct InnerRateLimit {
+            rate_limit_delay: Option<i64>,
+            rate_limit_blocked_until: Option<i64>,
+        }
+
+        let url = db_core::clean_url(url);
+        let res = sqlx::query_as!(
+            InnerRateLimit,
+            "SELECT rate_limit_delay, rate_limit_blocked_until FROM starchart_forges
+                WHERE hostname = $1",
+            url
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| DBError::DBError(Box::new(e)))?;
+        Ok(res.rate_limit_delay.map(|delay| ForgeRateLimit {
+            delay: delay as u64,
+            blocked_until: res.rate_limit_blocked_until,
+        }))
+    }
+
+    /// Persist rate limit state of a forge
+    async fn set_forge_rate_limit(&self, url: &Url, rate_limit: &ForgeRateLimit) -> DBResult<()> {
+        let url = db_core::clean_url(url);
+        let delay = rate_limit.delay as i64;
+        sqlx::query!(
+            "UPDATE starchart_forges
+                SET rate_limit_delay = $1, rate_limit_blocked_until = $2
+                WHERE hostname = $3",
+            delay,
+            rate_limit.blocked_until,
+            url
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| DBError::DBError(Box::new(e)))?;
+        Ok(())
+    }
+
+    /// Get progress of the unfinished crawl of a forge, if any
+    async fn get_crawl_checkpoint(&self, url: &Url) -> DBResult<Option<CrawlCheckpoint>> {
+        struct InnerCheckpoint {
+            checkpoint_token: Option<String>,
+            checkpoint_page: Option<i64>,
+            checkpoint_started_on: Option<i64>,
+            checkpoint_since: Option<i64>,
+        }
+
+        let url = db_core::clean_url(url);
+        let res = sqlx::query_as!(
+            InnerCheckpoint,
+            "SELECT checkpoint_token, checkpoint_page, checkpoint_started_on, checkpoint_since
+                FROM starchart_forges WHERE hostname = $1",
+            url
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| DBError::DBError(Box::new(e)))?;
+        match (
+            res.checkpoint_token,
+            res.checkpoint_page,
+            res.checkpoint_started_on,
+        ) {
+            (Some(token), Some(page), Some(started_on)) => Ok(Some(CrawlCheckpoint {
+                token,
+                page: page as u64,
+                started_on,
+                since: res.checkpoint_since,
+            })),
+            _ => Ok(None),
+        }
+    }
+
+    /// Persist progress of the crawl of a forge
+    async fn set_crawl_checkpoint(&self, url: &Url, checkpoint: &CrawlCheckpoint) -> DBResult<()> {
+        let url = db_core::clean_url(url);
+        let page = checkpoint.page as i64;
+        sqlx::query!(
+            "UPDATE starchart_forges SET
+                checkpoint_token = $1,
+                checkpoint_page = $2,
+                checkpoint_started_on = $3,
+                checkpoint_since = $4
+            WHERE hostname = $5",
+            checkpoint.token,
+            page,
+            checkpoint.started_on,
+            checkpoint.since,
+            url
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| DBError::DBError(Box::new(e)))?;
+        Ok(())
+    }
+
+    /// Remove progress of the crawl of a forge
+    async fn rm_crawl_checkpoint(&self, url: &Url) -> DBResult<()> {
+        let url = db_core::clean_url(url);
+        sqlx::query!(
+            "UPDATE starchart_forges SET
+                checkpoint_token = NULL,
+                checkpoint_page = NULL,
+                checkpoint_started_on = NULL,
+                checkpoint_since = NULL
+            WHERE hostname = $1",
+            url
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| DBError::DBError(Box::new(e)))?;
+        Ok(())
+    }
+
+    /// Schedule crawl of a forge
+    async fn schedule_forge_crawl(
+        &self,
+        url: &Url,
+        next_crawl_on: i64,
+        priority: u32,
+    ) -> DBResult<()> {
+        let url = db_core::clean_url(url);
+        let priority = priority as i64;
+        sqlx::query!(
+            "INSERT INTO starchart_crawl_schedule (hostname, next_crawl_on, priority)
+                VALUES ($1, $2, $3)
+            ON CONFLICT(hostname) DO UPDATE SET
+                next_crawl_on = excluded.next_crawl_on,
+                priority = excluded.priority,
+                consecutive_failures = 0,
+                disabled = FALSE",
+            url,
+            next_crawl_on,
+            priority
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| DBError::DBError(Box::new(e)))?;
+        Ok(())
+    }
+
+    /// Set seconds between crawls of a scheduled forge
+    async fn set_forge_crawl_interval(&self, url: &Url, interval: Option<u64>) -> DBResult<()> {
+        let url = db_core::clean_url(url);
+        let interval = interval.map(|i| i as i64);
+        let res = sqlx::query!(
+            "UPDATE starchart_crawl_schedule SET crawl_interval = $1 WHERE hostname = $2",
+            interval,
+            url
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| DBError::DBError(Box::new(e)))?;
+        if res.rows_affected() == 0 {
+            return Err(DBError::DBError(Box::new(Error::RowNotFound)));
+        }
+        Ok(())
+    }
+
+    /// Disable or enable crawls of a scheduled forge
+    async fn set_forge_crawl_disabled(&self, url: &Url, disabled: bool) -> DBResult<()> {
+        let url = db_core::clean_url(url);
+        let res = sqlx::query!(
+            "UPDATE starchart_crawl_schedule SET disabled = $1 WHERE hostname = $2",
+            disabled,
+            url
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| DBError::DBError(Box::new(e)))?;
+        if res.rows_affected() == 0 {
+            return Err(DBError::DBError(Box::new(Error::RowNotFound)));
+        }
+        Ok(())
+    }
+
+    /// Get crawl schedule of a forge, if it is scheduled
+    async fn get_forge_crawl_schedule(&self, url: &Url) -> DBResult<Option<CrawlSchedule>> {
+        let url = db_core::clean_url(url);
+        let res = sqlx::query_as!(
+            InnerCrawlSchedule,
+            "SELECT
+                hostname,
+                next_crawl_on,
+                priority,
+                crawl_interval,
+                last_started_on,
+                last_finished_on,
+                last_error,
+                consecutive_failures,
+                disabled
+            FROM
+                starchart_crawl_schedule
+            WHERE
+                hostname = $1",
+            url
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| DBError::DBError(Box::new(e)))?;
+        Ok(res.map(|s| s.into()))
+    }
+
+    /// Get forges that are due for a crawl
+    async fn get_due_forge_crawls(&self, now: i64, limit: u32) -> DBResult<Vec<CrawlSchedule>> {
+        let limit = limit as i64;
+        let mut res = sqlx::query_as!(
+            InnerCrawlSchedule,
+            "SELECT
+                hostname,
+                next_crawl_on,
+                priority,
+                crawl_interval,
+                last_started_on,
+                last_finished_on,
+                last_error,
+                consecutive_failures,
+                disabled
+            FROM
+                starchart_crawl_schedule
+            WHERE
+                next_crawl_on <= $1 AND disabled = FALSE
+            ORDER BY
+                priority DESC, next_crawl_on ASC
+            LIMIT $2",
+            now,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| DBError::DBError(Box::new(e)))?;
+        Ok(res.drain(0..).map(|s| s.into()).collect())
+    }
+
+    /// Record finished crawl of a scheduled forge
+    async fn record_forge_crawl_run(&self, url: &Url, crawl: &FinishedCrawl) -> DBResult<()> {
+        let url = db_core::clean_url(url);
+        let failed = crawl.error.is_some();
+        sqlx::query!(
+            "UPDATE starchart_crawl_schedule SET
+                last_started_on = $1,
+                last_finished_on = $2,
+                last_error = $3,
+                next_crawl_on = $4,
+                priority = 0,
+                consecutive_failures = CASE WHEN $5 THEN consecutive_failures + 1 ELSE 0 END
+            WHERE hostname = $6",
+            crawl.started_on,
+            crawl.finished_on,
+            crawl.error,
+            crawl.next_crawl_on,
+            failed,
+            url
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| DBError::DBError(Box::new(e)))?;
+
+        let pages = crawl.stats.pages as i64;
+        let repositories_added = crawl.stats.repositories_added as i64;
+        let repositories_updated = crawl.stats.repositories_updated as i64;
+        let repositories_removed = crawl.stats.repositories_removed as i64;
+        let users_added = crawl.stats.users_added as i64;
+        let users_updated = crawl.stats.users_updated as i64;
+        let users_removed = crawl.stats.users_removed as i64;
+        sqlx::query!(
+            "INSERT INTO starchart_crawl_runs
+                (
+                    hostname, started_on, finished_on, pages,
+                    repositories_added, repositories_updated, repositories_removed,
+                    users_added, users_updated, users_removed, error
+                )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)",
+            url,
+            crawl.started_on,
+            crawl.finished_on,
+            pages,
+            repositories_added,
+            repositories_updated,
+            repositories_removed,
+            users_added,
+            users_updated,
+            users_removed,
+            crawl.error
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| DBError::DBError(Box::new(e)))?;
+        Ok(())
+    }
+
+    /// Get crawl history of a forge, latest crawl first
+    async fn get_forge_crawl_runs(
+        &self,
+        url: &Url,
+        offset: u32,
+        limit: u32,
+    ) -> DBResult<Vec<CrawlRun>> {
+        struct InnerCrawlRun {
+            started_on: i64,
+            finished_on: i64,
+            pages: i64,
+            repositories_added: i64,
+            repositories_updated: i64,
+            repositories_removed: i64,
+            users_added: i64,
+            users_updated: i64,
+            users_removed: i64,
+            error: Option<String>,
+        }
+
+        let url = db_core::clean_url(url);
+        let limit = limit as i64;
+        let offset = offset as i64;
+        let mut res = sqlx::query_as!(
+            InnerCrawlRun,
+            "SELECT
+                started_on,
+                finished_on,
+                pages,
+                repositories_added,
+                repositories_updated,
+                repositories_removed,
+                users_added,
+                users_updated,
+                users_removed,
+                error
+            FROM
+                starchart_crawl_runs
+            WHERE
+                hostname = $1
+            ORDER BY
+                ID DESC
+            LIMIT $2 OFFSET $3",
+            url,
+            limit,
+            offset
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| DBError::DBError(Box::new(e)))?;
+        Ok(res
+            .drain(0..)
+            .map(|r| CrawlRun {
+                started_on: r.started_on,
+                finished_on: r.finished_on,
+                stats: CrawlStats {
+                    pages: r.pages as u64,
+                    repositories_added: r.repositories_added as u64,
+                    repositories_updated: r.repositories_updated as u64,
+                    repositories_removed: r.repositories_removed as u64,
+                    users_added: r.users_added as u64,
+                    users_updated: r.users_updated as u64,
+                    users_removed: r.users_removed as u64,
+                },
+                outcome: match r.error {
+                    Some(e) => CrawlOutcome::Failed(e),
+                    None => CrawlOutcome::Success,
+                },
+            })
+            .collect())
+    }
+
+    /// Get all forges
+    async fn get_all_forges(
+        &self,
+        with_imports: bool,
+        offset: u32,
+        limit: u32,
+    ) -> DBResult<Vec<Forge>> {
+        let limit = limit as i64;
+        let offset = offset as i64;
+        let mut inter_forges = if with_imports {
+            sqlx::query_as!(
+                InnerForge,
+                "SELECT
+                hostname,
+                last_crawl_on,
+                last_full_crawl_on,
+                starchart_forge_type.name,
+                starchart_introducer.instance_url
+            FROM
+                starchart_forges
+            INNER JOIN
+                starchart_forge_type
+            ON
+                starchart_forges.forge_type = starchart_forge_type.id
+            LEFT JOIN
+                starchart_introducer
+            ON
+                starchart_introducer.ID = starchart_forges.starchart_instance
+            ORDER BY
+                starchart_forges.ID
+            LIMIT $1 OFFSET $2;
+        ",
+                limit,
+                offset
+            )
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| DBError::DBError(Box::new(e)))?
+        } else {
+            sqlx::query_as!(
+                InnerForge,
+                "SELECT
+                hostname,
+                last_crawl_on,
+                last_full_crawl_on,
+                starchart_introducer.instance_url,
+                starchart_forge_type.name
+            FROM
+                starchart_forges
+            INNER JOIN
+                starchart_forge_type
+            ON
+                starchart_forges.forge_type = starchart_forge_type.id
+            LEFT JOIN
+                starchart_introducer
+            ON
+                starchart_introducer.ID = starchart_forges.starchart_instance
+            WHERE
+                starchart_forges.imported = false
+            ORDER BY
+                starchart_forges.ID
+            LIMIT $1 OFFSET $2;
+        ",
+                limit,
+                offset
+            )
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| DBError::DBError(Box::new(e)))?
+        };
+
+        let mut forges: Vec<Forge> = Vec::with_capacity(inter_forges.len());
+        inter_forges.drain(0..).for_each(|f| forges.push(f.into()));
+
+        Ok(forges)
+    }
+
+    /// check if a forge instance exists
+    async fn forge_exists(&self, url: &Url) -> DBResult<bool> {
+        let url = db_core::clean_url(url);
+        match sqlx::query!("SELECT ID FROM starchart_forges WHERE hostname = $1", url)
+            .fetch_one(&self.pool)
+            .await
+        {
+            Ok(_) => Ok(true),
+            Err(Error::RowNotFound) => Ok(false),
+            Err(e) => Err(DBError::DBError(Box::new(e))),
+        }
+    }
+
+    async fn forge_type_exists(&self, forge_type: &ForgeImplementation) -> DBResult<bool> {
+        let forge_type = forge_type.to_str();
+        match sqlx::query!(
+            "SELECT ID FROM starchart_forge_type WHERE name = $1",
+            forge_type
+        )
+        .fetch_one(&self.pool)
+        .await
+        {
+            Ok(_) => Ok(true),
+            Err(Error::RowNotFound) => Ok(false),
+            Err(e) => Err(DBError::DBError(Box::new(e))),
+        }
+    }
+
+    async fn create_dns_challenge(&self, c: &CreateDNSChallenge) -> DBResult<()> {
+        let url = db_core::clean_url(c.url);
+        sqlx::query!(
+            "INSERT INTO starchart_dns_challenges
+                (hostname, key, value, created, expires_on)
+            VALUES ($1, $2, $3, $4, $5)",
+            url,
+            c.key,
+            c.value,
+            c.created,
+            c.expires_on
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(map_register_err)?;
+        Ok(())
+    }
+
+    async fn dns_challenge_exists(&self, key: &str) -> DBResult<bool> {
+        match sqlx::query!(
+            "SELECT ID FROM starchart_dns_challenges WHERE key = $1",
+            key
+        )
+        .fetch_one(&self.pool)
+        .await
+        {
+            Ok(_) => Ok(true),
+            Err(Error::RowNotFound) => Ok(false),
+            Err(e) => Err(DBError::DBError(Box::new(e))),
+        }
+    }
+
+    async fn get_dns_challenge(&self, key: &str) -> DBResult<DNSChallenge> {
+        struct InnerDNSChallenge {
+            hostname: String,
+            key: String,
+            value: String,
+            created: i64,
+            expires_on: i64,
+        }
+
+        let c = sqlx::query_as!(
+            InnerDNSChallenge,
+            "SELECT hostname, key, value, created, expires_on
+                FROM starchart_dns_challenges WHERE key = $1",
+            key
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| DBError::DBError(Box::new(e)))?;
+        Ok(DNSChallenge {
+            url: c.hostname,
+            key: c.key,
+            value: c.value,
+            created: c.created,
+            expires_on: c.expires_on,
+        })
+    }
+
+    async fn delete_dns_challenge(&self, key: &str) -> DBResult<()> {
+        sqlx::query!("DELETE FROM starchart_dns_challenges WHERE key = $1", key)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| DBError::DBError(Box::new(e)))?;
+        Ok(())
+    }
+
+    async fn rm_expired_dns_challenges(&self, now: i64) -> DBResult<()> {
+        sqlx::query!(
+            "DELETE FROM starchart_dns_challenges WHERE expires_on <= $1",
+            now
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| DBError::DBError(Box::new(e)))?;
+        Ok(())
+    }
+
+    /// add new user to database
+    async fn add_user(&self, u: &AddUser) -> DBResult<()> {
+        let now = now_unix_time_stamp();
+        let url = db_core::clean_url(&u.url);
+        sqlx::query!(
+            "INSERT INTO
+                    starchart_users (
+                        hostname_id, username, html_url,
+                        profile_photo_html_url, added_on, last_crawl_on, imported
+                    )
+            VALUES (
+                    (SELECT ID FROM starchart_forges WHERE hostname = $1), $2, $3, $4, $5, $6, $7)",
+            url,
+            u.username,
+            u.html_link,
+            u.profile_photo,
+            now,
+            now,
+            u.import
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(map_register_err)?;
+        self.add_word_to_mini_index(u.username).await?;
+
+        Ok(())
+    }
+
+    /// update data of an existing user
+    async fn update_user(&self, u: &AddUser) -> DBResult<()> {
+        let now = now_unix_time_stamp();
+        let url = db_core::clean_url(&u.url);
+        let res = sqlx::query!(
+            "UPDATE starchart_users
+                SET html_url = $1, profile_photo_html_url = $2, last_crawl_on = $3
+                WHERE username = $4 AND
+                    hostname_id = (SELECT ID FROM starchart_forges WHERE hostname = $5)",
+            u.html_link,
+            u.profile_photo,
+            now,
+            u.username,
+            url,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(map_register_err)?;
+        if res.rows_affected() == 0 {
+            return Err(DBError::DBError(Box::new(Error::RowNotFound)));
+        }
+        self.add_word_to_mini_index(u.username).await?;
+
+        Ok(())
+    }
+
+    /// get user data
+    async fn get_user(&self, username: &str, url: &Url) -> DBResult<User> {
+        struct InnerUser {
+            profile_photo_html_url: Option<String>,
+            html_url: String,
+            imported: bool,
+        }
+
+        let url = db_core::clean_url(url);
+        let res = sqlx::query_as!(
+            InnerUser,
+            "SELECT html_url, profile_photo_html_url, imported FROM starchart_users WHERE username = $1 AND
+                hostname_id = (SELECT ID FROM starchart_forges WHERE hostname = $2)",
+            username,
+            url,
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| DBError::DBError(Box::new(e)))?;
+        Ok(User {
+            username: username.into(),
+            url,
+            profile_photo: res.profile_photo_html_url,
+            html_link: res.html_url,
+            import: res.imported,
+        })
+    }
+
+    /// check if an user exists. When url of a forge instace is provided, username search is
+    /// done only on that forge
+    async fn user_exists(&self, username: &str, url: Option<&Url>) -> DBResult<bool> {
+        match url {
+            Some(url) => {
+                let url = db_core::clean_url(url);
+                match sqlx::query!(
+                    "SELECT ID FROM starchart_users WHERE username = $1 AND
+                hostname_id = (SELECT ID FROM starchart_forges WHERE hostname = $2)",
+                    username,
+                    url,
+                )
+                .fetch_one(&self.pool)
+                .await
+                {
+                    Ok(_) => Ok(true),
+                    Err(Error::RowNotFound) => Ok(false),
+                    Err(e) => Err(DBError::DBError(Box::new(e))),
+                }
+            }
+            None => match sqlx::query!(
+                "SELECT ID FROM starchart_users WHERE username = $1",
+                username
+            )
+            .fetch_one(&self.pool)
+            .await
+            {
+                Ok(_) => Ok(true),
+                Err(Error::RowNotFound) => Ok(false),
+                Err(e) => Err(DBError::DBError(Box::new(e))),
+            },
+        }
+    }
+
+    /// check if a repo exists.
+    async fn repository_exists(&self, name: &str, owner: &str, url: &Url) -> DBResult<bool> {
+        let url = db_core::clean_url(url);
+        match sqlx::query!(
+            "SELECT ID FROM starchart_repositories
+                WHERE
+                    name = $1
+                AND
+                    owner_id = ( SELECT ID FROM starchart_users WHERE username = $2)
+                AND
+                    hostname_id = (SELECT ID FROM starchart_forges WHERE hostname = $3)",
+            name,
+            owner,
+            url,
+        )
+        .fetch_one(&self.pool)
+        .await
+        {
+            Ok(_) => Ok(true),
+            Err(Error::RowNotFound) => Ok(false),
+            Err(e) => Err(DBError::DBError(Box::new(e))),
+        }
+    }
+
+    /// add new repository to database.
+    async fn create_repository(&self, r: &AddRepository) -> DBResult<()> {
+        let now = now_unix_time_stamp();
+        let url = db_core::clean_url(&r.url);
+        let stars = r.metadata.stars.map(|s| s as i64);
+        let forks = r.metadata.forks.map(|f| f as i64);
+        sqlx::query!(
+            "INSERT INTO
+                starchart_repositories (
+                    hostname_id, owner_id, name, description, html_url, website, created,
+                    last_crawl, imported, stars, forks, fork, parent_html_url, mirror,
+                    archived, default_branch, clone_url, created_on_forge, updated_on_forge,
+                    language
+                )
+                VALUES (
+                    (SELECT ID FROM starchart_forges WHERE hostname = $1),
+                    (SELECT ID FROM starchart_users WHERE username = $2),
+                    $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19,
+                    $20
+                );",
+            url,
+            r.owner,
+            r.name,
+            r.description,
+            r.html_link,
+            r.website,
+            now,
+            now,
+            r.import,
+            stars,
+            forks,
+            r.metadata.fork,
+            r.metadata.parent,
+            r.metadata.mirror,
+            r.metadata.archived,
+            r.metadata.default_branch,
+            r.metadata.clone_url,
+            r.metadata.created_at,
+            r.metadata.updated_at,
+            r.metadata.language,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(map_register_err)?;
+
+        if let Some(description) = r.description {
+            self.add_word_to_mini_index(description).await?;
+        }
+        self.add_word_to_mini_index(r.name).await?;
+        if let Some(website) = r.website {
+            self.add_word_to_mini_index(website).await?;
+        }
+
+        if let Some(topics) = &r.tags {
+            self.add_repository_topics(r.html_link, topics).await?;
+        }
+        self.index_repository(r.html_link).await?;
+
+        Ok(())
+    }
+
+    /// update metadata of an existing repository
+    async fn update_repository(&self, r: &AddRepository) -> DBResult<()> {
+        let now = now_unix_time_stamp();
+        let url = db_core::clean_url(&r.url);
+        let stars = r.metadata.stars.map(|s| s as i64);
+        let forks = r.metadata.forks.map(|f| f as i64);
+        let res = sqlx::query!(
+            "UPDATE starchart_repositories
+                SET description = $1, html_url = $2, website = $3, last_crawl = $4,
+                    stars = $5, forks = $6, fork = $7, parent_html_url = $8, mirror = $9,
+                    archived = $10, default_branch = $11, clone_url = $12,
+                    created_on_forge = $13, updated_on_forge = $14, language = $15
+                WHERE
+                    name = $16
+                AND
+                    owner_id = ( SELECT ID FROM starchart_users WHERE username = $17)
+                AND
+                    hostname_id = (SELECT ID FROM starchart_forges WHERE hostname = $18)",
+            r.description,
+            r.html_link,
+            r.website,
+            now,
+            stars,
+            forks,
+            r.metadata.fork,
+            r.metadata.parent,
+            r.metadata.mirror,
+            r.metadata.archived,
+            r.metadata.default_branch,
+            r.metadata.clone_url,
+            r.metadata.created_at,
+            r.metadata.updated_at,
+            r.metadata.language,
+            r.name,
+            r.owner,
+            url,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(map_register_err)?;
+        if res.rows_affected() == 0 {
+            return Err(DBError::DBError(Box::new(Error::RowNotFound)));
+        }
+
+        if let Some(description) = r.description {
+            self.add_word_to_mini_index(description).await?;
+        }
+        if let Some(website) = r.website {
+            self.add_word_to_mini_index(website).await?;
+        }
+
+        sqlx::query!(
+            "DELETE FROM starchart_repository_topic_mapping
+                WHERE repository_id = (SELECT ID FROM starchart_repositories WHERE html_url = $1);",
+            r.html_link
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| DBError::DBError(Box::new(e)))?;
+        if let Some(topics) = &r.tags {
+            self.add_repository_topics(r.html_link, topics).await?;
+        }
+        self.index_repository(r.html_link).await?;
+
+        Ok(())
+    }
+
+    /// delete user
+    async fn delete_user(&self, username: &str, url: &Url) -> DBResult<()> {
+        let user = self.get_user(username, url).await?;
+        self.rm_word_from_mini_index(&user.username).await?;
+
+        let url = db_core::clean_url(url);
+        sqlx::query!(
+            " DELETE FROM starchart_users WHERE username = $1 AND
+                hostname_id = (SELECT ID FROM starchart_forges WHERE hostname = $2)",
+            username,
+            url
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(map_register_err)?;
+        Ok(())
+    }
+
+    /// delete repository
+    async fn delete_repository(&self, owner: &str, name: &str, url: &Url) -> DBResult<()> {
+        let url = db_core::clean_url(url);
+        sqlx::query!(
+            " DELETE FROM starchart_repositories
+                    WHERE
+                        name = $1
+                    AND
+                        owner_id = ( SELECT ID FROM starchart_users WHERE username = $2)
+                    AND
+                        hostname_id = (SELECT ID FROM starchart_forges WHERE hostname = $3)",
+            name,
+            owner,
+            url
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(map_register_err)?;
+        Ok(())
+    }
+
+    /// Get repositories of a forge that weren't seen by a crawl since `seen_since`
+    async fn get_unseen_repositories(
+        &self,
+        url: &Url,
+        seen_since: i64,
+    ) -> DBResult<Vec<Repository>> {
+        let url = db_core::clean_url(url);
+        let mut db_res = sqlx::query_as!(
+            InnerRepository,
+            "SELECT
+                starchart_forges.hostname,
+                starchart_users.username,
+                starchart_repositories.name,
+                starchart_repositories.description,
+                starchart_repositories.html_url,
+                starchart_repositories.ID,
+                starchart_repositories.website,
+                starchart_repositories.imported,
+                starchart_repositories.stars,
+                starchart_repositories.forks,
+                starchart_repositories.fork,
+                starchart_repositories.parent_html_url,
+                starchart_repositories.mirror,
+                starchart_repositories.archived,
+                starchart_repositories.default_branch,
+                starchart_repositories.clone_url,
+                starchart_repositories.created_on_forge,
+                starchart_repositories.updated_on_forge,
+                starchart_repositories.language
+            FROM
+                starchart_repositories
+            INNER JOIN
+                starchart_forges
+            ON
+                starchart_repositories.hostname_id = starchart_forges.id
+            INNER JOIN
+                starchart_users
+            ON
+                starchart_repositories.owner_id =  starchart_users.id
+            WHERE
+                starchart_forges.hostname = $1
+            AND
+                starchart_repositories.imported = false
+            AND
+                starchart_repositories.last_crawl < $2
+            ORDER BY
+                starchart_repositories.ID
+                ;",
+            url,
+            seen_since,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_register_err)?;
+
+        let mut res = Vec::with_capacity(db_res.len());
+        for repo in db_res.drain(0..) {
+            let tags = self.get_repository_topics(repo.id).await?;
+            res.push(repo.into_repository(tags));
+        }
+
+        Ok(res)
+    }
+
+    /// Get users of a forge that weren't seen by a crawl since `seen_since`
+    async fn get_unseen_users(&self, url: &Url, seen_since: i64) -> DBResult<Vec<User>> {
+        struct InnerUser {
+            username: String,
+            profile_photo_html_url: Option<String>,
+            html_url: String,
+            imported: bool,
+        }
+
+        let url = db_core::clean_url(url);
+        let mut db_res = sqlx::query_as!(
+            InnerUser,
+            "SELECT username, html_url, profile_photo_html_url, imported FROM starchart_users
+                WHERE
+                    hostname_id = (SELECT ID FROM starchart_forges WHERE hostname = $1)
+                AND
+                    imported = false
+                AND
+                    last_crawl_on < $2
+                ORDER BY ID",
+            url,
+            seen_since,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| DBError::DBError(Box::new(e)))?;
+
+        let mut res = Vec::with_capacity(db_res.len());
+        for u in db_res.drain(0..) {
+            res.push(User {
+                url: url.clone(),
+                username: u.username,
+                html_link: u.html_url,
+                profile_photo: u.profile_photo_html_url,
+                import: u.imported,
+            });
+        }
+        Ok(res)
+    }
+
+    /// Get all repositories
+    async fn get_all_repositories(&self, offset: u32, limit: u32) -> DBResult<Vec<Repository>> {
+        let limit = limit as i64;
+        let offset = offset as i64;
+        let mut db_res = sqlx::query_as!(
+            InnerRepository,
+            "SELECT
+                starchart_forges.hostname,
+                starchart_users.username,
+                starchart_repositories.name,
+                starchart_repositories.description,
+                starchart_repositories.html_url,
+                starchart_repositories.ID,
+                starchart_repositories.website,
+                starchart_repositories.imported,
+                starchart_repositories.stars,
+                starchart_repositories.forks,
+                starchart_repositories.fork,
+                starchart_repositories.parent_html_url,
+                starchart_repositories.mirror,
+                starchart_repositories.archived,
+                starchart_repositories.default_branch,
+                starchart_repositories.clone_url,
+                starchart_repositories.created_on_forge,
+                starchart_repositories.updated_on_forge,
+                starchart_repositories.language
+            FROM
+                starchart_repositories
+            INNER JOIN
+                starchart_forges
+            ON
+                starchart_repositories.hostname_id = starchart_forges.id
+            INNER JOIN
+                starchart_users
+            ON
+                starchart_repositories.owner_id =  starchart_users.id
+            ORDER BY
+                starchart_repositories.ID
+            LIMIT $1 OFFSET $2
+                ;",
+            limit,
+            offset,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_register_err)?;
+
+        let mut res = Vec::with_capacity(db_res.len());
+        for repo in db_res.drain(0..) {
+            let tags = self.get_repository_topics(repo.id).await?;
+            res.push(repo.into_repository(tags));
+        }
+
+        Ok(res)
+    }
+
+    /// Get repositories that match a search query
+    async fn query_repositories(
+        &self,
+        query: &SearchQuery,
+        offset: u32,
+        limit: u32,
+    ) -> DBResult<Vec<Repository>> {
+        if query.is_empty() {
+            return Ok(Vec::default());
+        }
+        // terms only contain letters and digits, so they can't be read as tsquery operators
+        let terms = if query.terms.is_empty() {
+            None
+        } else {
+            Some(
+                query
+                    .terms
+                    .iter()
+                    .map(|term| format!("{term}:*"))
+                    .collect::<Vec<String>>()
+                    .join(" & "),
+            )
+        };
+        let limit = limit as i64;
+        let offset = offset as i64;
+
+        let mut db_res = sqlx::query_as!(
+            InnerRepository,
+            "SELECT
+                starchart_forges.hostname,
+                starchart_users.username,
+                starchart_repositories.name,
+                starchart_repositories.description,
+                starchart_repositories.html_url,
+                starchart_repositories.ID,
+                starchart_repositories.website,
+                starchart_repositories.imported,
+                starchart_repositories.stars,
+                starchart_repositories.forks,
+                starchart_repositories.fork,
+                starchart_repositories.parent_html_url,
+                starchart_repositories.mirror,
+                starchart_repositories.archived,
+                starchart_repositories.default_branch,
+                starchart_repositories.clone_url,
+                starchart_repositories.created_on_forge,
+                starchart_repositories.updated_on_forge,
+                starchart_repositories.language
+            FROM
+                starchart_repositories
+            INNER JOIN
+                starchart_forges
+            ON
+                starchart_repositories.hostname_id = starchart_forges.id
+            INNER JOIN
+                starchart_users
+            ON
+                starchart_repositories.owner_id =  starchart_users.id
+            WHERE
+                ($1::TEXT IS NULL OR starchart_repositories.search_vector @@ to_tsquery('english', $1))
+            AND
+                ($2::TEXT IS NULL OR starchart_forges.hostname IN ('https://' || $2 || '/', 'http://' || $2 || '/'))
+            AND
+                ($3::TEXT IS NULL OR lower(starchart_users.username) = $3)
+            AND
+                ($4::TEXT IS NULL OR lower(starchart_repositories.language) = $4)
+            AND
+                ($5::BOOLEAN IS NULL OR starchart_repositories.archived = $5)
+            AND NOT EXISTS (
+                SELECT 1 FROM unnest($6::TEXT[]) AS topic
+                WHERE topic NOT IN (
+                    SELECT
+                        lower(starchart_project_topics.name)
+                    FROM
+                        starchart_project_topics
+                    INNER JOIN
+                        starchart_repository_topic_mapping
+                    ON
+                        starchart_repository_topic_mapping.topic_id = starchart_project_topics.ID
+                    WHERE
+                        starchart_repository_topic_mapping.repository_id = starchart_repositories.ID
+                )
+            )
+            ORDER BY
+                ts_rank(starchart_repositories.search_vector, to_tsquery('english', $1)) DESC,
+                starchart_repositories.ID
+            LIMIT $7 OFFSET $8
+                ;",
+            terms,
+            query.forge,
+            query.owner,
+            query.lang,
+            query.archived,
+            &query.topics,
+            limit,
+            offset,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_register_err)?;
+
+        let mut res = Vec::with_capacity(db_res.len());
+        for repo in db_res.drain(0..) {
+            let tags = self.get_repository_topics(repo.id).await?;
+            res.push(repo.into_repository(tags));
+        }
+        Ok(res)
+    }
+
+    /// Add Starchart instance to introducer
+    async fn add_starchart_to_introducer(&self, url: &Url) -> DBResult<()> {
+        let url = url.as_str();
+        sqlx::query!(
+            "INSERT INTO
+                starchart_introducer (instance_url)
+            VALUES ($1)
+            ON CONFLICT DO NOTHING;",
+            url
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(map_register_err)?;
+        Ok(())
+    }
+
+    /// Get all introduced Starchart instances
+    async fn get_all_introduced_starchart_instances(
+        &self,
+        offset: u32,
+        limit: u32,
+    ) -> DBResult<Vec<Starchart>> {
+        let limit = limit as i64;
+        let offset = offset as i64;
+        let s = sqlx::query_as!(
+            Starchart,
+            "SELECT
+                instance_url
+            FROM
+                starchart_introducer
+            ORDER BY
+                ID
+            LIMIT $1 OFFSET $2;
+        ",
+            limit,
+            offset
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| DBError::DBError(Box::new(e)))?;
+        Ok(s)
+    }
+
+    /// Add word to mini index
+    async fn add_word_to_mini_index(&self, word: &str) -> DBResult<()> {
+        sqlx::query!(
+            "INSERT INTO starchart_mini_index ( word )
+            VALUES ( $1)
+            ON CONFLICT DO NOTHING;",
+            word,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(map_register_err)?;
+        Ok(())
+    }
+
+    /// Remove word from mini index
+    async fn rm_word_from_mini_index(&self, word: &str) -> DBResult<()> {
+        sqlx::query!("DELETE FROM starchart_mini_index WHERE word = ($1)", word)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| DBError::DBError(Box::new(e)))?;
+        Ok(())
+    }
+
+    /// Check if word exists in mini index
+    async fn is_word_mini_indexed(&self, word: &str) -> DBResult<bool> {
+        match sqlx::query!("SELECT ID FROM starchart_mini_index WHERE word = $1", word)
+            .fetch_one(&self.pool)
+            .await
+        {
+            Ok(_) => Ok(true),
+            Err(Error::RowNotFound) => Ok(false),
+            Err(e) => Err(DBError::DBError(Box::new(e))),
+        }
+    }
+
+    /// consolidate and export mini index
+    async fn export_mini_index(&self) -> DBResult<String> {
+        struct Words {
+            word: String,
+        }
+        let mut words = sqlx::query_as!(Words, "SELECT word FROM starchart_mini_index ORDER BY ID")
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| DBError::DBError(Box::new(e)))?;
+        let mut mini_index = String::default();
+        words.drain(0..).for_each(|w| {
+            mini_index = if mini_index.is_empty() {
+                w.word
+            } else {
+                format!("{mini_index} {}", w.word)
+            }
+        });
+        Ok(mini_index)
+    }
+
+    /// Import mini-index
+    async fn import_mini_index(
+        &self,
+        starchart_instance_url: &Url,
+        mini_index: &str,
+    ) -> DBResult<()> {
+        // delete old index before importing fresh index
+        let _ = self.rm_imported_mini_index(starchart_instance_url).await;
+        let url = db_core::clean_url(starchart_instance_url);
+        sqlx::query!(
+            "INSERT INTO
+                starchart_federated_mini_index
+            (mini_index, starchart_instance)
+            VALUES ($1, (SELECT ID FROM starchart_introducer WHERE instance_url = $2));",
+            mini_index,
+            url
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(map_register_err)?;
+        Ok(())
+    }
+
+    /// Delete imported mini-index
+    async fn rm_imported_mini_index(&self, starchart_instance_url: &Url) -> DBResult<()> {
+        let url = db_core::clean_url(starchart_instance_url);
+        sqlx::query!(
+            "DELETE FROM starchart_federated_mini_index
+             WHERE
+                starchart_instance = (
+                    SELECT ID FROM starchart_introducer
+                    WHERE instance_url = $1
+                )",
+            url
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| DBError::DBError(Box::new(e)))?;
+        Ok(())
+    }
+    /// Search mini index
+    async fn search_mini_index(&self, query: &str) -> DBResult<Vec<String>> {
+        self.get_federated_mini_index_matrches(query).await
+    }
+
+    /// Mark a Starchart instance as imported
+    async fn record_starchart_imports(&self, starchart_url: &Url) -> DBResult<()> {
+        let url = db_core::clean_url(starchart_url);
+        sqlx::query!(
+            "INSERT INTO
+                starchart_imported_starcharts (starchart_instance)
+            VALUES ((SELECT ID FROM starchart_introducer WHERE instance_url = $1))
+            ON CONFLICT DO NOTHING;",
+            url
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(map_register_err)?;
+        Ok(())
+    }
+
+    /// Unmark a Starchart instance as imported
+    async fn rm_starchart_import(&self, starchart_url: &Url) -> DBResult<()> {
+        let url = db_core::clean_url(starchart_url);
+        sqlx::query!(
+            "DELETE FROM
+                starchart_imported_starcharts
+            WHERE starchart_instance =(SELECT ID FROM starchart_introducer WHERE instance_url = $1);",
+            url
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(map_register_err)?;
+        Ok(())
+    }
+
+    /// Check if Starchart instance is imported
+    async fn is_starchart_imported(&self, starchart_url: &Url) -> DBResult<bool> {
+        let url = db_core::clean_url(starchart_url);
+
+        match sqlx::query!(
+            "SELECT
+                ID
+            FROM
+                starchart_imported_starcharts
+            WHERE
+                starchart_instance = (SELECT ID FROM starchart_introducer WHERE instance_url = $1);",
+            url)
+            .fetch_one(&self.pool)
+            .await
+        {
+            Ok(_) => Ok(true),
+            Err(Error::RowNotFound) => Ok(false),
+            Err(e) => Err(DBError::DBError(Box::new(e))),
+        }
+    }
+}
+
+fn now_unix_time_stamp() -> i64 {
+    OffsetDateTime::now_utc().unix_timestamp()
+}
+
+struct InnerForge {
+    hostname: String,
+    last_crawl_on: Option<i64>,
+    last_full_crawl_on: Option<i64>,
+    name: String,
+    instance_url: Option<String>,
+}
+
+impl From<InnerForge> for Forge {
+    fn from(f: InnerForge) -> Self {
+        Self {
+            url: f.hostname,
+            last_crawl_on: f.last_crawl_on,
+            last_full_crawl_on: f.last_full_crawl_on,
+            forge_type: ForgeImplementation::from_str(&f.name).unwrap(),
+            starchart_url: f.instance_url,
+        }
+    }
+}
+
+struct InnerCrawlSchedule {
+    hostname: String,
+    next_crawl_on: i64,
+    priority: i64,
+    crawl_interval: Option<i64>,
+    last_started_on: Option<i64>,
+    last_finished_on: Option<i64>,
+    last_error: Option<String>,
+    consecutive_failures: i64,
+    disabled: bool,
+}
+
+impl From<InnerCrawlSchedule> for CrawlSchedule {
+    fn from(s: InnerCrawlSchedule) -> Self {
+        let last_outcome = s.last_finished_on.map(|_| match s.last_error {
+            Some(e) => CrawlOutcome::Failed(e),
+            None => CrawlOutcome::Success,
+        });
+        Self {
+            url: s.hostname,
+            next_crawl_on: s.next_crawl_on,
+            priority: s.priority as u32,
+            interval: s.crawl_interval.map(|i| i as u64),
+            last_started_on: s.last_started_on,
+            last_finished_on: s.last_finished_on,
+            last_outcome,
+            consecutive_failures: s.consecutive_failures as u32,
+            disabled: s.disabled,
+        }
+    }
+}
+
+struct InnerRepository {
+    /// html link to the repository
+    pub html_url: String,
+    /// url of the forge instance: with scheme but remove trailing slash
+    /// url can be derived  from html_link also, but used to link to user's forge instance
+    pub hostname: String,
+    /// repository name
+    pub name: String,
+    /// repository owner
+    pub username: String,
+    /// repository description, if any
+    pub description: Option<String>,
+    /// repository website, if any
+    pub website: Option<String>,
+    pub id: i64,
+    pub imported: bool,
+    pub stars: Option<i64>,
+    pub forks: Option<i64>,
+    pub fork: bool,
+    pub parent_html_url: Option<String>,
+    pub mirror: bool,
+    pub archived: bool,
+    pub default_branch: Option<String>,
+    pub clone_url: Option<String>,
+    pub created_on_forge: Option<i64>,
+    pub updated_on_forge: Option<i64>,
+    pub language: Option<String>,
+}
+
+impl InnerRepository {
+    fn into_repository(self, tags: Option<Vec<String>>) -> Repository {
+        Repository {
+            html_url: self.html_url,
+            url: self.hostname,
+            name: self.name,
+            username: self.username,
+            description: self.description,
+            website: self.website,
+            tags,
+            import: self.imported,
+            metadata: RepositoryMetadata {
+                stars: self.stars.map(|s| s as u32),
+                forks: self.forks.map(|f| f as u32),
+                fork: self.fork,
+                parent: self.parent_html_url,
+                mirror: self.mirror,
+                archived: self.archived,
+                default_branch: self.default_branch,
+                clone_url: self.clone_url,
+                created_at: self.created_on_forge,
+                updated_at: self.updated_on_forge,
+                language: self.language,
+            },
+        }
+    }
+}
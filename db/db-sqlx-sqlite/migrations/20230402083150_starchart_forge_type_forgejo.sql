@@ -0,0 +1 @@
+INSERT OR IGNORE INTO starchart_forge_type (name) VALUES('forgejo');
@@ -0,0 +1 @@
+ALTER TABLE starchart_forges ADD COLUMN last_full_crawl_on INTEGER DEFAULT NULL;
@@ -0,0 +1,10 @@
+ALTER TABLE starchart_repositories ADD COLUMN stars INTEGER DEFAULT NULL;
+ALTER TABLE starchart_repositories ADD COLUMN forks INTEGER DEFAULT NULL;
+ALTER TABLE starchart_repositories ADD COLUMN fork BOOLEAN NOT NULL DEFAULT false;
+ALTER TABLE starchart_repositories ADD COLUMN parent_html_url TEXT DEFAULT NULL;
+ALTER TABLE starchart_repositories ADD COLUMN mirror BOOLEAN NOT NULL DEFAULT false;
+ALTER TABLE starchart_repositories ADD COLUMN archived BOOLEAN NOT NULL DEFAULT false;
+ALTER TABLE starchart_repositories ADD COLUMN default_branch TEXT DEFAULT NULL;
+ALTER TABLE starchart_repositories ADD COLUMN clone_url TEXT DEFAULT NULL;
+ALTER TABLE starchart_repositories ADD COLUMN created_on_forge INTEGER DEFAULT NULL;
+ALTER TABLE starchart_repositories ADD COLUMN updated_on_forge INTEGER DEFAULT NULL;
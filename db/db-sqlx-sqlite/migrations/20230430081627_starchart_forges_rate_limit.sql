@@ -0,0 +1,2 @@
+ALTER TABLE starchart_forges ADD COLUMN rate_limit_delay INTEGER DEFAULT NULL;
+ALTER TABLE starchart_forges ADD COLUMN rate_limit_blocked_until INTEGER DEFAULT NULL;
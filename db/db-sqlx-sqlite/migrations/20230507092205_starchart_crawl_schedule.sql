@@ -0,0 +1,13 @@
+CREATE TABLE IF NOT EXISTS starchart_crawl_schedule (
+	hostname TEXT NOT NULL UNIQUE,
+	next_crawl_on INTEGER NOT NULL,
+	priority INTEGER NOT NULL DEFAULT 0,
+	crawl_interval INTEGER DEFAULT NULL,
+	last_started_on INTEGER DEFAULT NULL,
+	last_finished_on INTEGER DEFAULT NULL,
+	last_error TEXT DEFAULT NULL,
+	ID INTEGER PRIMARY KEY NOT NULL
+);
+
+INSERT OR IGNORE INTO starchart_crawl_schedule (hostname, next_crawl_on)
+	SELECT hostname, 0 FROM starchart_forges WHERE starchart_instance IS NULL;
@@ -0,0 +1,4 @@
+ALTER TABLE starchart_forges ADD COLUMN checkpoint_token TEXT DEFAULT NULL;
+ALTER TABLE starchart_forges ADD COLUMN checkpoint_page INTEGER DEFAULT NULL;
+ALTER TABLE starchart_forges ADD COLUMN checkpoint_started_on INTEGER DEFAULT NULL;
+ALTER TABLE starchart_forges ADD COLUMN checkpoint_since INTEGER DEFAULT NULL;
@@ -0,0 +1,19 @@
+CREATE TABLE IF NOT EXISTS starchart_crawl_runs (
+	hostname TEXT NOT NULL,
+	started_on INTEGER NOT NULL,
+	finished_on INTEGER NOT NULL,
+	pages INTEGER NOT NULL DEFAULT 0,
+	repositories_added INTEGER NOT NULL DEFAULT 0,
+	repositories_updated INTEGER NOT NULL DEFAULT 0,
+	repositories_removed INTEGER NOT NULL DEFAULT 0,
+	users_added INTEGER NOT NULL DEFAULT 0,
+	users_updated INTEGER NOT NULL DEFAULT 0,
+	users_removed INTEGER NOT NULL DEFAULT 0,
+	error TEXT DEFAULT NULL,
+	ID INTEGER PRIMARY KEY NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS starchart_crawl_runs_hostname ON starchart_crawl_runs(hostname);
+
+ALTER TABLE starchart_crawl_schedule ADD COLUMN consecutive_failures INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE starchart_crawl_schedule ADD COLUMN disabled BOOLEAN NOT NULL DEFAULT FALSE;
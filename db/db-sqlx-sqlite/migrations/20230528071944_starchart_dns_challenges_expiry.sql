@@ -0,0 +1 @@
+ALTER TABLE starchart_dns_challenges ADD COLUMN expires_on INTEGER NOT NULL DEFAULT 0;
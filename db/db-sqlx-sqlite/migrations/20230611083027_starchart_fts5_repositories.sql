@@ -0,0 +1,33 @@
+DROP TABLE IF EXISTS fts_repositories;
+DROP TABLE IF EXISTS fts_project_topics;
+DROP TABLE IF EXISTS fts_users;
+
+-- rowid is the ID of the repository in starchart_repositories
+CREATE VIRTUAL TABLE IF NOT EXISTS fts_repositories USING fts5(
+	name,
+	description,
+	website,
+	topics,
+	username
+);
+
+INSERT INTO fts_repositories (rowid, name, description, website, topics, username)
+	SELECT
+		starchart_repositories.ID,
+		starchart_repositories.name,
+		starchart_repositories.description,
+		starchart_repositories.website,
+		(
+			SELECT group_concat(starchart_project_topics.name, ' ')
+			FROM starchart_project_topics
+			INNER JOIN starchart_repository_topic_mapping
+			ON starchart_repository_topic_mapping.topic_id = starchart_project_topics.ID
+			WHERE starchart_repository_topic_mapping.repository_id = starchart_repositories.ID
+		),
+		starchart_users.username
+	FROM
+		starchart_repositories
+	INNER JOIN
+		starchart_users
+	ON
+		starchart_repositories.owner_id = starchart_users.ID;
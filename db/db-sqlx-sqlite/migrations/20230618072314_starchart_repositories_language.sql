@@ -0,0 +1 @@
+ALTER TABLE starchart_repositories ADD COLUMN language TEXT DEFAULT NULL;
@@ -28,7 +28,7 @@ pub fn map_register_err(e: Error) -> DBError {
                 DBError::DuplicateChallengeHostname
             } else if msg.contains("starchart_forges.hostname") {
                 DBError::DuplicateHostname
-            } else if msg.contains("starchart_dns_challenges.challenge") {
+            } else if msg.contains("starchart_dns_challenges.value") {
                 DBError::DuplicateChallengeText
             } else if msg.contains("starchart_users.html_url") {
                 DBError::DuplicateUserLink
@@ -95,18 +95,10 @@ impl Migrate for Database {
             .run(&self.pool)
             .await
             .map_err(|e| DBError::DBError(Box::new(e)))?;
-
-        self.init_project_topics_fts().await?;
-        self.init_username_fts().await?;
-        self.init_repository_fts().await?;
         Ok(())
     }
 }
 
-struct FTSRepository {
-    html_url: String,
-}
-
 impl Database {
     async fn get_federated_mini_index_matrches(&self, query: &str) -> DBResult<Vec<String>> {
         struct Match {
@@ -137,71 +129,44 @@ impl Database {
 
         Ok(res)
     }
-    async fn get_fts_repository(&self, query: &str) -> DBResult<Vec<FTSRepository>> {
-        let fts_repos = sqlx::query_as_unchecked!(
-            FTSRepository,
-            "SELECT html_url FROM fts_repositories WHERE html_url MATCH $1;",
-            query
-        )
-        .fetch_all(&self.pool)
-        .await
-        .map_err(|e| DBError::DBError(Box::new(e)))?;
-        Ok(fts_repos)
-    }
-
-    async fn new_fts_repositories(
-        &self,
-        name: &str,
-        description: Option<&str>,
-        website: Option<&str>,
-        html_url: &str,
-    ) -> DBResult<()> {
-        if !self.get_fts_repository(html_url).await?.is_empty() {
-            return Ok(());
-        }
+    /// Index a repository for full-text search, replacing its old entry. Must be called after
+    /// changes to the repository or its topics
+    async fn index_repository(&self, html_url: &str) -> DBResult<()> {
         sqlx::query!(
-            "INSERT OR IGNORE INTO fts_repositories ( name, description, website, html_url ) 
-            VALUES ( $1, $2, $3, $4 );",
-            name,
-            description,
-            website,
+            "DELETE FROM fts_repositories
+                WHERE rowid = (SELECT ID FROM starchart_repositories WHERE html_url = $1);",
             html_url
         )
         .execute(&self.pool)
         .await
-        .map_err(map_register_err)?;
-        Ok(())
-    }
-
-    async fn init_repository_fts(&self) -> DBResult<()> {
-        let limit = 50;
-        let mut page = 0;
-        loop {
-            let offset = page * limit;
-            let mut repositories = self.get_all_repositories(offset, limit).await?;
-            if repositories.is_empty() {
-                break;
-            }
-
-            for repo in repositories.drain(0..) {
-                self.new_fts_repositories(
-                    &repo.name,
-                    repo.description.as_deref(),
-                    repo.website.as_deref(),
-                    &repo.html_url,
-                )
-                .await?;
-            }
-            page += 1;
-        }
-
-        Ok(())
-    }
+        .map_err(|e| DBError::DBError(Box::new(e)))?;
 
-    async fn new_fts_user(&self, username: &str) -> DBResult<()> {
         sqlx::query!(
-            "INSERT OR IGNORE INTO fts_users ( username ) VALUES ( $1 );",
-            username,
+            "INSERT INTO fts_repositories (rowid, name, description, website, topics, username)
+                SELECT
+                    starchart_repositories.ID,
+                    starchart_repositories.name,
+                    starchart_repositories.description,
+                    starchart_repositories.website,
+                    (
+                        SELECT group_concat(starchart_project_topics.name, ' ')
+                        FROM starchart_project_topics
+                        INNER JOIN starchart_repository_topic_mapping
+                        ON starchart_repository_topic_mapping.topic_id = starchart_project_topics.ID
+                        WHERE
+                            starchart_repository_topic_mapping.repository_id
+                                = starchart_repositories.ID
+                    ),
+                    starchart_users.username
+                FROM
+                    starchart_repositories
+                INNER JOIN
+                    starchart_users
+                ON
+                    starchart_repositories.owner_id = starchart_users.ID
+                WHERE
+                    starchart_repositories.html_url = $1;",
+            html_url
         )
         .execute(&self.pool)
         .await
@@ -209,76 +174,56 @@ impl Database {
         Ok(())
     }
 
-    async fn init_username_fts(&self) -> DBResult<()> {
-        struct User {
-            username: String,
-        }
-        let limit = 50;
-        let mut page = 0;
-        loop {
-            let offset = page * limit;
-
-            let mut users = sqlx::query_as!(
-                User,
-                "SELECT username FROM starchart_users ORDER BY ID LIMIT $1 OFFSET $2",
-                limit,
-                offset,
+    async fn add_repository_topics(&self, html_url: &str, topics: &[&str]) -> DBResult<()> {
+        for topic in topics.iter() {
+            sqlx::query!(
+                "INSERT OR IGNORE INTO starchart_project_topics ( name ) VALUES ( $1 );",
+                topic,
             )
-            .fetch_all(&self.pool)
+            .execute(&self.pool)
             .await
-            .map_err(|e| DBError::DBError(Box::new(e)))?;
-            if users.is_empty() {
-                break;
-            }
-
-            for user in users.drain(0..) {
-                self.new_fts_user(&user.username).await?;
-            }
-            page += 1;
-        }
+            .map_err(map_register_err)?;
 
-        Ok(())
-    }
+            self.add_word_to_mini_index(topic).await?;
 
-    async fn new_fts_topic(&self, name: &str) -> DBResult<()> {
-        sqlx::query!(
-            "INSERT OR IGNORE INTO fts_project_topics ( name ) VALUES ( $1 );",
-            name,
-        )
-        .execute(&self.pool)
-        .await
-        .map_err(map_register_err)?;
+            sqlx::query!(
+                "
+                    INSERT INTO starchart_repository_topic_mapping ( topic_id, repository_id )
+                    VALUES (
+                        (SELECT ID FROM starchart_project_topics WHERE name = $1),
+                        (SELECT ID FROM starchart_repositories WHERE html_url = $2)
+                    );",
+                topic,
+                html_url,
+            )
+            .execute(&self.pool)
+            .await
+            .map_err(map_register_err)?;
+        }
         Ok(())
     }
 
-    async fn init_project_topics_fts(&self) -> DBResult<()> {
+    async fn get_repository_topics(&self, repository_id: i64) -> DBResult<Option<Vec<String>>> {
         struct Topic {
             name: String,
         }
-        let limit = 50;
-        let mut page = 0;
-        loop {
-            let offset = page * limit;
-            let mut topics = sqlx::query_as!(
-                Topic,
-                "SELECT name FROM starchart_project_topics ORDER BY ID LIMIT $1 OFFSET $2;",
-                limit,
-                offset
-            )
-            .fetch_all(&self.pool)
-            .await
-            .map_err(|e| DBError::DBError(Box::new(e)))?;
-            if topics.is_empty() {
-                break;
-            }
 
-            for topic in topics.drain(0..) {
-                self.new_fts_topic(&topic.name).await?;
-            }
-            page += 1;
-        }
+        let mut db_topics = sqlx::query_as!(
+            Topic,
+            "SELECT name FROM starchart_project_topics WHERE ID IN (
+                SELECT topic_id FROM starchart_repository_topic_mapping WHERE repository_id = $1
+            )",
+            repository_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_register_err)?;
 
-        Ok(())
+        if db_topics.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(db_topics.drain(0..).map(|t| t.name).collect()))
+        }
     }
 }
 
@@ -298,10 +243,36 @@ impl SCDatabase for Database {
     /// delete forge instance
     async fn delete_forge_instance(&self, url: &Url) -> DBResult<()> {
         let url = db_core::clean_url(url);
+        // repositories of the forge are removed by cascade, their search index entries aren't
+        sqlx::query!(
+            "DELETE FROM fts_repositories WHERE rowid IN (
+                SELECT ID FROM starchart_repositories WHERE hostname_id = (
+                    SELECT ID FROM starchart_forges WHERE hostname = $1
+                )
+            );",
+            url
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| DBError::DBError(Box::new(e)))?;
         sqlx::query!("DELETE FROM starchart_forges WHERE hostname = ($1)", url,)
             .execute(&self.pool)
             .await
             .map_err(|e| DBError::DBError(Box::new(e)))?;
+        sqlx::query!(
+            "DELETE FROM starchart_crawl_schedule WHERE hostname = ($1)",
+            url,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| DBError::DBError(Box::new(e)))?;
+        sqlx::query!(
+            "DELETE FROM starchart_crawl_runs WHERE hostname = ($1)",
+            url,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| DBError::DBError(Box::new(e)))?;
         self.rm_word_from_mini_index(&url).await?;
         Ok(())
     }
@@ -314,12 +285,13 @@ impl SCDatabase for Database {
         if let Some(instance_url) = f.starchart_url {
             sqlx::query!(
                 "INSERT OR IGNORE INTO starchart_forges
-                    (hostname, verified_on, forge_type, starchart_instance)
+                    (hostname, verified_on, forge_type, starchart_instance, imported)
                 VALUES (
                         $1,
                         $2,
                         (SELECT ID FROM starchart_forge_type WHERE name = $3),
-                        (SELECT ID FROM starchart_introducer WHERE instance_url = $4)
+                        (SELECT ID FROM starchart_introducer WHERE instance_url = $4),
+                        true
                     )",
                 url,
                 now,
@@ -346,6 +318,17 @@ impl SCDatabase for Database {
             .execute(&self.pool)
             .await
             .map_err(map_register_err)?;
+
+            // forges that aren't imported are crawled by this instance
+            sqlx::query!(
+                "INSERT OR IGNORE INTO starchart_crawl_schedule (hostname, next_crawl_on)
+                VALUES ($1, $2)",
+                url,
+                now
+            )
+            .execute(&self.pool)
+            .await
+            .map_err(|e| DBError::DBError(Box::new(e)))?;
         }
 
         self.add_word_to_mini_index(&url).await?;
@@ -361,6 +344,7 @@ impl SCDatabase for Database {
             "SELECT 
                 hostname,
                 last_crawl_on,
+                last_full_crawl_on,
                 starchart_introducer.instance_url,
                 starchart_forge_type.name
             FROM
@@ -385,6 +369,394 @@ impl SCDatabase for Database {
         Ok(f.into())
     }
 
+    /// Record completed crawl of a forge
+    async fn record_forge_crawl(&self, url: &Url, crawled_on: i64, full: bool) -> DBResult<()> {
+        let url = db_core::clean_url(url);
+        if full {
+            sqlx::query!(
+                "UPDATE starchart_forges
+                SET last_crawl_on = $1, last_full_crawl_on = $1
+                WHERE hostname = $2",
+                crawled_on,
+                url
+            )
+            .execute(&self.pool)
+            .await
+            .map_err(|e| DBError::DBError(Box::new(e)))?;
+        } else {
+            sqlx::query!(
+                "UPDATE starchart_forges SET last_crawl_on = $1 WHERE hostname = $2",
+                crawled_on,
+                url
+            )
+            .execute(&self.pool)
+            .await
+            .map_err(|e| DBError::DBError(Box::new(e)))?;
+        }
+        Ok(())
+    }
+
+    /// Get persisted rate limit state of a forge, if any
+    async fn get_forge_rate_limit(&self, url: &Url) -> DBResult<Option<ForgeRateLimit>> {
+        struct InnerRateLimit {
+            rate_limit_delay: Option<i64>,
+            rate_limit_blocked_until: Option<i64>,
+        }
+
+        let url = db_core::clean_url(url);
+        let res = sqlx::query_as!(
+            InnerRateLimit,
+            "SELECT rate_limit_delay, rate_limit_blocked_until FROM starchart_forges
+                WHERE hostname = $1",
+            url
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| DBError::DBError(Box::new(e)))?;
+        Ok(res.rate_limit_delay.map(|delay| ForgeRateLimit {
+            delay: delay as u64,
+            blocked_until: res.rate_limit_blocked_until,
+        }))
+    }
+
+    /// Persist rate limit state of a forge
+    async fn set_forge_rate_limit(&self, url: &Url, rate_limit: &ForgeRateLimit) -> DBResult<()> {
+        let url = db_core::clean_url(url);
+        let delay = rate_limit.delay as i64;
+        sqlx::query!(
+            "UPDATE starchart_forges
+                SET rate_limit_delay = $1, rate_limit_blocked_until = $2
+                WHERE hostname = $3",
+            delay,
+            rate_limit.blocked_until,
+            url
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| DBError::DBError(Box::new(e)))?;
+        Ok(())
+    }
+
+    /// Get progress of the unfinished crawl of a forge, if any
+    async fn get_crawl_checkpoint(&self, url: &Url) -> DBResult<Option<CrawlCheckpoint>> {
+        struct InnerCheckpoint {
+            checkpoint_token: Option<String>,
+            checkpoint_page: Option<i64>,
+            checkpoint_started_on: Option<i64>,
+            checkpoint_since: Option<i64>,
+        }
+
+        let url = db_core::clean_url(url);
+        let res = sqlx::query_as!(
+            InnerCheckpoint,
+            "SELECT checkpoint_token, checkpoint_page, checkpoint_started_on, checkpoint_since
+                FROM starchart_forges WHERE hostname = $1",
+            url
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| DBError::DBError(Box::new(e)))?;
+        match (
+            res.checkpoint_token,
+            res.checkpoint_page,
+            res.checkpoint_started_on,
+        ) {
+            (Some(token), Some(page), Some(started_on)) => Ok(Some(CrawlCheckpoint {
+                token,
+                page: page as u64,
+                started_on,
+                since: res.checkpoint_since,
+            })),
+            _ => Ok(None),
+        }
+    }
+
+    /// Persist progress of the crawl of a forge
+    async fn set_crawl_checkpoint(&self, url: &Url, checkpoint: &CrawlCheckpoint) -> DBResult<()> {
+        let url = db_core::clean_url(url);
+        let page = checkpoint.page as i64;
+        sqlx::query!(
+            "UPDATE starchart_forges SET
+                checkpoint_token = $1,
+                checkpoint_page = $2,
+                checkpoint_started_on = $3,
+                checkpoint_since = $4
+            WHERE hostname = $5",
+            checkpoint.token,
+            page,
+            checkpoint.started_on,
+            checkpoint.since,
+            url
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| DBError::DBError(Box::new(e)))?;
+        Ok(())
+    }
+
+    /// Remove progress of the crawl of a forge
+    async fn rm_crawl_checkpoint(&self, url: &Url) -> DBResult<()> {
+        let url = db_core::clean_url(url);
+        sqlx::query!(
+            "UPDATE starchart_forges SET
+                checkpoint_token = NULL,
+                checkpoint_page = NULL,
+                checkpoint_started_on = NULL,
+                checkpoint_since = NULL
+            WHERE hostname = $1",
+            url
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| DBError::DBError(Box::new(e)))?;
+        Ok(())
+    }
+
+    /// Schedule crawl of a forge
+    async fn schedule_forge_crawl(
+        &self,
+        url: &Url,
+        next_crawl_on: i64,
+        priority: u32,
+    ) -> DBResult<()> {
+        let url = db_core::clean_url(url);
+        sqlx::query!(
+            "INSERT INTO starchart_crawl_schedule (hostname, next_crawl_on, priority)
+                VALUES ($1, $2, $3)
+            ON CONFLICT(hostname) DO UPDATE SET
+                next_crawl_on = excluded.next_crawl_on,
+                priority = excluded.priority,
+                consecutive_failures = 0,
+                disabled = FALSE",
+            url,
+            next_crawl_on,
+            priority
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| DBError::DBError(Box::new(e)))?;
+        Ok(())
+    }
+
+    /// Set seconds between crawls of a scheduled forge
+    async fn set_forge_crawl_interval(&self, url: &Url, interval: Option<u64>) -> DBResult<()> {
+        let url = db_core::clean_url(url);
+        let interval = interval.map(|i| i as i64);
+        let res = sqlx::query!(
+            "UPDATE starchart_crawl_schedule SET crawl_interval = $1 WHERE hostname = $2",
+            interval,
+            url
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| DBError::DBError(Box::new(e)))?;
+        if res.rows_affected() == 0 {
+            return Err(DBError::DBError(Box::new(Error::RowNotFound)));
+        }
+        Ok(())
+    }
+
+    /// Disable or enable crawls of a scheduled forge
+    async fn set_forge_crawl_disabled(&self, url: &Url, disabled: bool) -> DBResult<()> {
+        let url = db_core::clean_url(url);
+        let res = sqlx::query!(
+            "UPDATE starchart_crawl_schedule SET disabled = $1 WHERE hostname = $2",
+            disabled,
+            url
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| DBError::DBError(Box::new(e)))?;
+        if res.rows_affected() == 0 {
+            return Err(DBError::DBError(Box::new(Error::RowNotFound)));
+        }
+        Ok(())
+    }
+
+    /// Get crawl schedule of a forge, if it is scheduled
+    async fn get_forge_crawl_schedule(&self, url: &Url) -> DBResult<Option<CrawlSchedule>> {
+        let url = db_core::clean_url(url);
+        let res = sqlx::query_as!(
+            InnerCrawlSchedule,
+            "SELECT
+                hostname,
+                next_crawl_on,
+                priority,
+                crawl_interval,
+                last_started_on,
+                last_finished_on,
+                last_error,
+                consecutive_failures,
+                disabled
+            FROM
+                starchart_crawl_schedule
+            WHERE
+                hostname = $1",
+            url
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| DBError::DBError(Box::new(e)))?;
+        Ok(res.map(|s| s.into()))
+    }
+
+    /// Get forges that are due for a crawl
+    async fn get_due_forge_crawls(&self, now: i64, limit: u32) -> DBResult<Vec<CrawlSchedule>> {
+        let mut res = sqlx::query_as!(
+            InnerCrawlSchedule,
+            "SELECT
+                hostname as \"hostname!\",
+                next_crawl_on as \"next_crawl_on!\",
+                priority as \"priority!\",
+                crawl_interval,
+                last_started_on,
+                last_finished_on,
+                last_error,
+                consecutive_failures as \"consecutive_failures!\",
+                disabled as \"disabled!\"
+            FROM
+                starchart_crawl_schedule
+            WHERE
+                next_crawl_on <= $1 AND disabled = FALSE
+            ORDER BY
+                priority DESC, next_crawl_on ASC
+            LIMIT $2",
+            now,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| DBError::DBError(Box::new(e)))?;
+        Ok(res.drain(0..).map(|s| s.into()).collect())
+    }
+
+    /// Record finished crawl of a scheduled forge
+    async fn record_forge_crawl_run(&self, url: &Url, crawl: &FinishedCrawl) -> DBResult<()> {
+        let url = db_core::clean_url(url);
+        let failed = crawl.error.is_some();
+        sqlx::query!(
+            "UPDATE starchart_crawl_schedule SET
+                last_started_on = $1,
+                last_finished_on = $2,
+                last_error = $3,
+                next_crawl_on = $4,
+                priority = 0,
+                consecutive_failures = CASE WHEN $5 THEN consecutive_failures + 1 ELSE 0 END
+            WHERE hostname = $6",
+            crawl.started_on,
+            crawl.finished_on,
+            crawl.error,
+            crawl.next_crawl_on,
+            failed,
+            url
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| DBError::DBError(Box::new(e)))?;
+
+        let pages = crawl.stats.pages as i64;
+        let repositories_added = crawl.stats.repositories_added as i64;
+        let repositories_updated = crawl.stats.repositories_updated as i64;
+        let repositories_removed = crawl.stats.repositories_removed as i64;
+        let users_added = crawl.stats.users_added as i64;
+        let users_updated = crawl.stats.users_updated as i64;
+        let users_removed = crawl.stats.users_removed as i64;
+        sqlx::query!(
+            "INSERT INTO starchart_crawl_runs
+                (
+                    hostname, started_on, finished_on, pages,
+                    repositories_added, repositories_updated, repositories_removed,
+                    users_added, users_updated, users_removed, error
+                )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)",
+            url,
+            crawl.started_on,
+            crawl.finished_on,
+            pages,
+            repositories_added,
+            repositories_updated,
+            repositories_removed,
+            users_added,
+            users_updated,
+            users_removed,
+            crawl.error
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| DBError::DBError(Box::new(e)))?;
+        Ok(())
+    }
+
+    /// Get crawl history of a forge, latest crawl first
+    async fn get_forge_crawl_runs(
+        &self,
+        url: &Url,
+        offset: u32,
+        limit: u32,
+    ) -> DBResult<Vec<CrawlRun>> {
+        struct InnerCrawlRun {
+            started_on: i64,
+            finished_on: i64,
+            pages: i64,
+            repositories_added: i64,
+            repositories_updated: i64,
+            repositories_removed: i64,
+            users_added: i64,
+            users_updated: i64,
+            users_removed: i64,
+            error: Option<String>,
+        }
+
+        let url = db_core::clean_url(url);
+        let mut res = sqlx::query_as!(
+            InnerCrawlRun,
+            "SELECT
+                started_on as \"started_on!\",
+                finished_on as \"finished_on!\",
+                pages as \"pages!\",
+                repositories_added as \"repositories_added!\",
+                repositories_updated as \"repositories_updated!\",
+                repositories_removed as \"repositories_removed!\",
+                users_added as \"users_added!\",
+                users_updated as \"users_updated!\",
+                users_removed as \"users_removed!\",
+                error
+            FROM
+                starchart_crawl_runs
+            WHERE
+                hostname = $1
+            ORDER BY
+                ID DESC
+            LIMIT $2 OFFSET $3",
+            url,
+            limit,
+            offset
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| DBError::DBError(Box::new(e)))?;
+        Ok(res
+            .drain(0..)
+            .map(|r| CrawlRun {
+                started_on: r.started_on,
+                finished_on: r.finished_on,
+                stats: CrawlStats {
+                    pages: r.pages as u64,
+                    repositories_added: r.repositories_added as u64,
+                    repositories_updated: r.repositories_updated as u64,
+                    repositories_removed: r.repositories_removed as u64,
+                    users_added: r.users_added as u64,
+                    users_updated: r.users_updated as u64,
+                    users_removed: r.users_removed as u64,
+                },
+                outcome: match r.error {
+                    Some(e) => CrawlOutcome::Failed(e),
+                    None => CrawlOutcome::Success,
+                },
+            })
+            .collect())
+    }
+
     /// Get all forges
     async fn get_all_forges(
         &self,
@@ -398,6 +770,7 @@ impl SCDatabase for Database {
                 "SELECT
                 hostname,
                 last_crawl_on,
+                last_full_crawl_on,
                 starchart_forge_type.name,
                 starchart_introducer.instance_url
             FROM
@@ -426,6 +799,7 @@ impl SCDatabase for Database {
                 "SELECT
                 hostname,
                 last_crawl_on,
+                last_full_crawl_on,
                 starchart_introducer.instance_url,
                 starchart_forge_type.name
             FROM
@@ -474,8 +848,40 @@ impl SCDatabase for Database {
     async fn forge_type_exists(&self, forge_type: &ForgeImplementation) -> DBResult<bool> {
         let forge_type = forge_type.to_str();
         match sqlx::query!(
-            "SELECT ID FROM starchart_forge_type WHERE name = $1",
-            forge_type
+            "SELECT ID FROM starchart_forge_type WHERE name = $1",
+            forge_type
+        )
+        .fetch_one(&self.pool)
+        .await
+        {
+            Ok(_) => Ok(true),
+            Err(Error::RowNotFound) => Ok(false),
+            Err(e) => Err(DBError::DBError(Box::new(e))),
+        }
+    }
+
+    async fn create_dns_challenge(&self, c: &CreateDNSChallenge) -> DBResult<()> {
+        let url = db_core::clean_url(c.url);
+        sqlx::query!(
+            "INSERT INTO starchart_dns_challenges
+                (hostname, key, value, created, expires_on)
+            VALUES ($1, $2, $3, $4, $5)",
+            url,
+            c.key,
+            c.value,
+            c.created,
+            c.expires_on
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(map_register_err)?;
+        Ok(())
+    }
+
+    async fn dns_challenge_exists(&self, key: &str) -> DBResult<bool> {
+        match sqlx::query!(
+            "SELECT ID FROM starchart_dns_challenges WHERE key = $1",
+            key
         )
         .fetch_one(&self.pool)
         .await
@@ -486,6 +892,52 @@ impl SCDatabase for Database {
         }
     }
 
+    async fn get_dns_challenge(&self, key: &str) -> DBResult<DNSChallenge> {
+        struct InnerDNSChallenge {
+            hostname: String,
+            key: String,
+            value: String,
+            created: i64,
+            expires_on: i64,
+        }
+
+        let c = sqlx::query_as!(
+            InnerDNSChallenge,
+            "SELECT hostname, key, value, created, expires_on
+                FROM starchart_dns_challenges WHERE key = $1",
+            key
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| DBError::DBError(Box::new(e)))?;
+        Ok(DNSChallenge {
+            url: c.hostname,
+            key: c.key,
+            value: c.value,
+            created: c.created,
+            expires_on: c.expires_on,
+        })
+    }
+
+    async fn delete_dns_challenge(&self, key: &str) -> DBResult<()> {
+        sqlx::query!("DELETE FROM starchart_dns_challenges WHERE key = $1", key)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| DBError::DBError(Box::new(e)))?;
+        Ok(())
+    }
+
+    async fn rm_expired_dns_challenges(&self, now: i64) -> DBResult<()> {
+        sqlx::query!(
+            "DELETE FROM starchart_dns_challenges WHERE expires_on <= $1",
+            now
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| DBError::DBError(Box::new(e)))?;
+        Ok(())
+    }
+
     /// add new user to database
     async fn add_user(&self, u: &AddUser) -> DBResult<()> {
         let now = now_unix_time_stamp();
@@ -509,7 +961,32 @@ impl SCDatabase for Database {
         .execute(&self.pool)
         .await
         .map_err(map_register_err)?;
-        self.new_fts_user(u.username).await?;
+        self.add_word_to_mini_index(u.username).await?;
+
+        Ok(())
+    }
+
+    /// update data of an existing user
+    async fn update_user(&self, u: &AddUser) -> DBResult<()> {
+        let now = now_unix_time_stamp();
+        let url = db_core::clean_url(&u.url);
+        let res = sqlx::query!(
+            "UPDATE starchart_users
+                SET html_url = $1, profile_photo_html_url = $2, last_crawl_on = $3
+                WHERE username = $4 AND
+                    hostname_id = (SELECT ID FROM starchart_forges WHERE hostname = $5)",
+            u.html_link,
+            u.profile_photo,
+            now,
+            u.username,
+            url,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(map_register_err)?;
+        if res.rows_affected() == 0 {
+            return Err(DBError::DBError(Box::new(Error::RowNotFound)));
+        }
         self.add_word_to_mini_index(u.username).await?;
 
         Ok(())
@@ -610,12 +1087,15 @@ impl SCDatabase for Database {
             "INSERT INTO 
                 starchart_repositories (
                     hostname_id, owner_id, name, description, html_url, website, created,
-                    last_crawl, imported
+                    last_crawl, imported, stars, forks, fork, parent_html_url, mirror,
+                    archived, default_branch, clone_url, created_on_forge, updated_on_forge,
+                    language
                 )
                 VALUES (
                     (SELECT ID FROM starchart_forges WHERE hostname = $1),
                     (SELECT ID FROM starchart_users WHERE username = $2),
-                    $3, $4, $5, $6, $7, $8, $9
+                    $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19,
+                    $20
                 );",
             url,
             r.owner,
@@ -626,13 +1106,22 @@ impl SCDatabase for Database {
             now,
             now,
             r.import,
+            r.metadata.stars,
+            r.metadata.forks,
+            r.metadata.fork,
+            r.metadata.parent,
+            r.metadata.mirror,
+            r.metadata.archived,
+            r.metadata.default_branch,
+            r.metadata.clone_url,
+            r.metadata.created_at,
+            r.metadata.updated_at,
+            r.metadata.language,
         )
         .execute(&self.pool)
         .await
         .map_err(map_register_err)?;
 
-        self.new_fts_repositories(r.name, r.description, r.website, r.html_link)
-            .await?;
         if let Some(description) = r.description {
             self.add_word_to_mini_index(description).await?;
         }
@@ -642,33 +1131,87 @@ impl SCDatabase for Database {
         }
 
         if let Some(topics) = &r.tags {
-            for topic in topics.iter() {
-                sqlx::query!(
-                    "INSERT OR IGNORE INTO starchart_project_topics ( name ) VALUES ( $1 );",
-                    topic,
-                )
-                .execute(&self.pool)
-                .await
-                .map_err(map_register_err)?;
-
-                self.new_fts_topic(topic).await?;
-                self.add_word_to_mini_index(topic).await?;
-
-                sqlx::query!(
-                    "
-                        INSERT INTO starchart_repository_topic_mapping ( topic_id, repository_id )
-                        VALUES (
-                            (SELECT ID FROM starchart_project_topics WHERE name = $1),
-                            (SELECT ID FROM starchart_repositories WHERE html_url = $2)
-                        );",
-                    topic,
-                    r.html_link,
-                )
-                .execute(&self.pool)
-                .await
-                .map_err(map_register_err)?;
-            }
+            self.add_repository_topics(r.html_link, topics).await?;
+        }
+        self.index_repository(r.html_link).await?;
+
+        Ok(())
+    }
+
+    /// update metadata of an existing repository
+    async fn update_repository(&self, r: &AddRepository) -> DBResult<()> {
+        let now = now_unix_time_stamp();
+        let url = db_core::clean_url(&r.url);
+        sqlx::query!(
+            "SELECT ID FROM starchart_repositories
+                WHERE
+                    name = $1
+                AND
+                    owner_id = ( SELECT ID FROM starchart_users WHERE username = $2)
+                AND
+                    hostname_id = (SELECT ID FROM starchart_forges WHERE hostname = $3)",
+            r.name,
+            r.owner,
+            url,
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| DBError::DBError(Box::new(e)))?;
+
+        sqlx::query!(
+            "UPDATE starchart_repositories
+                SET description = $1, html_url = $2, website = $3, last_crawl = $4,
+                    stars = $5, forks = $6, fork = $7, parent_html_url = $8, mirror = $9,
+                    archived = $10, default_branch = $11, clone_url = $12,
+                    created_on_forge = $13, updated_on_forge = $14, language = $15
+                WHERE
+                    name = $16
+                AND
+                    owner_id = ( SELECT ID FROM starchart_users WHERE username = $17)
+                AND
+                    hostname_id = (SELECT ID FROM starchart_forges WHERE hostname = $18)",
+            r.description,
+            r.html_link,
+            r.website,
+            now,
+            r.metadata.stars,
+            r.metadata.forks,
+            r.metadata.fork,
+            r.metadata.parent,
+            r.metadata.mirror,
+            r.metadata.archived,
+            r.metadata.default_branch,
+            r.metadata.clone_url,
+            r.metadata.created_at,
+            r.metadata.updated_at,
+            r.metadata.language,
+            r.name,
+            r.owner,
+            url,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(map_register_err)?;
+
+        if let Some(description) = r.description {
+            self.add_word_to_mini_index(description).await?;
+        }
+        if let Some(website) = r.website {
+            self.add_word_to_mini_index(website).await?;
+        }
+
+        sqlx::query!(
+            "DELETE FROM starchart_repository_topic_mapping
+                WHERE repository_id = (SELECT ID FROM starchart_repositories WHERE html_url = $1);",
+            r.html_link
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| DBError::DBError(Box::new(e)))?;
+        if let Some(topics) = &r.tags {
+            self.add_repository_topics(r.html_link, topics).await?;
         }
+        self.index_repository(r.html_link).await?;
 
         Ok(())
     }
@@ -679,7 +1222,19 @@ impl SCDatabase for Database {
         self.rm_word_from_mini_index(&user.username).await?;
 
         let url = db_core::clean_url(url);
-        // TODO fts delete user
+        sqlx::query!(
+            "DELETE FROM fts_repositories WHERE rowid IN (
+                SELECT ID FROM starchart_repositories WHERE owner_id = (
+                    SELECT ID FROM starchart_users WHERE username = $1 AND
+                    hostname_id = (SELECT ID FROM starchart_forges WHERE hostname = $2)
+                )
+            );",
+            username,
+            url
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| DBError::DBError(Box::new(e)))?;
 
         sqlx::query!(
             " DELETE FROM starchart_users WHERE username = $1 AND 
@@ -696,23 +1251,23 @@ impl SCDatabase for Database {
     /// delete repository
     async fn delete_repository(&self, owner: &str, name: &str, url: &Url) -> DBResult<()> {
         let url = db_core::clean_url(url);
-        // TODO fts delete repo
-
-        let repo = self.search_repository(&url).await?;
-        if !repo.is_empty() {
-            if let Some(r) = repo
-                .iter()
-                .find(|r| r.username == owner && r.name == name && r.url == url)
-            {
-                if let Some(description) = &r.description {
-                    self.add_word_to_mini_index(description).await?;
-                }
-                self.add_word_to_mini_index(&r.name).await?;
-                if let Some(website) = &r.website {
-                    self.add_word_to_mini_index(website).await?;
-                }
-            }
-        }
+        sqlx::query!(
+            "DELETE FROM fts_repositories WHERE rowid = (
+                SELECT ID FROM starchart_repositories
+                WHERE
+                    name = $1
+                AND
+                    owner_id = ( SELECT ID FROM starchart_users WHERE username = $2)
+                AND
+                    hostname_id = (SELECT ID FROM starchart_forges WHERE hostname = $3)
+            );",
+            name,
+            owner,
+            url
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| DBError::DBError(Box::new(e)))?;
 
         sqlx::query!(
             " DELETE FROM starchart_repositories
@@ -732,8 +1287,13 @@ impl SCDatabase for Database {
         Ok(())
     }
 
-    /// Get all repositories
-    async fn get_all_repositories(&self, offset: u32, limit: u32) -> DBResult<Vec<Repository>> {
+    /// Get repositories of a forge that weren't seen by a crawl since `seen_since`
+    async fn get_unseen_repositories(
+        &self,
+        url: &Url,
+        seen_since: i64,
+    ) -> DBResult<Vec<Repository>> {
+        let url = db_core::clean_url(url);
         let mut db_res = sqlx::query_as!(
             InnerRepository,
             "SELECT 
@@ -744,7 +1304,18 @@ impl SCDatabase for Database {
                 starchart_repositories.html_url,
                 starchart_repositories.ID,
                 starchart_repositories.website,
-                starchart_repositories.imported
+                starchart_repositories.imported,
+                starchart_repositories.stars,
+                starchart_repositories.forks,
+                starchart_repositories.fork,
+                starchart_repositories.parent_html_url,
+                starchart_repositories.mirror,
+                starchart_repositories.archived,
+                starchart_repositories.default_branch,
+                starchart_repositories.clone_url,
+                starchart_repositories.created_on_forge,
+                starchart_repositories.updated_on_forge,
+                starchart_repositories.language
             FROM
                 starchart_repositories
             INNER JOIN
@@ -755,67 +1326,76 @@ impl SCDatabase for Database {
                 starchart_users
             ON
                 starchart_repositories.owner_id =  starchart_users.id
+            WHERE
+                starchart_forges.hostname = $1
+            AND
+                starchart_repositories.imported = false
+            AND
+                starchart_repositories.last_crawl < $2
             ORDER BY
                 starchart_repositories.ID
-            LIMIT $1 OFFSET $2
                 ;",
-            limit,
-            offset,
+            url,
+            seen_since,
         )
         .fetch_all(&self.pool)
         .await
         .map_err(map_register_err)?;
 
         let mut res = Vec::with_capacity(db_res.len());
-        struct Topics {
-            name: String,
+        for repo in db_res.drain(0..) {
+            let tags = self.get_repository_topics(repo.ID).await?;
+            res.push(repo.into_repository(tags));
         }
 
-        for repo in db_res.drain(0..) {
-            let mut db_topics = sqlx::query_as!(
-                Topics,
-                "SELECT name FROM starchart_project_topics WHERE ID = (
-                SELECT topic_id FROM starchart_repository_topic_mapping WHERE repository_id = $1
-            )",
-                repo.ID
-            )
-            .fetch_all(&self.pool)
-            .await
-            .map_err(map_register_err)?;
+        Ok(res)
+    }
 
-            let topics = if db_topics.is_empty() {
-                None
-            } else {
-                let mut topics = Vec::with_capacity(db_topics.len());
-                for t in db_topics.drain(0..) {
-                    topics.push(t.name);
-                }
+    /// Get users of a forge that weren't seen by a crawl since `seen_since`
+    async fn get_unseen_users(&self, url: &Url, seen_since: i64) -> DBResult<Vec<User>> {
+        struct InnerUser {
+            username: String,
+            profile_photo_html_url: Option<String>,
+            html_url: String,
+            imported: bool,
+        }
+
+        let url = db_core::clean_url(url);
+        let mut db_res = sqlx::query_as!(
+            InnerUser,
+            "SELECT username, html_url, profile_photo_html_url, imported FROM starchart_users
+                WHERE
+                    hostname_id = (SELECT ID FROM starchart_forges WHERE hostname = $1)
+                AND
+                    imported = false
+                AND
+                    last_crawl_on < $2
+                ORDER BY ID",
+            url,
+            seen_since,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| DBError::DBError(Box::new(e)))?;
 
-                Some(topics)
-            };
-            res.push(Repository {
-                html_url: repo.html_url,
-                url: repo.hostname,
-                name: repo.name,
-                username: repo.username,
-                description: repo.description,
-                website: repo.website,
-                tags: topics,
-                import: repo.imported,
+        let mut res = Vec::with_capacity(db_res.len());
+        for u in db_res.drain(0..) {
+            res.push(User {
+                url: url.clone(),
+                username: u.username,
+                html_link: u.html_url,
+                profile_photo: u.profile_photo_html_url,
+                import: u.imported,
             });
         }
-
         Ok(res)
     }
 
-    /// Search all repositories
-    async fn search_repository(&self, query: &str) -> DBResult<Vec<Repository>> {
-        let mut fts_repos = self.get_fts_repository(query).await?;
-        let mut res = Vec::with_capacity(fts_repos.len());
-        for fts_repo in fts_repos.drain(0..) {
-            let repo = sqlx::query_as!(
-                InnerRepository,
-                "SELECT 
+    /// Get all repositories
+    async fn get_all_repositories(&self, offset: u32, limit: u32) -> DBResult<Vec<Repository>> {
+        let mut db_res = sqlx::query_as!(
+            InnerRepository,
+            "SELECT 
                 starchart_forges.hostname,
                 starchart_users.username,
                 starchart_repositories.name,
@@ -823,7 +1403,18 @@ impl SCDatabase for Database {
                 starchart_repositories.html_url,
                 starchart_repositories.ID,
                 starchart_repositories.website,
-                starchart_repositories.imported
+                starchart_repositories.imported,
+                starchart_repositories.stars,
+                starchart_repositories.forks,
+                starchart_repositories.fork,
+                starchart_repositories.parent_html_url,
+                starchart_repositories.mirror,
+                starchart_repositories.archived,
+                starchart_repositories.default_branch,
+                starchart_repositories.clone_url,
+                starchart_repositories.created_on_forge,
+                starchart_repositories.updated_on_forge,
+                starchart_repositories.language
             FROM
                 starchart_repositories
             INNER JOIN
@@ -834,49 +1425,142 @@ impl SCDatabase for Database {
                 starchart_users
             ON
                 starchart_repositories.owner_id =  starchart_users.id
-            WHERE starchart_repositories.html_url =  $1
+            ORDER BY
+                starchart_repositories.ID
+            LIMIT $1 OFFSET $2
                 ;",
-                fts_repo.html_url
-            )
-            .fetch_one(&self.pool)
-            .await
-            .map_err(map_register_err)?;
+            limit,
+            offset,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_register_err)?;
 
-            struct Topics {
-                name: String,
-            }
+        let mut res = Vec::with_capacity(db_res.len());
+        for repo in db_res.drain(0..) {
+            let tags = self.get_repository_topics(repo.ID).await?;
+            res.push(repo.into_repository(tags));
+        }
 
-            let mut db_topics = sqlx::query_as!(
-                Topics,
-                "SELECT name FROM starchart_project_topics WHERE ID = (
-                SELECT topic_id FROM starchart_repository_topic_mapping WHERE repository_id = $1
-            )",
-                repo.ID
+        Ok(res)
+    }
+
+    /// Get repositories that match a search query
+    async fn query_repositories(
+        &self,
+        query: &SearchQuery,
+        offset: u32,
+        limit: u32,
+    ) -> DBResult<Vec<Repository>> {
+        if query.is_empty() {
+            return Ok(Vec::default());
+        }
+        // terms are quoted so that they are never read as FTS5 operators
+        let terms = if query.terms.is_empty() {
+            None
+        } else {
+            Some(
+                query
+                    .terms
+                    .iter()
+                    .map(|term| format!("\"{term}\"*"))
+                    .collect::<Vec<String>>()
+                    .join(" "),
             )
-            .fetch_all(&self.pool)
-            .await
-            .map_err(map_register_err)?;
+        };
+        // filter values never contain whitespace, so topics are space-delimited
+        let topics = format!(" {} ", query.topics.join(" "));
+        let topics_count = query.topics.len() as i64;
 
-            let topics = if db_topics.is_empty() {
-                None
-            } else {
-                let mut topics = Vec::with_capacity(db_topics.len());
-                for t in db_topics.drain(0..) {
-                    topics.push(t.name);
-                }
+        let mut db_res = sqlx::query_as!(
+            InnerRepository,
+            "SELECT
+                starchart_forges.hostname,
+                starchart_users.username,
+                starchart_repositories.name,
+                starchart_repositories.description,
+                starchart_repositories.html_url,
+                starchart_repositories.ID,
+                starchart_repositories.website,
+                starchart_repositories.imported,
+                starchart_repositories.stars,
+                starchart_repositories.forks,
+                starchart_repositories.fork,
+                starchart_repositories.parent_html_url,
+                starchart_repositories.mirror,
+                starchart_repositories.archived,
+                starchart_repositories.default_branch,
+                starchart_repositories.clone_url,
+                starchart_repositories.created_on_forge,
+                starchart_repositories.updated_on_forge,
+                starchart_repositories.language
+            FROM
+                starchart_repositories
+            INNER JOIN
+                starchart_forges
+            ON
+                starchart_repositories.hostname_id = starchart_forges.id
+            INNER JOIN
+                starchart_users
+            ON
+                starchart_repositories.owner_id =  starchart_users.id
+            LEFT JOIN (
+                SELECT
+                    rowid, bm25(fts_repositories, 10.0, 2.0, 1.0, 5.0, 3.0) AS rank
+                FROM
+                    fts_repositories
+                WHERE
+                    fts_repositories MATCH coalesce($1, '\"\"')
+            ) AS matches
+            ON
+                matches.rowid = starchart_repositories.ID
+            WHERE
+                ($1 IS NULL OR matches.rowid IS NOT NULL)
+            AND
+                ($2 IS NULL OR starchart_forges.hostname IN ('https://' || $2 || '/', 'http://' || $2 || '/'))
+            AND
+                ($3 IS NULL OR lower(starchart_users.username) = $3)
+            AND
+                ($4 IS NULL OR lower(starchart_repositories.language) = $4)
+            AND
+                ($5 IS NULL OR starchart_repositories.archived = $5)
+            AND
+                (
+                    SELECT COUNT(DISTINCT lower(starchart_project_topics.name))
+                    FROM
+                        starchart_project_topics
+                    INNER JOIN
+                        starchart_repository_topic_mapping
+                    ON
+                        starchart_repository_topic_mapping.topic_id = starchart_project_topics.ID
+                    WHERE
+                        starchart_repository_topic_mapping.repository_id = starchart_repositories.ID
+                    AND
+                        instr($6, ' ' || lower(starchart_project_topics.name) || ' ') > 0
+                ) = $7
+            ORDER BY
+                matches.rank,
+                starchart_repositories.ID
+            LIMIT $8 OFFSET $9
+                ;",
+            terms,
+            query.forge,
+            query.owner,
+            query.lang,
+            query.archived,
+            topics,
+            topics_count,
+            limit,
+            offset,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_register_err)?;
 
-                Some(topics)
-            };
-            res.push(Repository {
-                html_url: repo.html_url,
-                url: repo.hostname,
-                name: repo.name,
-                username: repo.username,
-                description: repo.description,
-                website: repo.website,
-                tags: topics,
-                import: repo.imported,
-            });
+        let mut res = Vec::with_capacity(db_res.len());
+        for repo in db_res.drain(0..) {
+            let tags = self.get_repository_topics(repo.ID).await?;
+            res.push(repo.into_repository(tags));
         }
         Ok(res)
     }
@@ -1077,6 +1761,7 @@ fn now_unix_time_stamp() -> i64 {
 struct InnerForge {
     hostname: String,
     last_crawl_on: Option<i64>,
+    last_full_crawl_on: Option<i64>,
     name: String,
     instance_url: String,
 }
@@ -1086,12 +1771,45 @@ impl From<InnerForge> for Forge {
         Self {
             url: f.hostname,
             last_crawl_on: f.last_crawl_on,
+            last_full_crawl_on: f.last_full_crawl_on,
             forge_type: ForgeImplementation::from_str(&f.name).unwrap(),
             starchart_url: Some(f.instance_url),
         }
     }
 }
 
+struct InnerCrawlSchedule {
+    hostname: String,
+    next_crawl_on: i64,
+    priority: i64,
+    crawl_interval: Option<i64>,
+    last_started_on: Option<i64>,
+    last_finished_on: Option<i64>,
+    last_error: Option<String>,
+    consecutive_failures: i64,
+    disabled: bool,
+}
+
+impl From<InnerCrawlSchedule> for CrawlSchedule {
+    fn from(s: InnerCrawlSchedule) -> Self {
+        let last_outcome = s.last_finished_on.map(|_| match s.last_error {
+            Some(e) => CrawlOutcome::Failed(e),
+            None => CrawlOutcome::Success,
+        });
+        Self {
+            url: s.hostname,
+            next_crawl_on: s.next_crawl_on,
+            priority: s.priority as u32,
+            interval: s.crawl_interval.map(|i| i as u64),
+            last_started_on: s.last_started_on,
+            last_finished_on: s.last_finished_on,
+            last_outcome,
+            consecutive_failures: s.consecutive_failures as u32,
+            disabled: s.disabled,
+        }
+    }
+}
+
 #[allow(non_snake_case)]
 struct InnerRepository {
     /// html link to the repository
@@ -1109,4 +1827,43 @@ struct InnerRepository {
     pub website: Option<String>,
     pub ID: i64,
     pub imported: bool,
+    pub stars: Option<i64>,
+    pub forks: Option<i64>,
+    pub fork: bool,
+    pub parent_html_url: Option<String>,
+    pub mirror: bool,
+    pub archived: bool,
+    pub default_branch: Option<String>,
+    pub clone_url: Option<String>,
+    pub created_on_forge: Option<i64>,
+    pub updated_on_forge: Option<i64>,
+    pub language: Option<String>,
+}
+
+impl InnerRepository {
+    fn into_repository(self, tags: Option<Vec<String>>) -> Repository {
+        Repository {
+            html_url: self.html_url,
+            url: self.hostname,
+            name: self.name,
+            username: self.username,
+            description: self.description,
+            website: self.website,
+            tags,
+            import: self.imported,
+            metadata: RepositoryMetadata {
+                stars: self.stars.map(|s| s as u32),
+                forks: self.forks.map(|f| f as u32),
+                fork: self.fork,
+                parent: self.parent_html_url,
+                mirror: self.mirror,
+                archived: self.archived,
+                default_branch: self.default_branch,
+                clone_url: self.clone_url,
+                created_at: self.created_on_forge,
+                updated_at: self.updated_on_forge,
+                language: self.language,
+            },
+        }
+    }
 }
@@ -23,6 +23,18 @@ use crate::*;
 
 use db_core::tests::*;
 
+/// Connect to the database at `SQLITE_DATABASE_URL` and apply migrations
+async fn get_db() -> Database {
+    let url = env::var("SQLITE_DATABASE_URL").expect("Set SQLITE_DATABASE_URL env var");
+    // connections keep reading a stale snapshot after a statement fails, which the helpers that
+    // check duplicate errors would trip over if they were served by another connection
+    let pool_options = SqlitePoolOptions::new().max_connections(1);
+    let connection_options = ConnectionOptions::Fresh(Fresh { pool_options, url });
+    let db = connection_options.connect().await.unwrap();
+    db.migrate().await.unwrap();
+    db
+}
+
 #[actix_rt::test]
 async fn everything_works() {
     const URL: &str = "https://test-gitea.example.com";
@@ -59,14 +71,7 @@ async fn everything_works() {
         import: false,
     };
 
-    let db = {
-        let url = env::var("SQLITE_DATABASE_URL").expect("Set SQLITE_DATABASE_URL env var");
-        let pool_options = SqlitePoolOptions::new().max_connections(2);
-        let connection_options = ConnectionOptions::Fresh(Fresh { pool_options, url });
-        let db = connection_options.connect().await.unwrap();
-        db.migrate().await.unwrap();
-        db
-    };
+    let db = get_db().await;
 
     let add_repo_msg = AddRepository {
         html_link: HTML_REPO_URL,
@@ -77,6 +82,19 @@ async fn everything_works() {
         description: "starchart sqlite test repo sescription".into(),
         url,
         import: false,
+        metadata: RepositoryMetadata {
+            stars: Some(42),
+            forks: Some(3),
+            fork: true,
+            parent: Some("https://upstream.example.org/starchart".into()),
+            mirror: false,
+            archived: true,
+            default_branch: Some("master".into()),
+            language: Some("Rust".into()),
+            clone_url: Some(format!("{HTML_REPO_URL}.git")),
+            created_at: Some(1_650_000_000),
+            updated_at: Some(1_680_000_000),
+        },
     };
 
     adding_forge_works(
@@ -90,34 +108,78 @@ async fn everything_works() {
 }
 
 #[actix_rt::test]
-async fn introducer_works() {
-    let url = env::var("SQLITE_DATABASE_URL").expect("Set SQLITE_DATABASE_URL env var");
-    let pool_options = SqlitePoolOptions::new().max_connections(2);
-    let connection_options = ConnectionOptions::Fresh(Fresh { pool_options, url });
-    let db = connection_options.connect().await.unwrap();
+async fn dns_challenge_works() {
+    let db = get_db().await;
+    let forge_url = Url::parse("https://dns_challenge_works_sqlite_sqlx.example.com").unwrap();
+    dns_challenge_helper(&db, &forge_url).await;
+}
 
+#[actix_rt::test]
+async fn introducer_works() {
+    let db = get_db().await;
     let instance_url = Url::parse("https://introducer_works_sqlite_sqlx.example.com").unwrap();
     instance_introducer_helper(&db, &instance_url).await;
 }
 
 #[actix_rt::test]
 async fn forge_type_exists() {
-    let url = env::var("SQLITE_DATABASE_URL").expect("Set SQLITE_DATABASE_URL env var");
-    let pool_options = SqlitePoolOptions::new().max_connections(2);
-    let connection_options = ConnectionOptions::Fresh(Fresh { pool_options, url });
-    let db = connection_options.connect().await.unwrap();
-
-    db.migrate().await.unwrap();
+    let db = get_db().await;
     forge_type_exists_helper(&db).await;
 }
 
 #[actix_rt::test]
 async fn mini_index_test() {
-    let url = env::var("SQLITE_DATABASE_URL").expect("Set SQLITE_DATABASE_URL env var");
-    let pool_options = SqlitePoolOptions::new().max_connections(2);
-    let connection_options = ConnectionOptions::Fresh(Fresh { pool_options, url });
-    let db = connection_options.connect().await.unwrap();
-
-    db.migrate().await.unwrap();
+    let db = get_db().await;
     mini_index_helper(&db).await;
 }
+
+#[actix_rt::test]
+async fn pagination_works() {
+    let db = get_db().await;
+    let forge_url = Url::parse("https://pagination_works_sqlite_sqlx.example.com").unwrap();
+    pagination_helper(&db, &forge_url).await;
+}
+
+#[actix_rt::test]
+async fn imports_work() {
+    let db = get_db().await;
+    let instance_url = Url::parse("https://imports_work_instance_sqlite_sqlx.example.com").unwrap();
+    let forge_url = Url::parse("https://imports_work_sqlite_sqlx.example.com").unwrap();
+    imports_helper(&db, &instance_url, &forge_url).await;
+}
+
+#[actix_rt::test]
+async fn delete_cascade_works() {
+    let db = get_db().await;
+    let forge_url = Url::parse("https://delete_cascade_works_sqlite_sqlx.example.com").unwrap();
+    delete_cascade_helper(&db, &forge_url).await;
+}
+
+#[actix_rt::test]
+async fn duplicate_errors_work() {
+    let db = get_db().await;
+    let forge_url = Url::parse("https://duplicate_errors_work_sqlite_sqlx.example.com").unwrap();
+    duplicate_errors_helper(&db, &forge_url).await;
+}
+
+#[actix_rt::test]
+async fn search_works() {
+    let db = get_db().await;
+    let forge_url = Url::parse("https://search_works_sqlite_sqlx.example.com").unwrap();
+    search_helper(&db, &forge_url).await;
+}
+
+#[actix_rt::test]
+async fn query_works() {
+    let db = get_db().await;
+    let forge_url = Url::parse("https://query_works_sqlite_sqlx.example.com").unwrap();
+    query_helper(&db, &forge_url).await;
+}
+
+#[actix_rt::test]
+async fn starchart_import_works() {
+    let db = get_db().await;
+    let instance_url =
+        Url::parse("https://starchart_import_works_sqlite_sqlx.example.com").unwrap();
+    starchart_import_helper(&db, &instance_url).await;
+}
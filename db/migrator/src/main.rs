@@ -18,7 +18,7 @@
 use std::env;
 
 use sqlx::migrate::MigrateDatabase;
-//use sqlx::postgres::PgPoolOptions;
+use sqlx::postgres::PgPoolOptions;
 use sqlx::sqlite::SqlitePoolOptions;
 use sqlx::Sqlite;
 
@@ -26,25 +26,23 @@ use sqlx::Sqlite;
 #[actix_rt::main]
 async fn main() {
     //TODO featuregate sqlite and postgres
-//    postgres_migrate().await;
+    postgres_migrate().await;
     sqlite_migrate().await;
 }
 
-//async fn postgres_migrate() {
-//    let db_url = env::var("POSTGRES_DATABASE_URL").expect("set POSTGRES_DATABASE_URL env var");
-//    let db = PgPoolOptions::new()
-//        .max_connections(2)
-//        .connect(&db_url)
-//        .await
-//        .expect("Unable to form database pool");
-//
-//    todo!("unimplemented");
-//
-////    sqlx::migrate!("../db-sqlx-postgres/migrations/")
-////        .run(&db)
-////        .await
-////        .unwrap();
-//}
+async fn postgres_migrate() {
+    let db_url = env::var("POSTGRES_DATABASE_URL").expect("set POSTGRES_DATABASE_URL env var");
+    let db = PgPoolOptions::new()
+        .max_connections(2)
+        .connect(&db_url)
+        .await
+        .expect("Unable to form database pool");
+
+    sqlx::migrate!("../db-sqlx-postgres/migrations/")
+        .run(&db)
+        .await
+        .unwrap();
+}
 
 async fn sqlite_migrate() {
     let db_url = env::var("SQLITE_DATABASE_URL").expect("Set SQLITE_DATABASE_URL env var");
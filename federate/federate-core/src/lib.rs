@@ -55,9 +55,15 @@ pub trait Federate: Sync + Send {
     /// create user instance
     async fn create_user(&self, f: &AddUser<'_>) -> Result<(), Self::Error>;
 
+    /// update user instance
+    async fn update_user(&self, f: &AddUser<'_>) -> Result<(), Self::Error>;
+
     /// add repository instance
     async fn create_repository(&self, f: &AddRepository<'_>) -> Result<(), Self::Error>;
 
+    /// update repository instance
+    async fn update_repository(&self, f: &AddRepository<'_>) -> Result<(), Self::Error>;
+
     /// check if a repository exists.
     async fn repository_exists(
         &self,
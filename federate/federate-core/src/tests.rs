@@ -52,6 +52,14 @@ pub async fn adding_forge_works<'a, T: Federate>(
         .await
         .unwrap());
 
+    // update user and repository
+    ff.update_user(&create_user_msg).await.unwrap();
+    ff.update_repository(&add_repo_msg).await.unwrap();
+    assert!(ff
+        .repository_exists(add_repo_msg.name, add_repo_msg.owner, &add_repo_msg.url)
+        .await
+        .unwrap());
+
     // tar()
     let tar = ff.tar().await.unwrap().to_str().unwrap().to_string();
     let latest = ff.latest_tar().await.unwrap();
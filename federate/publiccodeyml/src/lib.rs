@@ -17,12 +17,13 @@
  */
 use std::fs as StdFs;
 use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use async_trait::async_trait;
 use log::info;
 use mktemp::Temp;
 use reqwest::Client;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use tar::Archive;
 use tokio::fs;
 use tokio::io::AsyncWriteExt;
@@ -44,8 +45,59 @@ pub const INSTANCE_INFO_FILE: &str = "instance.yml";
 pub const USER_INFO_FILE: &str = "user.yml";
 pub const REPO_INFO_FILE: &str = "publiccode.yml";
 
+pub const REMOVED_FILE: &str = "removed.yml";
+
 pub const CONTENTS_DIR: &str = "uncompressed";
 
+/// Removals are published in every archive for this long, in seconds, so that importers that
+/// skip archives still see them
+pub const REMOVAL_RETENTION: u64 = 60 * 60 * 24 * 30;
+
+/// Users and repositories removed in the last [REMOVAL_RETENTION] seconds. Stored at the root of
+/// the archive, so that importers can remove them too.
+#[derive(Default, Serialize, Deserialize)]
+pub struct Removed {
+    pub users: Vec<RemovedUser>,
+    pub repositories: Vec<RemovedRepository>,
+}
+
+impl Removed {
+    /// Forget removals older than [REMOVAL_RETENTION]. Returns `true` if any were forgotten
+    fn prune(&mut self, now: u64) -> bool {
+        let len = self.users.len() + self.repositories.len();
+        let kept = |removed_on: u64| now.saturating_sub(removed_on) < REMOVAL_RETENTION;
+        self.users.retain(|u| kept(u.removed_on));
+        self.repositories.retain(|r| kept(r.removed_on));
+        len != self.users.len() + self.repositories.len()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RemovedUser {
+    pub url: Url,
+    pub username: String,
+    /// unix timestamp of the removal
+    #[serde(default)]
+    pub removed_on: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RemovedRepository {
+    pub url: Url,
+    pub owner: String,
+    pub name: String,
+    /// unix timestamp of the removal
+    #[serde(default)]
+    pub removed_on: u64,
+}
+
+fn now_unix_time_stamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
 #[derive(Clone)]
 pub struct PccFederate {
     pub base_dir: String,
@@ -102,6 +154,60 @@ impl PccFederate {
         Ok(path)
     }
 
+    /// removals are applied only to forges that were imported from the Starchart instance that
+    /// published them
+    async fn is_imported_from(
+        &self,
+        db: &dyn SCDatabase,
+        forge_url: &Url,
+        starchart_url: &Url,
+    ) -> FResult<bool> {
+        if !db.forge_exists(forge_url).await? {
+            return Ok(false);
+        }
+        let forge = db.get_forge(forge_url).await?;
+        Ok(forge.starchart_url.as_deref() == Some(starchart_url.as_str()))
+    }
+
+    /// get removals that will be published in the next archive
+    pub async fn get_removed(&self) -> FResult<Removed> {
+        let path = self.get_content_path(false).await?.join(REMOVED_FILE);
+        if path.exists() {
+            Ok(serde_yaml::from_str(&fs::read_to_string(path).await?)?)
+        } else {
+            Ok(Removed::default())
+        }
+    }
+
+    async fn set_removed(&self, removed: &Removed) -> FResult<()> {
+        let path = self.get_content_path(false).await?.join(REMOVED_FILE);
+        self.write_util(removed, &path).await
+    }
+
+    /// Forget removals of users and repositories that were added again, lest importers remove
+    /// them
+    async fn forget_removed(
+        &self,
+        url: &Url,
+        username: &str,
+        repository: Option<&str>,
+    ) -> FResult<()> {
+        let mut removed = self.get_removed().await?;
+        let len = removed.users.len() + removed.repositories.len();
+        match repository {
+            None => removed
+                .users
+                .retain(|u| !(&u.url == url && u.username == username)),
+            Some(name) => removed
+                .repositories
+                .retain(|r| !(&r.url == url && r.owner == username && r.name == name)),
+        }
+        if len != removed.users.len() + removed.repositories.len() {
+            self.set_removed(&removed).await?;
+        }
+        Ok(())
+    }
+
     /// utility method to write data
     async fn write_util<S: Serialize + Send + Sync>(&self, data: &S, path: &Path) -> FResult<()> {
         let fcontents = serde_yaml::to_string(data)?;
@@ -171,6 +277,13 @@ impl Federate for PccFederate {
     /// create user instance
     async fn create_user(&self, f: &AddUser<'_>) -> Result<(), Self::Error> {
         let path = self.get_user_path(f.username, &f.url, true).await?;
+        self.write_util(f, &path.join(USER_INFO_FILE)).await?;
+        self.forget_removed(&f.url, f.username, None).await
+    }
+
+    /// update user instance
+    async fn update_user(&self, f: &AddUser<'_>) -> Result<(), Self::Error> {
+        let path = self.get_user_path(f.username, &f.url, false).await?;
         self.write_util(f, &path.join(USER_INFO_FILE)).await
     }
 
@@ -181,6 +294,17 @@ impl Federate for PccFederate {
             .await?
             .join(REPO_INFO_FILE);
         let publiccode: schema::Repository = f.into();
+        self.write_util(&publiccode, &path).await?;
+        self.forget_removed(&f.url, f.owner, Some(f.name)).await
+    }
+
+    /// update repository instance
+    async fn update_repository(&self, f: &AddRepository<'_>) -> Result<(), Self::Error> {
+        let path = self
+            .get_repo_path(f.name, f.owner, &f.url, false)
+            .await?
+            .join(REPO_INFO_FILE);
+        let publiccode: schema::Repository = f.into();
         self.write_util(&publiccode, &path).await
     }
 
@@ -203,7 +327,16 @@ impl Federate for PccFederate {
     /// delete user
     async fn delete_user(&self, username: &str, url: &Url) -> Result<(), Self::Error> {
         let path = self.get_user_path(username, url, false).await?;
-        self.rm_util(&path).await?;
+        if path.exists() {
+            self.rm_util(&path).await?;
+            let mut removed = self.get_removed().await?;
+            removed.users.push(RemovedUser {
+                url: url.clone(),
+                username: username.into(),
+                removed_on: now_unix_time_stamp(),
+            });
+            self.set_removed(&removed).await?;
+        }
         Ok(())
     }
 
@@ -215,19 +348,32 @@ impl Federate for PccFederate {
         url: &Url,
     ) -> Result<(), Self::Error> {
         let path = self.get_repo_path(name, owner, url, false).await?;
-        self.rm_util(&path).await
+        if path.exists() {
+            self.rm_util(&path).await?;
+            let mut removed = self.get_removed().await?;
+            removed.repositories.push(RemovedRepository {
+                url: url.clone(),
+                owner: owner.into(),
+                name: name.into(),
+                removed_on: now_unix_time_stamp(),
+            });
+            self.set_removed(&removed).await?;
+        }
+        Ok(())
     }
 
     async fn tar(&self) -> Result<PathBuf, Self::Error> {
         use std::fs::File;
-        use std::time::{SystemTime, UNIX_EPOCH};
 
         use tar::Builder;
 
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
+        let now = now_unix_time_stamp();
+
+        // removals are published until importers are expected to have seen them
+        let mut removed = self.get_removed().await?;
+        if removed.prune(now) {
+            self.set_removed(&removed).await?;
+        }
 
         let path = Path::new(&self.base_dir).join(format!("{now}.tar"));
         let file = File::create(&path)?;
@@ -418,6 +564,39 @@ impl Federate for PccFederate {
                 }
             }
         }
+
+        let removed_file = uncompressed.join(REMOVED_FILE);
+        if removed_file.exists() {
+            let removed: Removed =
+                serde_yaml::from_str(&fs::read_to_string(removed_file).await.unwrap()).unwrap();
+            for r in removed.repositories.iter() {
+                if !self
+                    .is_imported_from(db.as_ref(), &r.url, &starchart_url)
+                    .await?
+                {
+                    continue;
+                }
+                if db.repository_exists(&r.name, &r.owner, &r.url).await? {
+                    info!("[import][{}] Removing repository: {}", r.url, r.name);
+                    db.delete_repository(&r.owner, &r.name, &r.url).await?;
+                }
+                self.delete_repository(&r.owner, &r.name, &r.url).await?;
+            }
+            for u in removed.users.iter() {
+                if !self
+                    .is_imported_from(db.as_ref(), &u.url, &starchart_url)
+                    .await?
+                {
+                    continue;
+                }
+                if db.user_exists(&u.username, Some(&u.url)).await? {
+                    info!("[import][{}] Removing user: {}", u.url, u.username);
+                    db.delete_user(&u.username, &u.url).await?;
+                }
+                self.delete_user(&u.username, &u.url).await?;
+            }
+        }
+
         db.record_starchart_imports(&starchart_url).await?;
         Ok(())
     }
@@ -17,7 +17,7 @@
  */
 use std::collections::HashMap;
 
-use db_core::AddRepository;
+use db_core::{AddRepository, RepositoryMetadata};
 use serde::{Deserialize, Serialize};
 use url::Url;
 
@@ -125,7 +125,7 @@ impl From<&db_core::AddRepository<'_>> for Repository {
             url: Url::parse(r.html_link).unwrap(),
             landing_url: r.website.map(|s| Url::parse(s).unwrap()),
             name: r.name.into(),
-            is_based_on: None, // TODO collect is_fork information in forge/*
+            is_based_on: r.metadata.parent.clone(),
             description,
             legal,
             intended_audience,
@@ -157,6 +157,11 @@ impl Repository {
             description,
             website,
             import,
+            metadata: RepositoryMetadata {
+                fork: self.is_based_on.is_some(),
+                parent: self.is_based_on.clone(),
+                ..Default::default()
+            },
         }
     }
 }
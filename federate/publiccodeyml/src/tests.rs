@@ -57,10 +57,36 @@ async fn everything_works() {
         description: None,
         url: url.clone(),
         import: false,
+        metadata: RepositoryMetadata::default(),
     };
 
     let pcc = PccFederate::new(tmp_dir.to_str().unwrap().to_string())
         .await
         .unwrap();
-    tests::adding_forge_works(&pcc, create_forge_msg, add_user_msg, add_repo_msg).await;
+    tests::adding_forge_works(&pcc, create_forge_msg, add_user_msg.clone(), add_repo_msg).await;
+
+    // removals are published in archives until they are older than REMOVAL_RETENTION
+    let mut removed = pcc.get_removed().await.unwrap();
+    assert!(removed
+        .repositories
+        .iter()
+        .any(|r| r.name == REPO_NAME && r.owner == USERNAME && r.url == url));
+    assert!(removed
+        .users
+        .iter()
+        .any(|u| u.username == USERNAME && u.url == url));
+    pcc.tar().await.unwrap();
+    pcc.tar().await.unwrap();
+    assert_eq!(pcc.get_removed().await.unwrap().repositories.len(), 1);
+
+    removed.repositories[0].removed_on -= REMOVAL_RETENTION;
+    pcc.set_removed(&removed).await.unwrap();
+    pcc.tar().await.unwrap();
+    let removed = pcc.get_removed().await.unwrap();
+    assert!(removed.repositories.is_empty());
+    assert_eq!(removed.users.len(), 1);
+
+    // users and repositories that are added again are no longer removed
+    pcc.create_user(&add_user_msg).await.unwrap();
+    assert!(pcc.get_removed().await.unwrap().users.is_empty());
 }
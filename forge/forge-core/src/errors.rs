@@ -0,0 +1,82 @@
+/*
+ * ForgeFlux StarChart - A federated software forge spider
+ * Copyright (C) 2022  Aravinth Manivannan <realaravinth@batsense.net>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+//! represents all the ways a forge can fail to be crawled
+use std::error::Error as StdError;
+
+use reqwest::{header::RETRY_AFTER, Response, StatusCode};
+use thiserror::Error;
+
+/// Error data structure grouping various error subtypes
+#[derive(Debug, Error)]
+pub enum ForgeError {
+    /// forge couldn't be reached
+    #[error("Couldn't reach forge: {0}")]
+    Network(#[source] BoxDynError),
+
+    /// forge responded with an unexpected HTTP status
+    #[error("Forge responded with HTTP status {0}")]
+    HttpStatus(u16),
+
+    /// forge is rate limiting requests
+    #[error("Forge is rate limiting requests")]
+    RateLimited {
+        /// seconds to wait before retrying, if the forge sent them
+        retry_after: Option<u64>,
+    },
+
+    /// forge response couldn't be decoded
+    #[error("Couldn't decode forge response: {0}")]
+    Decode(#[source] BoxDynError),
+
+    /// continuation token wasn't issued by this forge
+    #[error("Invalid continuation token {0}")]
+    InvalidToken(String),
+}
+
+/// Convenience type alias for grouping client-specific errors
+pub type BoxDynError = Box<dyn StdError + 'static + Send + Sync>;
+
+/// Generic result data structure
+pub type ForgeResult<V> = std::result::Result<V, ForgeError>;
+
+impl From<reqwest::Error> for ForgeError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_decode() {
+            Self::Decode(Box::new(e))
+        } else {
+            Self::Network(Box::new(e))
+        }
+    }
+}
+
+/// Pass successful responses through and turn the rest into errors
+pub fn check_status(res: Response) -> ForgeResult<Response> {
+    let status = res.status();
+    if status.is_success() {
+        Ok(res)
+    } else if status == StatusCode::TOO_MANY_REQUESTS {
+        let retry_after = res
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|h| h.to_str().ok())
+            .and_then(|h| h.trim().parse().ok());
+        Err(ForgeError::RateLimited { retry_after })
+    } else {
+        Err(ForgeError::HttpStatus(status.as_u16()))
+    }
+}
@@ -22,12 +22,20 @@ use async_trait::async_trait;
 use db_core::prelude::*;
 use url::Url;
 
+pub mod errors;
+pub mod ratelimit;
+
+use errors::{ForgeError, ForgeResult};
+use ratelimit::RateLimiter;
+
 pub mod prelude {
     pub use super::*;
     pub use async_trait::async_trait;
 }
 
 pub mod dev {
+    pub use super::errors::{check_status, ForgeError, ForgeResult};
+    pub use super::ratelimit::RateLimiter;
     pub use super::*;
     pub use async_trait::async_trait;
     pub use db_core;
@@ -76,6 +84,8 @@ pub struct Repository {
     pub description: Option<String>,
     /// repository website, if any
     pub website: Option<String>,
+    /// repository metadata reported by the forge
+    pub metadata: RepositoryMetadata,
 }
 
 impl<'a> From<&'a Repository> for AddRepository<'a> {
@@ -98,6 +108,7 @@ impl<'a> From<&'a Repository> for AddRepository<'a> {
             html_link: &r.html_link,
             website: r.website.as_deref(),
             import: false,
+            metadata: r.metadata.clone(),
         }
     }
 }
@@ -110,12 +121,47 @@ pub struct CrawlResp {
     pub repos: Repositories,
     pub tags: Tags,
     pub users: UserMap,
+    /// opaque token to crawl the next page with. Only meaningful to the forge that issued it
+    pub next_token: Option<String>,
+    /// are there more pages to crawl? A page can be empty and still be followed by more pages
+    pub has_more: bool,
+}
+
+/// Continuation token of the page after `page`, for forges that paginate with page numbers
+pub fn next_page_token(page: u64) -> String {
+    (page + 1).to_string()
+}
+
+/// Page number encoded in a continuation token issued with [next_page_token]. Crawls start
+/// from page 1
+pub fn page_from_token(token: Option<&str>) -> ForgeResult<u64> {
+    match token {
+        Some(t) => t
+            .parse()
+            .map_err(|_| ForgeError::InvalidToken(t.to_owned())),
+        None => Ok(1),
+    }
 }
 
 #[async_trait]
 pub trait SCForge: std::marker::Send + std::marker::Sync + CloneSPForge {
-    async fn is_forge(&self) -> bool;
-    async fn crawl(&self, limit: u64, page: u64, rate_limit: u64) -> CrawlResp;
+    /// Check if the instance is running this forge's software. Instances running other
+    /// software are `Ok(false)`, errors are reserved for instances that can't be probed
+    async fn is_forge(&self) -> ForgeResult<bool>;
+    /// Crawl a page of repositories. The first page is crawled without a token, subsequent
+    /// pages with the [CrawlResp::next_token] of the previous page.
+    ///
+    /// When `since`(unix timestamp) is set, only repositories updated since then need to be
+    /// crawled. Forges that can't tell when repositories were updated crawl all of them.
+    ///
+    /// All API calls must go through `rate_limiter`, which is shared by all crawls of the forge.
+    async fn crawl(
+        &self,
+        limit: u64,
+        token: Option<String>,
+        since: Option<i64>,
+        rate_limiter: &RateLimiter,
+    ) -> ForgeResult<CrawlResp>;
     fn get_url(&self) -> &Url;
     fn forge_type(&self) -> ForgeImplementation;
 }
@@ -140,3 +186,47 @@ impl Clone for Box<dyn SCForge> {
         (**self).clone_forge()
     }
 }
+
+/// Create a forge client for a forge instance URL
+pub type ForgeBuilder = Arc<dyn Fn(&Url) -> Box<dyn SCForge> + Send + Sync>;
+
+/// Registry of supported forge implementations, used to find out which software a forge
+/// instance is running
+#[derive(Clone, Default)]
+pub struct ForgeRegistry {
+    builders: Vec<ForgeBuilder>,
+}
+
+impl ForgeRegistry {
+    /// Register a forge implementation. Implementations are probed in the order in which they
+    /// are registered.
+    pub fn register(&mut self, builder: ForgeBuilder) {
+        self.builders.push(builder);
+    }
+
+    /// Probe forge instance with all registered implementations and return the first match
+    pub async fn detect(&self, url: &Url) -> ForgeResult<Option<Box<dyn SCForge>>> {
+        for builder in self.builders.iter() {
+            let forge = builder(url);
+            if forge.is_forge().await? {
+                return Ok(Some(forge));
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn page_tokens_work() {
+        assert_eq!(page_from_token(None).unwrap(), 1);
+        assert_eq!(page_from_token(Some(&next_page_token(1))).unwrap(), 2);
+        assert!(matches!(
+            page_from_token(Some("not-a-page")),
+            Err(ForgeError::InvalidToken(t)) if t == "not-a-page"
+        ));
+    }
+}
@@ -0,0 +1,339 @@
+/*
+ * ForgeFlux StarChart - A federated software forge spider
+ * Copyright © 2023 Aravinth Manivannan <realaravinth@batsense.net>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+//! Per-forge rate limiting that adapts to how the forge responds
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use db_core::ForgeRateLimit;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::{RequestBuilder, Response, StatusCode};
+
+use crate::errors::{check_status, ForgeError, ForgeResult};
+
+/// Delay between API calls never grows beyond this, unless the forge asks for a longer minimum
+/// delay. Forges can't block calls for longer than this either
+pub const MAX_DELAY: Duration = Duration::from_secs(300);
+
+/// Minimum delay between API calls that forges ask for is capped to this
+pub const MAX_MIN_DELAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Requests that were rate limited or failed on the forge's side are retried this many times
+pub const MAX_RETRIES: usize = 3;
+
+/// `X-RateLimit-Reset` values above this are unix timestamps, the rest are seconds from now
+const RESET_IS_TIMESTAMP: u64 = 1_000_000_000;
+
+struct Inner {
+    min_delay: Duration,
+    delay: Duration,
+    next_request: Instant,
+    blocked_until: Option<SystemTime>,
+}
+
+/// Rate limiter of a forge. API calls are spaced by a delay that doubles when the forge rate
+/// limits or fails requests and shrinks back to the configured minimum while it is healthy.
+/// `Retry-After` and `X-RateLimit-*` headers are honored.
+pub struct RateLimiter {
+    inner: Mutex<Inner>,
+}
+
+impl RateLimiter {
+    /// Create rate limiter that waits at least `min_delay`, up to [MAX_MIN_DELAY], between API
+    /// calls
+    pub fn new(min_delay: Duration) -> Self {
+        let min_delay = min_delay.min(MAX_MIN_DELAY);
+        Self {
+            inner: Mutex::new(Inner {
+                min_delay,
+                delay: min_delay,
+                next_request: Instant::now(),
+                blocked_until: None,
+            }),
+        }
+    }
+
+    /// Create rate limiter that resumes from persisted state
+    pub fn with_state(min_delay: Duration, state: &ForgeRateLimit) -> Self {
+        let limiter = Self::new(min_delay);
+        {
+            let mut inner = limiter.inner.lock().unwrap();
+            let min_delay = inner.min_delay;
+            inner.delay =
+                Duration::from_millis(state.delay).clamp(min_delay, MAX_DELAY.max(min_delay));
+            inner.blocked_until = state
+                .blocked_until
+                .and_then(|t| UNIX_EPOCH.checked_add(Duration::from_secs(t.max(0) as u64)))
+                .map(|t| t.min(SystemTime::now() + MAX_DELAY));
+        }
+        limiter
+    }
+
+    /// Change the minimum delay between API calls, up to [MAX_MIN_DELAY]
+    pub fn set_min_delay(&self, min_delay: Duration) {
+        let min_delay = min_delay.min(MAX_MIN_DELAY);
+        let mut inner = self.inner.lock().unwrap();
+        inner.min_delay = min_delay;
+        inner.delay = inner.delay.max(min_delay);
+    }
+
+    /// State to persist, so that restarts don't hammer the forge
+    pub fn state(&self) -> ForgeRateLimit {
+        let inner = self.inner.lock().unwrap();
+        ForgeRateLimit {
+            delay: inner.delay.as_millis() as u64,
+            blocked_until: inner
+                .blocked_until
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|t| t.as_secs() as i64),
+        }
+    }
+
+    /// current delay between API calls
+    pub fn delay(&self) -> Duration {
+        self.inner.lock().unwrap().delay
+    }
+
+    /// Wait until the next API call is allowed. Concurrent callers are spaced out too
+    pub async fn wait(&self) {
+        let at = {
+            let mut inner = self.inner.lock().unwrap();
+            let now = Instant::now();
+            let mut at = inner.next_request.max(now);
+            if let Some(blocked_until) = inner.blocked_until {
+                match blocked_until.duration_since(SystemTime::now()) {
+                    Ok(wait) => at = at.max(later(now, wait)),
+                    Err(_) => inner.blocked_until = None,
+                }
+            }
+            inner.next_request = later(at, inner.delay);
+            at
+        };
+        tokio::time::sleep_until(at.into()).await;
+    }
+
+    /// Adapt to a forge response
+    pub fn observe(&self, status: StatusCode, headers: &HeaderMap) {
+        fn header(headers: &HeaderMap, names: &[&str]) -> Option<u64> {
+            names
+                .iter()
+                .find_map(|n| headers.get(*n))
+                .and_then(|h| h.to_str().ok())
+                .and_then(|h| h.trim().parse().ok())
+        }
+
+        let mut inner = self.inner.lock().unwrap();
+        let now = SystemTime::now();
+        // waits that forges ask for are capped to MAX_DELAY
+        let after = |wait: Duration| now.checked_add(wait.min(MAX_DELAY)).unwrap_or(now);
+
+        if status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error() {
+            inner.delay = inner
+                .delay
+                .saturating_mul(2)
+                .clamp(Duration::from_secs(1), MAX_DELAY.max(inner.min_delay));
+        } else if status.is_success() {
+            inner.delay = (inner.delay.saturating_mul(3) / 4).max(inner.min_delay);
+        }
+
+        if let Some(retry_after) = header(headers, &[RETRY_AFTER.as_str()]) {
+            inner.blocked_until = Some(after(Duration::from_secs(retry_after)));
+        }
+
+        let remaining = header(headers, &["x-ratelimit-remaining", "ratelimit-remaining"]);
+        let window = header(headers, &["x-ratelimit-reset", "ratelimit-reset"]).map(|r| {
+            let r = Duration::from_secs(r);
+            if r > Duration::from_secs(RESET_IS_TIMESTAMP) {
+                let since_epoch = now.duration_since(UNIX_EPOCH).unwrap_or_default();
+                r.saturating_sub(since_epoch)
+            } else {
+                r
+            }
+        });
+        if let (Some(remaining), Some(window)) = (remaining, window) {
+            if remaining == 0 {
+                let reset = after(window);
+                inner.blocked_until = Some(inner.blocked_until.map_or(reset, |b| b.max(reset)));
+            } else {
+                // spread the remaining calls over what is left of the window
+                let spread = (window / remaining.min(u32::MAX as u64) as u32).min(MAX_DELAY);
+                inner.delay = inner.delay.max(spread);
+            }
+        }
+    }
+
+    /// Adapt to a request that didn't reach the forge
+    pub fn observe_failure(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.delay = inner
+            .delay
+            .saturating_mul(2)
+            .clamp(Duration::from_secs(1), MAX_DELAY.max(inner.min_delay));
+    }
+
+    /// Send request when the rate limit allows it. Requests that are rate limited or that fail on
+    /// the forge's side are retried, up to [MAX_RETRIES] times.
+    pub async fn send(&self, req: RequestBuilder) -> ForgeResult<Response> {
+        let mut retries = 0;
+        let mut req = req;
+        loop {
+            let retry = if retries < MAX_RETRIES {
+                req.try_clone()
+            } else {
+                None
+            };
+            self.wait().await;
+            let res = match req.send().await {
+                Ok(res) => res,
+                Err(e) => {
+                    self.observe_failure();
+                    match retry {
+                        Some(retry) if e.is_timeout() || e.is_connect() => {
+                            req = retry;
+                            retries += 1;
+                            continue;
+                        }
+                        _ => return Err(e.into()),
+                    }
+                }
+            };
+            self.observe(res.status(), res.headers());
+            match check_status(res) {
+                Err(ForgeError::RateLimited { .. }) | Err(ForgeError::HttpStatus(500..=599))
+                    if retry.is_some() =>
+                {
+                    req = retry.unwrap();
+                    retries += 1;
+                }
+                res => return res,
+            }
+        }
+    }
+}
+
+/// `at` + `wait`, or `at` if that can't be represented
+fn later(at: Instant, wait: Duration) -> Instant {
+    at.checked_add(wait).unwrap_or(at)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn rate_limiter_adapts() {
+        let min_delay = Duration::from_secs(2);
+        let limiter = RateLimiter::new(min_delay);
+        let headers = HeaderMap::new();
+
+        // backs off exponentially when the forge fails
+        limiter.observe(StatusCode::INTERNAL_SERVER_ERROR, &headers);
+        assert_eq!(limiter.delay(), Duration::from_secs(4));
+        limiter.observe(StatusCode::BAD_GATEWAY, &headers);
+        assert_eq!(limiter.delay(), Duration::from_secs(8));
+        limiter.observe(StatusCode::TOO_MANY_REQUESTS, &headers);
+        assert_eq!(limiter.delay(), Duration::from_secs(16));
+
+        // speeds up when the forge is healthy, but never beyond the minimum delay
+        limiter.observe(StatusCode::OK, &headers);
+        assert_eq!(limiter.delay(), Duration::from_secs(12));
+        for _ in 0..20 {
+            limiter.observe(StatusCode::OK, &headers);
+        }
+        assert_eq!(limiter.delay(), min_delay);
+
+        // Retry-After blocks calls
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("60"));
+        limiter.observe(StatusCode::TOO_MANY_REQUESTS, &headers);
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+        let state = limiter.state();
+        assert!(state.blocked_until.unwrap() >= now + 59);
+        assert_eq!(state.delay, 4000);
+
+        // state is restored
+        let restored = RateLimiter::with_state(min_delay, &state);
+        assert_eq!(restored.state(), state);
+
+        // remaining calls are spread over the rate limit window
+        let limiter = RateLimiter::new(Duration::ZERO);
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ratelimit-remaining", HeaderValue::from_static("10"));
+        headers.insert("x-ratelimit-reset", HeaderValue::from_static("100"));
+        limiter.observe(StatusCode::OK, &headers);
+        assert!(limiter.delay() >= Duration::from_secs(9));
+        assert!(limiter.state().blocked_until.is_none());
+
+        headers.insert("x-ratelimit-remaining", HeaderValue::from_static("0"));
+        limiter.observe(StatusCode::OK, &headers);
+        assert!(limiter.state().blocked_until.unwrap() >= now + 99);
+    }
+
+    #[test]
+    fn huge_values_are_capped() {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+        let max_delay = MAX_DELAY.as_secs() as i64;
+
+        let limiter = RateLimiter::new(Duration::ZERO);
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            RETRY_AFTER,
+            HeaderValue::from_static("18446744073709551615"),
+        );
+        limiter.observe(StatusCode::TOO_MANY_REQUESTS, &headers);
+        let blocked_until = limiter.state().blocked_until.unwrap();
+        assert!(blocked_until >= now + max_delay - 1 && blocked_until <= now + max_delay + 1);
+
+        let limiter = RateLimiter::new(Duration::ZERO);
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ratelimit-remaining", HeaderValue::from_static("0"));
+        headers.insert(
+            "x-ratelimit-reset",
+            HeaderValue::from_static("18446744073709551615"),
+        );
+        limiter.observe(StatusCode::OK, &headers);
+        let blocked_until = limiter.state().blocked_until.unwrap();
+        assert!(blocked_until <= now + max_delay + 1);
+        headers.insert("x-ratelimit-remaining", HeaderValue::from_static("1"));
+        limiter.observe(StatusCode::OK, &headers);
+        assert_eq!(limiter.delay(), MAX_DELAY);
+
+        // minimum delays asked for are capped and keep doubling delays from overflowing
+        let limiter = RateLimiter::new(Duration::MAX);
+        assert_eq!(limiter.delay(), MAX_MIN_DELAY);
+        limiter.observe(StatusCode::INTERNAL_SERVER_ERROR, &headers);
+        limiter.observe_failure();
+        assert_eq!(limiter.delay(), MAX_MIN_DELAY);
+        let restored = RateLimiter::with_state(
+            Duration::MAX,
+            &ForgeRateLimit {
+                delay: u64::MAX,
+                blocked_until: Some(i64::MAX),
+            },
+        );
+        assert_eq!(restored.delay(), MAX_MIN_DELAY);
+        assert!(restored.state().blocked_until.unwrap() <= now + max_delay + 1);
+    }
+}
@@ -16,13 +16,14 @@
  * along with this program.  If not, see <http://www.gnu.org/licenses/>.
  */
 use std::sync::Arc;
-use std::time::Duration;
 
+use reqwest::header::{HeaderMap, LINK};
 use reqwest::Client;
-use tokio::task::JoinHandle;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
 use url::Url;
 
-use db_core::ForgeImplementation;
+use db_core::{ForgeImplementation, RepositoryMetadata};
 use forge_core::dev::*;
 use forge_core::Repository;
 
@@ -31,36 +32,88 @@ pub mod schema;
 const REPO_SEARCH_PATH: &str = "/api/v1/repos/search";
 const GITEA_NODEINFO: &str = "/api/v1/nodeinfo";
 const GITEA_IDENTIFIER: &str = "gitea";
+const FORGEJO_IDENTIFIER: &str = "forgejo";
+const TOTAL_COUNT_HEADER: &str = "x-total-count";
+
+/// Is there a page after `page`? Gitea links to the next page in the `Link` header and sends
+/// the number of results in `X-Total-Count`. Gitea caps `limit` to its own maximum page size,
+/// so a short page doesn't mean that it was the last one
+fn has_next_page(headers: &HeaderMap, page: u64, limit: u64, len: usize) -> bool {
+    if let Some(link) = headers.get(LINK).and_then(|l| l.to_str().ok()) {
+        return link
+            .split(',')
+            .any(|l| l.split(';').skip(1).any(|p| p.trim() == "rel=\"next\""));
+    }
+    match headers
+        .get(TOTAL_COUNT_HEADER)
+        .and_then(|c| c.to_str().ok())
+        .and_then(|c| c.parse::<u64>().ok())
+    {
+        Some(total) => page.saturating_sub(1).saturating_mul(limit) + (len as u64) < total,
+        None => len != 0,
+    }
+}
 
 #[derive(Clone)]
 pub struct Gitea {
     pub instance_url: Url,
     pub client: Client,
     url: Url,
+    forge_type: ForgeImplementation,
 }
 
 impl Gitea {
     pub fn new(instance_url: Url, client: Client) -> Self {
+        Self::with_forge_type(instance_url, client, ForgeImplementation::Gitea)
+    }
+
+    /// Forgejo is a Gitea fork that serves the same API, so it is crawled with the same client.
+    pub fn forgejo(instance_url: Url, client: Client) -> Self {
+        Self::with_forge_type(instance_url, client, ForgeImplementation::Forgejo)
+    }
+
+    fn with_forge_type(instance_url: Url, client: Client, forge_type: ForgeImplementation) -> Self {
         let url = Url::parse(&db_core::clean_url(&instance_url)).unwrap();
 
         Self {
             instance_url,
             client,
             url,
+            forge_type,
+        }
+    }
+
+    /// software name reported in nodeinfo by instances of this forge type
+    fn identifier(&self) -> &'static str {
+        match self.forge_type {
+            ForgeImplementation::Forgejo => FORGEJO_IDENTIFIER,
+            _ => GITEA_IDENTIFIER,
         }
     }
 }
 
 impl PartialEq for Gitea {
     fn eq(&self, other: &Self) -> bool {
-        self.url == other.url && self.instance_url == other.instance_url
+        self.url == other.url
+            && self.instance_url == other.instance_url
+            && self.forge_type == other.forge_type
     }
 }
 
 #[async_trait]
 impl SCForge for Gitea {
-    async fn is_forge(&self) -> bool {
-        true
+    async fn is_forge(&self) -> ForgeResult<bool> {
+        let mut url = self.instance_url.clone();
+        url.set_path(GITEA_NODEINFO);
+        let res = match check_status(self.client.get(url).send().await?) {
+            Ok(res) => res,
+            Err(ForgeError::HttpStatus(_)) => return Ok(false),
+            Err(e) => return Err(e),
+        };
+        match res.json::<schema::NodeInfo>().await {
+            Ok(nodeinfo) => Ok(nodeinfo.software.name == self.identifier()),
+            Err(_) => Ok(false),
+        }
     }
 
     fn get_url(&self) -> &Url {
@@ -68,10 +121,16 @@ impl SCForge for Gitea {
     }
 
     fn forge_type(&self) -> ForgeImplementation {
-        ForgeImplementation::Gitea
+        self.forge_type.clone()
     }
 
-    async fn crawl(&self, limit: u64, page: u64, rate_limit: u64) -> CrawlResp {
+    async fn crawl(
+        &self,
+        limit: u64,
+        token: Option<String>,
+        since: Option<i64>,
+        rate_limiter: &RateLimiter,
+    ) -> ForgeResult<CrawlResp> {
         fn empty_is_none(s: &str) -> Option<String> {
             let s = s.trim();
             if s.is_empty() {
@@ -86,19 +145,21 @@ impl SCForge for Gitea {
         let mut repos = Repositories::default();
 
         let instance_url = self.instance_url.clone();
+        let page = page_from_token(token.as_deref())?;
 
         let mut url = instance_url.clone();
         url.set_path(REPO_SEARCH_PATH);
-        url.set_query(Some(&format!("page={page}&limit={limit}")));
-        let mut res: schema::SearchResults = self
-            .client
-            .get(url)
-            .send()
-            .await
-            .unwrap()
-            .json()
-            .await
-            .unwrap();
+        if since.is_some() {
+            url.set_query(Some(&format!(
+                "page={page}&limit={limit}&sort=updated&order=desc"
+            )));
+        } else {
+            url.set_query(Some(&format!("page={page}&limit={limit}")));
+        }
+        let res = rate_limiter.send(self.client.get(url)).await?;
+        let headers = res.headers().clone();
+        let mut res: schema::SearchResults = res.json().await?;
+        let mut has_more = has_next_page(&headers, page, limit, res.data.len());
 
         fn to_user(u: schema::User, g: &Gitea) -> Arc<forge_core::User> {
             let mut profile_url = g.instance_url.clone();
@@ -112,9 +173,32 @@ impl SCForge for Gitea {
             })
         }
 
-        let mut sleep_fut: Option<JoinHandle<()>> = None;
+        fn unix_timestamp(t: &str) -> ForgeResult<i64> {
+            OffsetDateTime::parse(t, &Rfc3339)
+                .map(|t| t.unix_timestamp())
+                .map_err(|e| ForgeError::Decode(Box::new(e)))
+        }
 
         for repo in res.data.drain(0..) {
+            let updated_at = match unix_timestamp(&repo.updated_at) {
+                Ok(updated_at) => updated_at,
+                Err(e) => {
+                    log::warn!(
+                        "Skipping {}: can't parse updated_at {:?}: {e}",
+                        repo.html_url,
+                        repo.updated_at
+                    );
+                    continue;
+                }
+            };
+            if let Some(since) = since {
+                // repositories are sorted by last update, the rest are older
+                if updated_at < since {
+                    has_more = false;
+                    break;
+                }
+            }
+
             let user = if !users.contains_key(&repo.owner.username) {
                 let u = to_user(repo.owner, self);
                 let username = u.username.clone();
@@ -130,22 +214,11 @@ impl SCForge for Gitea {
                 &user.username, repo.name
             ));
 
-            if let Some(sleep_fut) = sleep_fut {
-                sleep_fut.await.unwrap();
-            }
-
-            let mut topics: schema::Topics = self
-                .client
-                .get(url)
-                .send()
-                .await
-                .unwrap()
+            let mut topics: schema::Topics = rate_limiter
+                .send(self.client.get(url))
+                .await?
                 .json()
-                .await
-                .unwrap();
-            sleep_fut = Some(tokio::spawn(tokio::time::sleep(Duration::new(
-                rate_limit, 0,
-            ))));
+                .await?;
 
             let mut rtopics = Vec::with_capacity(topics.topics.len());
             for t in topics.topics.drain(0..) {
@@ -156,6 +229,20 @@ impl SCForge for Gitea {
                 rtopics.push(t);
             }
 
+            let metadata = RepositoryMetadata {
+                stars: Some(repo.stars_count as u32),
+                forks: Some(repo.forks_count as u32),
+                fork: repo.fork,
+                parent: repo.parent.map(|p| p.html_url),
+                mirror: repo.mirror,
+                archived: repo.archived,
+                default_branch: empty_is_none(&repo.default_branch),
+                clone_url: empty_is_none(&repo.clone_url),
+                language: empty_is_none(&repo.language),
+                created_at: unix_timestamp(&repo.created_at).ok(),
+                updated_at: Some(updated_at),
+            };
+
             let frepo = Repository {
                 url: self.url.clone(),
                 website: empty_is_none(&repo.website),
@@ -164,32 +251,163 @@ impl SCForge for Gitea {
                 html_link: repo.html_url,
                 tags: Some(rtopics),
                 description: Some(repo.description),
+                metadata,
             };
 
             repos.push(frepo);
         }
-        CrawlResp { repos, tags, users }
+        Ok(CrawlResp {
+            repos,
+            tags,
+            users,
+            next_token: Some(next_page_token(page)),
+            has_more,
+        })
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::time::Duration;
     use url::Url;
 
+    use mock_gitea::{Fixtures, MockGitea, RateLimit};
+    use reqwest::StatusCode;
+
     pub const GITEA_HOST: &str = "http://localhost:8080";
     pub const NET_REPOSITORIES: u64 = 100;
     pub const PER_CRAWL: u64 = 10;
 
     #[actix_rt::test]
     async fn gitea_works() {
-        let ctx = Gitea::new(Url::parse(GITEA_HOST).unwrap(), Client::new());
-        assert!(ctx.is_forge().await);
+        let gitea = MockGitea::start(Fixtures::generate("bot", NET_REPOSITORIES as usize));
+        let ctx = Gitea::new(gitea.url().clone(), Client::new());
+        assert!(ctx.is_forge().await.unwrap());
+        assert!(!Gitea::forgejo(gitea.url().clone(), Client::new())
+            .is_forge()
+            .await
+            .unwrap());
         let steps = NET_REPOSITORIES / PER_CRAWL;
 
-        for i in 0..steps {
-            let res = ctx.crawl(PER_CRAWL, i, 0).await;
+        let rate_limiter = RateLimiter::new(Duration::ZERO);
+        let mut token = None;
+        let requests = gitea.requests();
+        for step in 0..steps {
+            let res = ctx
+                .crawl(PER_CRAWL, token.take(), None, &rate_limiter)
+                .await
+                .unwrap();
             assert_eq!(res.repos.len() as u64, PER_CRAWL);
+            assert_eq!(res.has_more, step + 1 < steps);
+            for repo in res.repos.iter() {
+                let i: usize = repo.name.trim_start_matches("repository_").parse().unwrap();
+                let language = if i % 2 == 1 { None } else { Some("Rust") };
+                assert_eq!(repo.metadata.language.as_deref(), language);
+            }
+            token = res.next_token;
         }
+        // the last page is known from Gitea's headers, without requesting an empty page
+        assert_eq!(
+            gitea.requests(),
+            requests + (steps * (PER_CRAWL + 1)) as usize
+        );
+
+        // incremental crawls stop at repositories that weren't updated since
+        let since = OffsetDateTime::parse("2023-04-02T00:01:35Z", &Rfc3339).unwrap();
+        let res = ctx
+            .crawl(PER_CRAWL, None, Some(since.unix_timestamp()), &rate_limiter)
+            .await
+            .unwrap();
+        assert_eq!(res.repos.len(), 5);
+        assert_eq!(res.repos[0].name, "repository_99");
+        assert!(!res.has_more);
+
+        // failures on the forge's side are retried, the rest aren't
+        gitea.fail_next(1, StatusCode::BAD_GATEWAY);
+        assert_eq!(
+            ctx.crawl(1, None, None, &rate_limiter)
+                .await
+                .unwrap()
+                .repos
+                .len(),
+            1
+        );
+        gitea.fail_next(1, StatusCode::NOT_FOUND);
+        assert!(ctx.crawl(1, None, None, &rate_limiter).await.is_err());
+
+        // exhausted rate limits block further calls
+        gitea.set_rate_limit(Some(RateLimit {
+            requests: 2,
+            window: Duration::from_secs(60),
+        }));
+        let rate_limiter = RateLimiter::new(Duration::ZERO);
+        ctx.crawl(1, None, None, &rate_limiter).await.unwrap();
+        assert!(rate_limiter.state().blocked_until.is_some());
+
+        gitea.stop().await;
+    }
+
+    #[actix_rt::test]
+    async fn bad_timestamps_are_tolerated() {
+        let mut fixtures = Fixtures::generate("bot", 3);
+        fixtures.repositories[0].created_at = "yesterday".into();
+        fixtures.repositories[1].updated_at = "yesterday".into();
+        let gitea = MockGitea::start(fixtures);
+        let ctx = Gitea::new(gitea.url().clone(), Client::new());
+
+        let res = ctx
+            .crawl(PER_CRAWL, None, None, &RateLimiter::new(Duration::ZERO))
+            .await
+            .unwrap();
+        let names: Vec<&str> = res.repos.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["repository_0", "repository_2"]);
+        assert!(res.repos[0].metadata.created_at.is_none());
+        assert!(res.repos[1].metadata.created_at.is_some());
+
+        gitea.stop().await;
+    }
+
+    #[test]
+    fn forgejo_flavour_works() {
+        let url = Url::parse(GITEA_HOST).unwrap();
+        let gitea = Gitea::new(url.clone(), Client::new());
+        let forgejo = Gitea::forgejo(url, Client::new());
+
+        assert_eq!(gitea.forge_type(), ForgeImplementation::Gitea);
+        assert_eq!(gitea.identifier(), GITEA_IDENTIFIER);
+        assert_eq!(forgejo.forge_type(), ForgeImplementation::Forgejo);
+        assert_eq!(forgejo.identifier(), FORGEJO_IDENTIFIER);
+        assert_eq!(gitea.get_url(), forgejo.get_url());
+        assert!(gitea != forgejo);
+    }
+
+    #[test]
+    fn has_next_page_works() {
+        let mut headers = HeaderMap::new();
+        // nothing to go by but the page itself
+        assert!(has_next_page(&headers, 3, 10, 10));
+        assert!(!has_next_page(&headers, 3, 10, 0));
+
+        headers.insert(TOTAL_COUNT_HEADER, "25".parse().unwrap());
+        assert!(has_next_page(&headers, 2, 10, 10));
+        assert!(!has_next_page(&headers, 3, 10, 5));
+
+        // links take precedence
+        headers.insert(
+            LINK,
+            "<https://git.example.org/api/v1/repos/search?page=1&limit=10>; rel=\"first\""
+                .parse()
+                .unwrap(),
+        );
+        assert!(!has_next_page(&headers, 2, 10, 10));
+        headers.insert(
+            LINK,
+            "<https://git.example.org/api/v1/repos/search?page=3&limit=10>; rel=\"next\",\
+             <https://git.example.org/api/v1/repos/search?page=1&limit=10>; rel=\"first\""
+                .parse()
+                .unwrap(),
+        );
+        assert!(has_next_page(&headers, 2, 10, 10));
     }
 }
@@ -75,6 +75,8 @@ pub struct Repository {
     pub release_counter: usize,
     pub default_branch: String,
     pub archived: bool,
+    #[serde(default)]
+    pub language: String,
     pub created_at: String,
     pub updated_at: String,
     pub internal_tracker: InternalIssueTracker,
@@ -150,6 +152,18 @@ pub struct Topics {
     pub topics: Vec<String>,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NodeInfo {
+    pub version: String,
+    pub software: Software,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Software {
+    pub name: String,
+    pub version: String,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -169,4 +183,17 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn nodeinfo_schema_doesnt_panic() {
+        let files = [
+            ("./tests/schema/gitea/nodeinfo.json", "gitea"),
+            ("./tests/schema/gitea/forgejo_nodeinfo.json", "forgejo"),
+        ];
+        for (file, software) in files.iter() {
+            let contents = fs::read_to_string(file).unwrap();
+            let nodeinfo: NodeInfo = serde_json::from_str(&contents).expect("Gitea schema paniced");
+            assert_eq!(&nodeinfo.software.name, software);
+        }
+    }
 }
@@ -0,0 +1,421 @@
+/*
+ * ForgeFlux StarChart - A federated software forge spider
+ * Copyright © 2023 Aravinth Manivannan <realaravinth@batsense.net>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+use std::sync::Arc;
+
+use reqwest::Client;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+use url::Url;
+
+use db_core::{ForgeImplementation, RepositoryMetadata};
+use forge_core::dev::*;
+use forge_core::Repository;
+
+pub mod schema;
+
+const PROJECTS_PATH: &str = "/api/v4/projects";
+/// empty on the last page
+const NEXT_PAGE_HEADER: &str = "x-next-page";
+/// Replaces the `/` between groups and subgroups in usernames, which Starchart uses as a single
+/// path segment. GitLab doesn't allow it in group paths, so usernames don't collide
+const SUBGROUP_SEPARATOR: &str = "~";
+
+#[derive(Clone)]
+pub struct GitLab {
+    pub instance_url: Url,
+    pub client: Client,
+    url: Url,
+}
+
+impl GitLab {
+    pub fn new(instance_url: Url, client: Client) -> Self {
+        let url = Url::parse(&db_core::clean_url(&instance_url)).unwrap();
+
+        Self {
+            instance_url,
+            client,
+            url,
+        }
+    }
+
+    /// `since` is an RFC 3339 timestamp. When set, only projects with activity after it are
+    /// listed
+    fn projects_url(&self, limit: u64, page: u64, since: Option<&str>) -> Url {
+        let mut url = self.instance_url.clone();
+        url.set_path(PROJECTS_PATH);
+        url.set_query(Some(&format!(
+            "visibility=public&order_by=id&sort=asc&page={page}&per_page={limit}"
+        )));
+        if let Some(since) = since {
+            url.query_pairs_mut()
+                .append_pair("last_activity_after", since);
+        }
+        url
+    }
+
+    /// Username of the owner of projects in `namespace`. Projects can belong to nested subgroups,
+    /// whose full path is joined with [SUBGROUP_SEPARATOR]
+    fn username(namespace: &schema::Namespace) -> String {
+        namespace.full_path.replace('/', SUBGROUP_SEPARATOR)
+    }
+
+    /// GitLab sends avatar URLs of uploads relative to the instance
+    fn absolute_url(&self, link: String) -> String {
+        if link.starts_with('/') {
+            let mut url = self.instance_url.clone();
+            url.set_path(&link);
+            url.to_string()
+        } else {
+            link
+        }
+    }
+}
+
+impl PartialEq for GitLab {
+    fn eq(&self, other: &Self) -> bool {
+        self.url == other.url && self.instance_url == other.instance_url
+    }
+}
+
+#[async_trait]
+impl SCForge for GitLab {
+    async fn is_forge(&self) -> ForgeResult<bool> {
+        let res = self
+            .client
+            .get(self.projects_url(1, 1, None))
+            .send()
+            .await?;
+        let res = match check_status(res) {
+            Ok(res) => res,
+            Err(ForgeError::HttpStatus(_)) => return Ok(false),
+            Err(e) => return Err(e),
+        };
+        Ok(res.json::<schema::Projects>().await.is_ok())
+    }
+
+    fn get_url(&self) -> &Url {
+        &self.url
+    }
+
+    fn forge_type(&self) -> ForgeImplementation {
+        ForgeImplementation::GitLab
+    }
+
+    async fn crawl(
+        &self,
+        limit: u64,
+        token: Option<String>,
+        since: Option<i64>,
+        rate_limiter: &RateLimiter,
+    ) -> ForgeResult<CrawlResp> {
+        fn empty_is_none(s: Option<String>) -> Option<String> {
+            let s = s?;
+            let s = s.trim();
+            if s.is_empty() {
+                None
+            } else {
+                Some(s.to_owned())
+            }
+        }
+
+        fn unix_timestamp(t: &str) -> Option<i64> {
+            OffsetDateTime::parse(t, &Rfc3339)
+                .map(|t| t.unix_timestamp())
+                .ok()
+        }
+
+        let mut tags = Tags::default();
+        let mut users = UserMap::default();
+        let mut repos = Repositories::default();
+
+        let page = page_from_token(token.as_deref())?;
+        // timestamps that can't be represented fall back to a full crawl
+        let since = since
+            .and_then(|s| OffsetDateTime::from_unix_timestamp(s).ok())
+            .and_then(|s| s.format(&Rfc3339).ok());
+
+        // topics are part of the projects listing, so there's only one API call per page
+        let res = rate_limiter
+            .send(
+                self.client
+                    .get(self.projects_url(limit, page, since.as_deref())),
+            )
+            .await?;
+        let next_page = res
+            .headers()
+            .get(NEXT_PAGE_HEADER)
+            .and_then(|h| h.to_str().ok())
+            .map(|h| h.trim().to_owned())
+            .filter(|h| !h.is_empty());
+        let mut res: schema::Projects = res.json().await?;
+
+        fn to_user(n: schema::Namespace, g: &GitLab) -> Arc<forge_core::User> {
+            let username = Arc::new(GitLab::username(&n));
+            Arc::new(forge_core::User {
+                username,
+                html_link: n.web_url,
+                profile_photo: n.avatar_url.map(|a| g.absolute_url(a)),
+                url: g.url.clone(),
+            })
+        }
+
+        for mut project in res.drain(0..) {
+            let username = Self::username(&project.namespace);
+            let user = if !users.contains_key(&username) {
+                let u = to_user(project.namespace.clone(), self);
+                users.insert(u.username.clone(), u.clone());
+                u
+            } else {
+                users.get(&username).unwrap().clone()
+            };
+
+            let mut topics = project.topics();
+            let mut rtopics = Vec::with_capacity(topics.len());
+            for t in topics.drain(0..) {
+                let t = Arc::new(t);
+                if !tags.contains(&t) {
+                    tags.insert(t.clone());
+                }
+                rtopics.push(t);
+            }
+
+            let metadata = RepositoryMetadata {
+                stars: Some(project.star_count as u32),
+                forks: Some(project.forks_count as u32),
+                fork: project.forked_from_project.is_some(),
+                parent: project.forked_from_project.map(|p| p.web_url),
+                mirror: project.mirror,
+                archived: project.archived,
+                default_branch: project.default_branch,
+                clone_url: Some(project.http_url_to_repo),
+                language: None,
+                created_at: unix_timestamp(&project.created_at),
+                updated_at: unix_timestamp(&project.last_activity_at),
+            };
+
+            let frepo = Repository {
+                url: self.url.clone(),
+                website: None,
+                name: project.path,
+                owner: user,
+                html_link: project.web_url,
+                tags: Some(rtopics),
+                description: empty_is_none(project.description),
+                metadata,
+            };
+
+            repos.push(frepo);
+        }
+        Ok(CrawlResp {
+            repos,
+            tags,
+            users,
+            has_more: next_page.is_some(),
+            next_token: next_page,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::fs;
+    use std::net::TcpListener;
+    use std::time::Duration;
+    use url::Url;
+
+    use actix_web::dev::ServerHandle;
+    use actix_web::{web, App, HttpResponse, HttpServer};
+
+    pub const GITLAB_HOST: &str = "https://gitlab.example.com";
+    /// one page of projects per line
+    pub const FIXTURES: &str = "./tests/schema/gitlab/gitlab.com.json";
+
+    /// Serve `pages` of projects like GitLab's projects API. Pages are numbered from 1, others
+    /// are not found
+    fn gitlab_server(pages: Vec<String>) -> (Url, ServerHandle) {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let url = Url::parse(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+        let pages = web::Data::new(pages);
+        let server = HttpServer::new(move || {
+            App::new().app_data(pages.clone()).route(
+                PROJECTS_PATH,
+                web::get().to(
+                    |pages: web::Data<Vec<String>>,
+                     query: web::Query<HashMap<String, String>>| async move {
+                        let page: usize = query.get("page").unwrap().parse().unwrap();
+                        match pages.get(page.wrapping_sub(1)) {
+                            Some(projects) => {
+                                let next = if page < pages.len() {
+                                    (page + 1).to_string()
+                                } else {
+                                    String::default()
+                                };
+                                HttpResponse::Ok()
+                                    .content_type("application/json")
+                                    .insert_header((NEXT_PAGE_HEADER, next))
+                                    .body(projects.clone())
+                            }
+                            None => HttpResponse::NotFound().finish(),
+                        }
+                    },
+                ),
+            )
+        })
+        .workers(1)
+        .disable_signals()
+        .shutdown_timeout(0)
+        .listen(listener)
+        .unwrap()
+        .run();
+        let handle = server.handle();
+        actix_web::rt::spawn(server);
+        (url, handle)
+    }
+
+    #[actix_rt::test]
+    async fn gitlab_works() {
+        let pages: Vec<String> = fs::read_to_string(FIXTURES)
+            .unwrap()
+            .lines()
+            .map(|l| l.to_owned())
+            .collect();
+        let (url, handle) = gitlab_server(pages);
+        let ctx = GitLab::new(url.clone(), Client::new());
+        assert!(ctx.is_forge().await.unwrap());
+        let rate_limiter = RateLimiter::new(Duration::ZERO);
+
+        // first page
+        let res = ctx.crawl(2, None, None, &rate_limiter).await.unwrap();
+        assert!(res.has_more);
+        assert_eq!(res.next_token.as_deref(), Some("2"));
+        assert_eq!(res.repos.len(), 2);
+
+        let starchart = &res.repos[0];
+        assert_eq!(starchart.name, "starchart");
+        assert_eq!(starchart.owner.username.as_str(), "forgeflux");
+        assert_eq!(
+            starchart.html_link,
+            "https://gitlab.com/forgeflux/starchart"
+        );
+        assert_eq!(starchart.url, ctx.url);
+        assert_eq!(
+            starchart.description.as_deref(),
+            Some("Federated forge spider")
+        );
+        let topics: Vec<&str> = starchart
+            .tags
+            .as_ref()
+            .unwrap()
+            .iter()
+            .map(|t| t.as_str())
+            .collect();
+        assert_eq!(topics, ["spider", "federation"]);
+        assert_eq!(starchart.metadata.stars, Some(7));
+        assert_eq!(starchart.metadata.forks, Some(2));
+        assert!(!starchart.metadata.fork && !starchart.metadata.mirror);
+        assert_eq!(
+            starchart.metadata.clone_url.as_deref(),
+            Some("https://gitlab.com/forgeflux/starchart.git")
+        );
+        assert_eq!(
+            starchart.metadata.created_at,
+            Some(
+                OffsetDateTime::parse("2022-04-10T11:09:21.004Z", &Rfc3339)
+                    .unwrap()
+                    .unix_timestamp()
+            )
+        );
+
+        let identity = &res.repos[1];
+        assert!(identity.metadata.fork);
+        assert_eq!(
+            identity.metadata.parent.as_deref(),
+            Some("https://gitlab.com/forgeflux/identity")
+        );
+        assert!(res.users.keys().any(|u| u.as_str() == "forgeflux"));
+        assert!(res.users.keys().any(|u| u.as_str() == "realaravinth"));
+        assert!(res.tags.iter().any(|t| t.as_str() == "federation"));
+
+        // last page
+        let res = ctx
+            .crawl(2, res.next_token, None, &rate_limiter)
+            .await
+            .unwrap();
+        assert!(!res.has_more);
+        assert_eq!(res.next_token, None);
+
+        // topics of instances that predate them
+        let legacy = &res.repos[0];
+        assert_eq!(legacy.tags.as_ref().unwrap().len(), 2);
+        assert!(res.tags.iter().any(|t| t.as_str() == "legacy"));
+
+        // projects of subgroups belong to a user whose name is a single path segment
+        let mirror = &res.repos[1];
+        assert_eq!(mirror.owner.username.as_str(), "forgeflux~mirrors");
+        assert_eq!(
+            mirror.owner.html_link,
+            "https://gitlab.com/groups/forgeflux/mirrors"
+        );
+        let mut avatar = url.clone();
+        avatar.set_path("/uploads/-/system/group/avatar/5600/mirrors.png");
+        assert_eq!(mirror.owner.profile_photo.as_deref(), Some(avatar.as_str()));
+        assert!(mirror.metadata.mirror);
+        assert!(mirror.metadata.archived);
+
+        // pages that don't exist are errors
+        assert!(ctx
+            .crawl(2, Some("3".into()), None, &rate_limiter)
+            .await
+            .is_err());
+
+        handle.stop(true).await;
+
+        // instances that don't serve GitLab's API aren't GitLab
+        let (url, handle) = gitlab_server(Vec::default());
+        assert!(!GitLab::new(url, Client::new()).is_forge().await.unwrap());
+        let (url, handle_html) = gitlab_server(vec!["<html></html>".into()]);
+        assert!(!GitLab::new(url, Client::new()).is_forge().await.unwrap());
+        handle.stop(true).await;
+        handle_html.stop(true).await;
+    }
+
+    #[test]
+    fn relative_links_are_resolved() {
+        let ctx = GitLab::new(Url::parse(GITLAB_HOST).unwrap(), Client::new());
+        assert_eq!(ctx.forge_type(), ForgeImplementation::GitLab);
+        assert_eq!(
+            ctx.absolute_url("/uploads/-/system/group/avatar/1/avatar.png".into()),
+            "https://gitlab.example.com/uploads/-/system/group/avatar/1/avatar.png"
+        );
+        let absolute = "https://secure.gravatar.com/avatar/1?s=80&d=identicon";
+        assert_eq!(ctx.absolute_url(absolute.into()), absolute);
+
+        let projects = ctx.projects_url(20, 3, None);
+        assert_eq!(projects.path(), PROJECTS_PATH);
+        assert!(projects.query().unwrap().contains("page=3&per_page=20"));
+        assert!(!projects.query().unwrap().contains("last_activity_after"));
+
+        let projects = ctx.projects_url(20, 3, Some("2023-04-16T09:12:53Z"));
+        assert!(projects
+            .query()
+            .unwrap()
+            .contains("last_activity_after=2023-04-16T09%3A12%3A53Z"));
+    }
+}
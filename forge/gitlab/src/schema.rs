@@ -0,0 +1,114 @@
+/*
+ * ForgeFlux StarChart - A federated software forge spider
+ * Copyright © 2023 Aravinth Manivannan <realaravinth@batsense.net>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+use serde::{Deserialize, Serialize};
+
+/// Response of `GET /api/v4/projects`
+pub type Projects = Vec<Project>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NamespaceKind {
+    User,
+    Group,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Namespace {
+    pub id: u64,
+    pub name: String,
+    pub path: String,
+    pub kind: NamespaceKind,
+    pub full_path: String,
+    pub parent_id: Option<u64>,
+    pub avatar_url: Option<String>,
+    pub web_url: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ForkedFromProject {
+    pub id: u64,
+    pub name: String,
+    pub path_with_namespace: String,
+    pub web_url: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Project {
+    pub id: u64,
+    pub description: Option<String>,
+    pub name: String,
+    pub name_with_namespace: String,
+    pub path: String,
+    pub path_with_namespace: String,
+    pub created_at: String,
+    pub default_branch: Option<String>,
+    /// deprecated in favour of `topics` since GitLab 14.5 but still sent by older instances
+    #[serde(default)]
+    pub tag_list: Vec<String>,
+    #[serde(default)]
+    pub topics: Vec<String>,
+    pub ssh_url_to_repo: String,
+    pub http_url_to_repo: String,
+    pub web_url: String,
+    pub readme_url: Option<String>,
+    pub avatar_url: Option<String>,
+    pub forks_count: usize,
+    pub star_count: usize,
+    pub last_activity_at: String,
+    pub namespace: Namespace,
+    #[serde(default)]
+    pub archived: bool,
+    #[serde(default)]
+    pub empty_repo: bool,
+    /// only sent by instances that support pull mirroring
+    #[serde(default)]
+    pub mirror: bool,
+    pub forked_from_project: Option<ForkedFromProject>,
+}
+
+impl Project {
+    /// Topics of the project. Falls back to `tag_list` on instances that predate `topics`
+    pub fn topics(&mut self) -> Vec<String> {
+        if self.topics.is_empty() {
+            std::mem::take(&mut self.tag_list)
+        } else {
+            std::mem::take(&mut self.topics)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+
+    #[test]
+    /// Tests if GitLab responses panic when deserialized with serde into structs defined in this
+    /// module/file. Fields that were added or deprecated across GitLab versions are defaulted, so
+    /// responses from older instances should decode too.
+    fn schema_doesnt_panic() {
+        let files = ["./tests/schema/gitlab/gitlab.com.json"];
+        for file in files.iter() {
+            let contents = fs::read_to_string(file).unwrap();
+            for line in contents.lines() {
+                let _: Projects = serde_json::from_str(line).expect("GitLab schema paniced");
+            }
+        }
+    }
+}
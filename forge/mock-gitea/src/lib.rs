@@ -0,0 +1,529 @@
+/*
+ * ForgeFlux StarChart - A federated software forge spider
+ * Copyright © 2023 Aravinth Manivannan <realaravinth@batsense.net>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+//! In-process mock of the Gitea API that Starchart crawls. It serves repositories from fixtures
+//! and can be made to fail or rate limit requests, so that crawlers can be tested without a
+//! live Gitea instance.
+// Gitea repositories have too many fields for serde_json::json!'s default recursion limit
+#![recursion_limit = "256"]
+use std::collections::{BTreeMap, VecDeque};
+use std::net::TcpListener;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use actix_web::dev::ServerHandle;
+use actix_web::http::StatusCode;
+use actix_web::{web, App, HttpResponse, HttpServer};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use url::Url;
+
+pub const REPO_SEARCH_PATH: &str = "/api/v1/repos/search";
+pub const TOPICS_PATH: &str = "/api/v1/repos/{owner}/{repo}/topics";
+pub const NODEINFO_PATH: &str = "/api/v1/nodeinfo";
+pub const WELL_KNOWN_PATH: &str = "/.well-known/{path:.*}";
+
+/// Gitea's default maximum page size
+pub const MAX_PAGE_SIZE: usize = 50;
+
+/// Repository served by the mock
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FixtureRepository {
+    pub owner: String,
+    pub name: String,
+    pub description: String,
+    pub website: String,
+    pub topics: Vec<String>,
+    pub stars: usize,
+    pub forks: usize,
+    pub archived: bool,
+    /// primary language, empty if unknown
+    pub language: String,
+    /// RFC 3339 timestamp
+    pub created_at: String,
+    /// RFC 3339 timestamp
+    pub updated_at: String,
+}
+
+/// Data served by the mock
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Fixtures {
+    /// software name reported in nodeinfo: `gitea` or `forgejo`
+    pub software: String,
+    /// page size is capped to this, like Gitea does
+    pub max_page_size: usize,
+    pub repositories: Vec<FixtureRepository>,
+    /// contents of files served under `/.well-known/`, keyed by their path relative to it
+    pub well_known: BTreeMap<String, String>,
+}
+
+impl Fixtures {
+    /// `count`(at most 86400) repositories named `repository_{i}` and owned by `owner`, like the
+    /// ones that `scripts/gitea.py` creates on a live instance. Repositories that come later were
+    /// updated more recently, and the language of even-numbered repositories is Rust.
+    pub fn generate(owner: &str, count: usize) -> Self {
+        let repositories = (0..count)
+            .map(|i| {
+                let name = format!("repository_{i}");
+                FixtureRepository {
+                    owner: owner.into(),
+                    description: format!("this repository is named {name}"),
+                    website: format!("https://{name}.example.org"),
+                    topics: vec!["starchart".into(), format!("topic_{i}")],
+                    stars: i,
+                    forks: i / 2,
+                    archived: false,
+                    language: if i % 2 == 1 { "" } else { "Rust" }.into(),
+                    created_at: "2023-04-01T00:00:00Z".into(),
+                    updated_at: format!(
+                        "2023-04-02T{:02}:{:02}:{:02}Z",
+                        i / 3600,
+                        (i / 60) % 60,
+                        i % 60
+                    ),
+                    name,
+                }
+            })
+            .collect();
+        Self {
+            software: "gitea".into(),
+            max_page_size: MAX_PAGE_SIZE,
+            repositories,
+            well_known: BTreeMap::default(),
+        }
+    }
+}
+
+/// Rate limit enforced by the mock. Requests over the limit are answered with `429 Too Many
+/// Requests` until the window is over.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RateLimit {
+    /// requests allowed in a window
+    pub requests: u64,
+    pub window: Duration,
+}
+
+struct Behaviour {
+    failures: VecDeque<StatusCode>,
+    rate_limit: Option<RateLimit>,
+    window_started: Instant,
+    used: u64,
+    requests: usize,
+}
+
+struct State {
+    url: Url,
+    fixtures: Fixtures,
+    behaviour: Mutex<Behaviour>,
+}
+
+impl State {
+    /// Answer request with `res`, unless it must fail or is rate limited
+    fn serve(&self, res: impl FnOnce() -> HttpResponse) -> HttpResponse {
+        let mut behaviour = self.behaviour.lock().unwrap();
+        behaviour.requests += 1;
+        if let Some(status) = behaviour.failures.pop_front() {
+            return HttpResponse::build(status).finish();
+        }
+
+        let rate_limit = match behaviour.rate_limit {
+            Some(rate_limit) => rate_limit,
+            None => return res(),
+        };
+        let now = Instant::now();
+        if now.duration_since(behaviour.window_started) >= rate_limit.window {
+            behaviour.window_started = now;
+            behaviour.used = 0;
+        }
+        let left = rate_limit.window - now.duration_since(behaviour.window_started);
+        let reset = left.as_millis().div_ceil(1000).to_string();
+        if behaviour.used >= rate_limit.requests {
+            return HttpResponse::TooManyRequests()
+                .insert_header(("Retry-After", reset.as_str()))
+                .insert_header(("X-RateLimit-Remaining", "0"))
+                .insert_header(("X-RateLimit-Reset", reset.as_str()))
+                .finish();
+        }
+        behaviour.used += 1;
+        let remaining = (rate_limit.requests - behaviour.used).to_string();
+        drop(behaviour);
+
+        let mut res = res();
+        let headers = res.headers_mut();
+        headers.insert(
+            "x-ratelimit-remaining".parse().unwrap(),
+            remaining.parse().unwrap(),
+        );
+        headers.insert("x-ratelimit-reset".parse().unwrap(), reset.parse().unwrap());
+        res
+    }
+
+    fn user(&self, owner: &str) -> Value {
+        json!({
+            "id": 1,
+            "login": owner,
+            "full_name": "",
+            "email": format!("{owner}@example.com"),
+            "avatar_url": format!("{}avatars/{owner}", self.url),
+            "language": "",
+            "is_admin": false,
+            "last_login": "0001-01-01T00:00:00Z",
+            "created": "2023-04-01T00:00:00Z",
+            "restricted": false,
+            "active": false,
+            "prohibit_login": false,
+            "location": "",
+            "website": "",
+            "description": "",
+            "visibility": "public",
+            "followers_count": 0,
+            "following_count": 0,
+            "starred_repos_count": 0,
+            "username": owner,
+        })
+    }
+
+    fn repository(&self, r: &FixtureRepository) -> Value {
+        let html_url = format!("{}{}/{}", self.url, r.owner, r.name);
+        json!({
+            "name": r.name,
+            "full_name": format!("{}/{}", r.owner, r.name),
+            "description": r.description,
+            "empty": false,
+            "private": false,
+            "fork": false,
+            "template": false,
+            "parent": null,
+            "mirror": false,
+            "size": 0,
+            "html_url": html_url,
+            "ssh_url": format!("git@{}:{}/{}.git", self.url.host_str().unwrap(), r.owner, r.name),
+            "clone_url": format!("{html_url}.git"),
+            "original_url": "",
+            "owner": self.user(&r.owner),
+            "website": r.website,
+            "stars_count": r.stars,
+            "forks_count": r.forks,
+            "watchers_count": 1,
+            "open_issues_count": 0,
+            "open_pr_counter": 0,
+            "release_counter": 0,
+            "default_branch": "master",
+            "archived": r.archived,
+            "language": r.language,
+            "created_at": r.created_at,
+            "updated_at": r.updated_at,
+            "internal_tracker": {
+                "enable_time_tracker": true,
+                "allow_only_contributors_to_track_time": true,
+                "enable_issue_dependencies": true
+            },
+            "has_issues": true,
+            "has_wiki": true,
+            "has_pull_requests": true,
+            "has_projects": true,
+            "ignore_whitespace_conflicts": false,
+            "allow_merge_commits": true,
+            "allow_rebase": true,
+            "allow_rebase_explicit": true,
+            "allow_squash_merge": true,
+            "default_merge_style": "merge",
+            "avatar_url": "",
+            "internal": false,
+            "mirror_interval": "",
+            "mirror_updated": "0001-01-01T00:00:00Z",
+            "repo_transfer": null,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct SearchQuery {
+    page: Option<usize>,
+    limit: Option<usize>,
+    sort: Option<String>,
+    order: Option<String>,
+}
+
+async fn search(state: web::Data<State>, q: web::Query<SearchQuery>) -> HttpResponse {
+    state.serve(|| {
+        let max_page_size = state.fixtures.max_page_size;
+        let page = q.page.unwrap_or(1).max(1);
+        let limit = q.limit.unwrap_or(max_page_size).clamp(1, max_page_size);
+
+        let mut repos: Vec<&FixtureRepository> = state.fixtures.repositories.iter().collect();
+        if q.sort.as_deref() == Some("updated") {
+            repos.sort_by(|a, b| a.updated_at.cmp(&b.updated_at));
+            if q.order.as_deref() == Some("desc") {
+                repos.reverse();
+            }
+        }
+        let data: Vec<Value> = repos
+            .iter()
+            .skip((page - 1) * limit)
+            .take(limit)
+            .map(|r| state.repository(r))
+            .collect();
+
+        // Gitea links to neighbouring pages in the Link header and sends the number of results
+        // in X-Total-Count
+        let link = |page: usize, rel: &str| {
+            let mut url = state.url.clone();
+            url.set_path(REPO_SEARCH_PATH);
+            url.query_pairs_mut()
+                .append_pair("page", &page.to_string())
+                .append_pair("limit", &limit.to_string());
+            format!("<{url}>; rel=\"{rel}\"")
+        };
+        let last = repos.len().div_ceil(limit).max(1);
+        let mut links = Vec::new();
+        if page < last {
+            links.push(link(page + 1, "next"));
+            links.push(link(last, "last"));
+        }
+        if page > 1 {
+            links.push(link(1, "first"));
+            links.push(link(page - 1, "prev"));
+        }
+
+        let mut res = HttpResponse::Ok();
+        res.insert_header(("x-total-count", repos.len().to_string()));
+        if !links.is_empty() {
+            res.insert_header(("link", links.join(",")));
+        }
+        res.json(json!({ "ok": true, "data": data }))
+    })
+}
+
+async fn topics(state: web::Data<State>, path: web::Path<(String, String)>) -> HttpResponse {
+    let (owner, name) = path.into_inner();
+    state.serve(|| {
+        match state
+            .fixtures
+            .repositories
+            .iter()
+            .find(|r| r.owner == owner && r.name == name)
+        {
+            Some(r) => HttpResponse::Ok().json(json!({ "topics": r.topics })),
+            None => HttpResponse::NotFound()
+                .json(json!({ "errors": [], "message": "The target couldn't be found." })),
+        }
+    })
+}
+
+async fn nodeinfo(state: web::Data<State>) -> HttpResponse {
+    state.serve(|| {
+        HttpResponse::Ok().json(json!({
+            "version": "2.1",
+            "software": {
+                "name": state.fixtures.software,
+                "version": "1.19.0",
+            },
+            "protocols": ["activitypub"],
+            "openRegistrations": false,
+            "metadata": {},
+        }))
+    })
+}
+
+async fn well_known(state: web::Data<State>, path: web::Path<String>) -> HttpResponse {
+    state.serve(|| match state.fixtures.well_known.get(path.as_str()) {
+        Some(contents) => HttpResponse::Ok().body(contents.clone()),
+        None => HttpResponse::NotFound().finish(),
+    })
+}
+
+/// Mock Gitea instance, listening on a free local port. Must be started from within an actix
+/// runtime, like the one set up by `actix_rt::test`.
+pub struct MockGitea {
+    state: web::Data<State>,
+    handle: ServerHandle,
+}
+
+impl MockGitea {
+    /// Start serving `fixtures`
+    pub fn start(fixtures: Fixtures) -> Self {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let url = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+        let state = web::Data::new(State {
+            url,
+            fixtures,
+            behaviour: Mutex::new(Behaviour {
+                failures: VecDeque::default(),
+                rate_limit: None,
+                window_started: Instant::now(),
+                used: 0,
+                requests: 0,
+            }),
+        });
+
+        let app_state = state.clone();
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(app_state.clone())
+                .route(REPO_SEARCH_PATH, web::get().to(search))
+                .route(TOPICS_PATH, web::get().to(topics))
+                .route(NODEINFO_PATH, web::get().to(nodeinfo))
+                .route(WELL_KNOWN_PATH, web::get().to(well_known))
+        })
+        .workers(1)
+        .disable_signals()
+        .shutdown_timeout(0)
+        .listen(listener)
+        .unwrap()
+        .run();
+        let handle = server.handle();
+        actix_web::rt::spawn(server);
+        Self { state, handle }
+    }
+
+    /// URL of the instance
+    pub fn url(&self) -> &Url {
+        &self.state.url
+    }
+
+    /// Answer the next `requests` requests with `status`
+    pub fn fail_next(&self, requests: usize, status: StatusCode) {
+        let mut behaviour = self.state.behaviour.lock().unwrap();
+        behaviour.failures.extend((0..requests).map(|_| status));
+    }
+
+    /// Enforce rate limit. `None` lifts it
+    pub fn set_rate_limit(&self, rate_limit: Option<RateLimit>) {
+        let mut behaviour = self.state.behaviour.lock().unwrap();
+        behaviour.rate_limit = rate_limit;
+        behaviour.window_started = Instant::now();
+        behaviour.used = 0;
+    }
+
+    /// Number of requests received so far
+    pub fn requests(&self) -> usize {
+        self.state.behaviour.lock().unwrap().requests
+    }
+
+    /// Stop serving
+    pub async fn stop(self) {
+        self.handle.stop(true).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[actix_rt::test]
+    async fn mock_gitea_works() {
+        let mut fixtures = Fixtures::generate("bot", 60);
+        fixtures
+            .well_known
+            .insert("starchart/example.org".into(), "example.org".into());
+        let gitea = MockGitea::start(fixtures);
+        let client = reqwest::Client::new();
+        let get = |path: &str| {
+            let mut url = gitea.url().clone();
+            url.set_path(path);
+            client.get(url)
+        };
+
+        let nodeinfo: Value = get(NODEINFO_PATH)
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(nodeinfo["software"]["name"], "gitea");
+
+        // page size is capped, the last page doesn't link to a next one
+        let res = get(REPO_SEARCH_PATH)
+            .query(&[("page", "2"), ("limit", "100")])
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.headers()["x-total-count"], "60");
+        let link = res.headers()["link"].to_str().unwrap();
+        assert!(link.contains("rel=\"prev\""));
+        assert!(!link.contains("rel=\"next\""));
+        let res: Value = res.json().await.unwrap();
+        let data = res["data"].as_array().unwrap();
+        assert_eq!(data.len(), 10);
+        assert_eq!(data[0]["name"], "repository_50");
+        assert_eq!(
+            data[0]["html_url"],
+            format!("{}bot/repository_50", gitea.url())
+        );
+
+        let res = get(REPO_SEARCH_PATH)
+            .query(&[("limit", "1"), ("sort", "updated"), ("order", "desc")])
+            .send()
+            .await
+            .unwrap();
+        let link = res.headers()["link"].to_str().unwrap();
+        assert!(link.contains(&format!(
+            "<{}api/v1/repos/search?page=2&limit=1>; rel=\"next\"",
+            gitea.url()
+        )));
+        let res: Value = res.json().await.unwrap();
+        assert_eq!(res["data"][0]["name"], "repository_59");
+
+        let topics: Value = get("/api/v1/repos/bot/repository_1/topics")
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(topics["topics"], json!(["starchart", "topic_1"]));
+        let res = get("/api/v1/repos/bot/nope/topics").send().await.unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND.as_u16());
+
+        let res = get("/.well-known/starchart/example.org")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.text().await.unwrap(), "example.org");
+        let res = get("/.well-known/starchart/example.com")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND.as_u16());
+
+        // failures
+        gitea.fail_next(2, StatusCode::BAD_GATEWAY);
+        for _ in 0..2 {
+            let res = get(NODEINFO_PATH).send().await.unwrap();
+            assert_eq!(res.status(), StatusCode::BAD_GATEWAY.as_u16());
+        }
+        let res = get(NODEINFO_PATH).send().await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK.as_u16());
+
+        // rate limits
+        gitea.set_rate_limit(Some(RateLimit {
+            requests: 1,
+            window: Duration::from_secs(60),
+        }));
+        let res = get(NODEINFO_PATH).send().await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK.as_u16());
+        assert_eq!(res.headers()["x-ratelimit-remaining"], "0");
+        let res = get(NODEINFO_PATH).send().await.unwrap();
+        assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS.as_u16());
+        assert_eq!(res.headers()["retry-after"], "60");
+
+        assert_eq!(gitea.requests(), 12);
+        gitea.stop().await;
+    }
+}
@@ -0,0 +1,484 @@
+/*
+ * ForgeFlux StarChart - A federated software forge spider
+ * Copyright © 2023 Aravinth Manivannan <realaravinth@batsense.net>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+use std::sync::Arc;
+
+use reqwest::{Client, RequestBuilder, StatusCode};
+use url::Url;
+
+use db_core::{ForgeImplementation, RepositoryMetadata};
+use forge_core::dev::*;
+use forge_core::Repository;
+
+pub mod schema;
+
+const GRAPHQL_PATH: &str = "/query";
+const VERSION_QUERY: &str = "query { version { major minor patch } }";
+const REPOSITORIES_QUERY: &str = "query repositories($username: String!, $cursor: Cursor) {
+    user(username: $username) {
+        repositories(cursor: $cursor) {
+            results { id name description visibility updated owner { canonicalName } }
+            cursor
+        }
+    }
+}";
+
+/// git.sr.ht only lists repositories of a user through its GraphQL API, and every request to the
+/// API must be authenticated. So repositories of configured users are crawled with an OAuth 2.0
+/// personal access token that has the `git.sr.ht/REPOSITORIES:RO` grant. The token is only sent
+/// to instances that host configured users, never to forges that are merely probed
+#[derive(Clone)]
+pub struct SourceHut {
+    pub instance_url: Url,
+    pub client: Client,
+    url: Url,
+    access_token: Option<String>,
+    /// usernames, without the `~` prefix, of users whose repositories are crawled
+    users: Vec<String>,
+}
+
+impl SourceHut {
+    /// `users` are profile links of users, like `https://git.sr.ht/~sircmpwn`. Only users of
+    /// this instance are crawled, and `access_token` is dropped if it hosts none of them
+    pub fn new(
+        instance_url: Url,
+        client: Client,
+        access_token: Option<String>,
+        users: &[Url],
+    ) -> Self {
+        let url = Url::parse(&db_core::clean_url(&instance_url)).unwrap();
+        let users = users
+            .iter()
+            .filter(|u| db_core::clean_url(u) == url.as_str())
+            .map(|u| {
+                u.path()
+                    .trim_matches('/')
+                    .trim_start_matches('~')
+                    .to_owned()
+            })
+            .filter(|u| !u.is_empty())
+            .collect::<Vec<_>>();
+        let access_token = access_token.filter(|_| !users.is_empty());
+
+        Self {
+            instance_url,
+            client,
+            url,
+            access_token,
+            users,
+        }
+    }
+
+    fn graphql_request<V: serde::Serialize>(&self, query: &schema::Query<V>) -> RequestBuilder {
+        let req = self.client.post(self.graphql_url()).json(query);
+        match &self.access_token {
+            Some(token) => req.bearer_auth(token),
+            None => req,
+        }
+    }
+
+    fn graphql_url(&self) -> Url {
+        let mut url = self.instance_url.clone();
+        url.set_path(GRAPHQL_PATH);
+        url.set_query(None);
+        url
+    }
+
+    /// `owner` is the canonical name of the user, which is prefixed with `~`
+    fn profile_link(&self, owner: &str) -> String {
+        let mut url = self.instance_url.clone();
+        url.set_path(owner);
+        url.set_query(None);
+        url.to_string()
+    }
+
+    fn repository_link(&self, owner: &str, name: &str) -> String {
+        let mut url = self.instance_url.clone();
+        url.set_path(&format!("{owner}/{name}"));
+        url.set_query(None);
+        url.to_string()
+    }
+}
+
+impl PartialEq for SourceHut {
+    fn eq(&self, other: &Self) -> bool {
+        self.url == other.url && self.instance_url == other.instance_url
+    }
+}
+
+#[async_trait]
+impl SCForge for SourceHut {
+    async fn is_forge(&self) -> ForgeResult<bool> {
+        let query = schema::Query {
+            query: VERSION_QUERY,
+            variables: (),
+        };
+        // probed without the access token: the forge might not be the one it was issued by
+        let res = self
+            .client
+            .post(self.graphql_url())
+            .json(&query)
+            .send()
+            .await?;
+        let unauthorized = res.status() == StatusCode::UNAUTHORIZED;
+        let res = if unauthorized {
+            res
+        } else {
+            match check_status(res) {
+                Ok(res) => res,
+                Err(ForgeError::HttpStatus(_)) => return Ok(false),
+                Err(e) => return Err(e),
+            }
+        };
+        // git.sr.ht answers unauthenticated requests with GraphQL errors
+        match res.json::<schema::Response<schema::VersionData>>().await {
+            Ok(res) => Ok(res.data.is_some() || (unauthorized && !res.errors.is_empty())),
+            Err(_) => Ok(false),
+        }
+    }
+
+    fn get_url(&self) -> &Url {
+        &self.url
+    }
+
+    fn forge_type(&self) -> ForgeImplementation {
+        ForgeImplementation::SourceHut
+    }
+
+    /// git.sr.ht paginates with cursors and has a fixed page size, so pages are cut to `limit`
+    /// repositories and the continuation token is a [Position] in the listings of users. The
+    /// listing can't be filtered by last update, so `since` is ignored
+    async fn crawl(
+        &self,
+        limit: u64,
+        token: Option<String>,
+        _since: Option<i64>,
+        rate_limiter: &RateLimiter,
+    ) -> ForgeResult<CrawlResp> {
+        fn empty_is_none(s: Option<String>) -> Option<String> {
+            let s = s?;
+            let s = s.trim();
+            if s.is_empty() {
+                None
+            } else {
+                Some(s.to_owned())
+            }
+        }
+
+        let tags = Tags::default();
+        let mut users = UserMap::default();
+        let mut repos = Repositories::default();
+
+        let position = Position::from_token(token.as_deref())?;
+        let username = match self.users.get(position.user) {
+            Some(username) => username,
+            None if token.is_none() => {
+                return Ok(CrawlResp {
+                    repos,
+                    tags,
+                    users,
+                    has_more: false,
+                    next_token: None,
+                })
+            }
+            None => return Err(ForgeError::InvalidToken(token.unwrap())),
+        };
+
+        let query = schema::Query {
+            query: REPOSITORIES_QUERY,
+            variables: schema::RepositoriesVariables {
+                username: username.clone(),
+                cursor: position.cursor.clone(),
+            },
+        };
+        let res: schema::Response<schema::RepositoriesData> = rate_limiter
+            .send(self.graphql_request(&query))
+            .await?
+            .json()
+            .await?;
+        let listing = match res.data {
+            Some(data) => match data.user {
+                Some(user) => user.repositories,
+                // users that were deleted have no repositories
+                None => schema::RepositoryCursor {
+                    results: Vec::default(),
+                    cursor: None,
+                },
+            },
+            None => {
+                let errors: Vec<String> = res.errors.into_iter().map(|e| e.message).collect();
+                return Err(ForgeError::Decode(errors.join(", ").into()));
+            }
+        };
+
+        fn to_user(owner: &str, s: &SourceHut) -> Arc<forge_core::User> {
+            Arc::new(forge_core::User {
+                username: Arc::new(owner.to_owned()),
+                html_link: s.profile_link(owner),
+                profile_photo: None,
+                url: s.url.clone(),
+            })
+        }
+
+        let page_len = listing.results.len();
+        let end = page_len.min(position.skip + limit.max(1) as usize);
+        for repo in listing.results.into_iter().take(end).skip(position.skip) {
+            if repo.visibility != schema::Visibility::Public {
+                continue;
+            }
+
+            let owner = &repo.owner.canonical_name;
+            let user = if !users.contains_key(owner) {
+                let u = to_user(owner, self);
+                users.insert(u.username.clone(), u.clone());
+                u
+            } else {
+                users.get(owner).unwrap().clone()
+            };
+
+            let frepo = Repository {
+                url: self.url.clone(),
+                website: None,
+                html_link: self.repository_link(owner, &repo.name),
+                name: repo.name,
+                owner: user,
+                tags: None,
+                description: empty_is_none(repo.description),
+                metadata: RepositoryMetadata::default(),
+            };
+
+            repos.push(frepo);
+        }
+
+        let next = if end < page_len {
+            Some(Position {
+                skip: end,
+                ..position
+            })
+        } else if listing.cursor.is_some() {
+            Some(Position {
+                user: position.user,
+                skip: 0,
+                cursor: listing.cursor,
+            })
+        } else if position.user + 1 < self.users.len() {
+            Some(Position {
+                user: position.user + 1,
+                skip: 0,
+                cursor: None,
+            })
+        } else {
+            None
+        };
+        // private and unlisted repositories are skipped, so pages can be empty before the end of
+        // the listing
+        Ok(CrawlResp {
+            repos,
+            tags,
+            users,
+            has_more: next.is_some(),
+            next_token: next.map(|p| p.to_token()),
+        })
+    }
+}
+
+/// Position of a crawl in the listings of users: index of the user in [SourceHut::users], cursor
+/// of the page and number of repositories of the page that were already crawled
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct Position {
+    user: usize,
+    skip: usize,
+    cursor: Option<String>,
+}
+
+impl Position {
+    fn from_token(token: Option<&str>) -> ForgeResult<Self> {
+        let token = match token {
+            Some(token) => token,
+            None => return Ok(Self::default()),
+        };
+        let invalid = || ForgeError::InvalidToken(token.to_owned());
+        let mut parts = token.splitn(3, ':');
+        let user = parts
+            .next()
+            .ok_or_else(invalid)?
+            .parse()
+            .map_err(|_| invalid())?;
+        let skip = parts
+            .next()
+            .ok_or_else(invalid)?
+            .parse()
+            .map_err(|_| invalid())?;
+        let cursor = parts.next().ok_or_else(invalid)?;
+        let cursor = if cursor.is_empty() {
+            None
+        } else {
+            Some(cursor.to_owned())
+        };
+        Ok(Self { user, skip, cursor })
+    }
+
+    fn to_token(&self) -> String {
+        format!(
+            "{}:{}:{}",
+            self.user,
+            self.skip,
+            self.cursor.as_deref().unwrap_or_default()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+
+    use actix_web::dev::ServerHandle;
+    use actix_web::http::header;
+    use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer};
+    use url::Url;
+
+    use super::*;
+
+    pub const SOURCEHUT_HOST: &str = "https://git.sr.example.com";
+
+    #[test]
+    fn links_are_built() {
+        let users = [
+            Url::parse("https://git.sr.example.com/~realaravinth").unwrap(),
+            Url::parse("https://git.sr.example.com/~sircmpwn/").unwrap(),
+            Url::parse("https://git.sr.other.example.com/~rjarry").unwrap(),
+        ];
+        let ctx = SourceHut::new(
+            Url::parse(SOURCEHUT_HOST).unwrap(),
+            Client::new(),
+            None,
+            &users,
+        );
+        assert_eq!(ctx.users, vec!["realaravinth", "sircmpwn"]);
+        assert!(ctx.access_token.is_none());
+        assert_eq!(ctx.forge_type(), ForgeImplementation::SourceHut);
+        assert_eq!(
+            ctx.graphql_url().as_str(),
+            "https://git.sr.example.com/query"
+        );
+        assert_eq!(
+            ctx.profile_link("~realaravinth"),
+            "https://git.sr.example.com/~realaravinth"
+        );
+        assert_eq!(
+            ctx.repository_link("~realaravinth", "starchart"),
+            "https://git.sr.example.com/~realaravinth/starchart"
+        );
+    }
+
+    #[test]
+    fn access_token_is_kept_for_configured_instances() {
+        let users = [Url::parse("https://git.sr.example.com/~realaravinth").unwrap()];
+        let token = Some("token".to_string());
+        let configured = SourceHut::new(
+            Url::parse(SOURCEHUT_HOST).unwrap(),
+            Client::new(),
+            token.clone(),
+            &users,
+        );
+        assert_eq!(configured.access_token, token);
+        let probed = SourceHut::new(
+            Url::parse("https://forge.example.org").unwrap(),
+            Client::new(),
+            token,
+            &users,
+        );
+        assert!(probed.access_token.is_none());
+    }
+
+    /// Serve `body` with `status` on the GraphQL endpoint. Requests with an `Authorization`
+    /// header are answered with `400 Bad Request`
+    fn graphql_server(status: u16, body: &'static str) -> (Url, ServerHandle) {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let url = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+        let server = HttpServer::new(move || {
+            App::new().route(
+                GRAPHQL_PATH,
+                web::post().to(move |req: HttpRequest| async move {
+                    if req.headers().contains_key(header::AUTHORIZATION) {
+                        return HttpResponse::BadRequest().finish();
+                    }
+                    HttpResponse::build(actix_web::http::StatusCode::from_u16(status).unwrap())
+                        .content_type("application/json")
+                        .body(body)
+                }),
+            )
+        })
+        .workers(1)
+        .disable_signals()
+        .shutdown_timeout(0)
+        .listen(listener)
+        .unwrap()
+        .run();
+        let handle = server.handle();
+        actix_web::rt::spawn(server);
+        (url, handle)
+    }
+
+    #[actix_rt::test]
+    async fn is_forge_works() {
+        let users = [Url::parse("https://git.sr.example.com/~realaravinth").unwrap()];
+        let cases = [
+            (
+                401,
+                r#"{"errors":[{"message":"Authorization header is required"}]}"#,
+                true,
+            ),
+            (
+                200,
+                r#"{"data":{"version":{"major":0,"minor":1,"patch":0}}}"#,
+                true,
+            ),
+            (401, "Unauthorized", false),
+            (404, r#"{"errors":[{"message":"not found"}]}"#, false),
+        ];
+        for (status, body, is_forge) in cases {
+            let (url, handle) = graphql_server(status, body);
+            let ctx = SourceHut::new(url, Client::new(), Some("token".into()), &users);
+            assert_eq!(ctx.is_forge().await.unwrap(), is_forge, "{status} {body}");
+            handle.stop(true).await;
+        }
+    }
+
+    #[test]
+    fn position_tokens_work() {
+        assert_eq!(Position::from_token(None).unwrap(), Position::default());
+        let positions = [
+            Position::default(),
+            Position {
+                user: 2,
+                skip: 20,
+                cursor: Some("ZW5kX29mX3BhZ2VfMTU=:x".into()),
+            },
+        ];
+        for position in positions {
+            let token = position.to_token();
+            assert_eq!(Position::from_token(Some(&token)).unwrap(), position);
+        }
+        for token in ["", "1", "1:x:", "a:0:"] {
+            assert!(matches!(
+                Position::from_token(Some(token)),
+                Err(ForgeError::InvalidToken(t)) if t == token
+            ));
+        }
+    }
+}
@@ -21,6 +21,9 @@ use actix_web_codegen_const_routes::get;
 
 pub use api_routes::*;
 
+use url::Url;
+
+use crate::db::BoxDB;
 use crate::pages::chart::home::{OptionalPage, Page};
 use crate::WebFederate;
 use crate::{errors::*, WebDB};
@@ -28,6 +31,18 @@ use crate::{introduce, search};
 
 const LIMIT: u32 = 50;
 
+/// Get crawl schedule of a forge and a page of its crawl history
+pub async fn get_forge_crawls(db: &BoxDB, req: &ForgeCrawlsReq) -> ServiceResult<ForgeCrawlsResp> {
+    let url = Url::parse(&req.url)?;
+    let schedule = db
+        .get_forge_crawl_schedule(&url)
+        .await?
+        .ok_or(ServiceError::ForgeNotFound)?;
+    let offset = req.page.unwrap_or(1).saturating_sub(1) * LIMIT;
+    let crawls = db.get_forge_crawl_runs(&url, offset, LIMIT).await?;
+    Ok(ForgeCrawlsResp { schedule, crawls })
+}
+
 #[get(path = "ROUTES.forges")]
 pub async fn forges(db: WebDB, q: web::Query<OptionalPage>) -> ServiceResult<impl Responder> {
     let q = q.into_inner();
@@ -38,6 +53,15 @@ pub async fn forges(db: WebDB, q: web::Query<OptionalPage>) -> ServiceResult<imp
     Ok(HttpResponse::Ok().json(forges))
 }
 
+#[get(path = "ROUTES.forge_crawls")]
+pub async fn forge_crawls(
+    db: WebDB,
+    q: web::Query<ForgeCrawlsReq>,
+) -> ServiceResult<impl Responder> {
+    let crawls = get_forge_crawls(&db, &q.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(crawls))
+}
+
 #[get(path = "ROUTES.get_latest")]
 pub async fn lastest(federate: WebFederate) -> ServiceResult<impl Responder> {
     let latest = federate.latest_tar_json().await.unwrap();
@@ -47,6 +71,7 @@ pub async fn lastest(federate: WebFederate) -> ServiceResult<impl Responder> {
 pub fn services(cfg: &mut web::ServiceConfig) {
     cfg.service(lastest);
     cfg.service(forges);
+    cfg.service(forge_crawls);
     search::services(cfg);
     introduce::services(cfg);
 }
@@ -65,6 +90,7 @@ mod tests {
     #[actix_rt::test]
     async fn list_forges_works() {
         const URL: &str = "https://list-forges-works-test.example.com";
+        const FORGEJO_URL: &str = "https://list-forges-works-test-forgejo.example.com";
         const HTML_PROFILE_URL: &str = "https://list-forges-works-test.example.com/user1";
         const USERNAME: &str = "user1";
 
@@ -91,6 +117,14 @@ mod tests {
             "forge creation failed, forge existence check failure"
         );
 
+        let create_forgejo_msg = CreateForge {
+            url: Url::parse(FORGEJO_URL).unwrap(),
+            forge_type: ForgeImplementation::Forgejo,
+            starchart_url: None,
+        };
+        let _ = db.delete_forge_instance(&create_forgejo_msg.url).await;
+        db.create_forge_instance(&create_forgejo_msg).await.unwrap();
+
         // test starts
         let lisit_res_resp = get_request!(&app, ROUTES.forges);
         assert_eq!(lisit_res_resp.status(), StatusCode::OK);
@@ -98,6 +132,11 @@ mod tests {
         assert!(!forges_list.is_empty());
         assert!(forges_list
             .iter()
-            .any(|f| f.url == create_forge_msg.url.to_string()));
+            .any(|f| f.url == create_forge_msg.url.to_string()
+                && f.forge_type == ForgeImplementation::Gitea));
+        assert!(forges_list
+            .iter()
+            .any(|f| f.url == create_forgejo_msg.url.to_string()
+                && f.forge_type == ForgeImplementation::Forgejo));
     }
 }
@@ -19,10 +19,22 @@ use std::sync::Arc;
 use std::time::Duration;
 
 use actix::dev::*;
-use reqwest::{Client, ClientBuilder};
+use reqwest::{redirect, Client, ClientBuilder};
+use trust_dns_resolver::{
+    config::{NameServerConfig, Protocol, ResolverConfig, ResolverOpts},
+    TokioAsyncResolver,
+};
+use url::Url;
+
+use forge_core::{ForgeRegistry, SCForge};
+use gitea::Gitea;
+use gitlab::GitLab;
+use sourcehut::SourceHut;
 
 use crate::master::Master;
-use crate::settings::Settings;
+use crate::settings::{self, ResolverProtocol, Settings};
+use crate::spider::{CrawlErrors, RateLimiters};
+use crate::verify::WELL_KNOWN_TIMEOUT;
 use crate::{PKG_NAME, VERSION};
 
 /// in seconds
@@ -31,8 +43,15 @@ const CLIENT_TIMEOUT: u64 = 60;
 #[derive(Clone)]
 pub struct Ctx {
     pub client: Client,
+    /// client that fetches HTTP challenges. It doesn't follow redirects, so that challenges are
+    /// only read from the forge itself
+    pub well_known_client: Client,
     pub settings: Settings,
     pub master: Addr<Master>,
+    pub forges: ForgeRegistry,
+    pub resolver: TokioAsyncResolver,
+    pub crawl_errors: CrawlErrors,
+    pub rate_limiters: RateLimiters,
 }
 
 impl Ctx {
@@ -53,13 +72,90 @@ impl Ctx {
             .tcp_keepalive(timeout)
             .build()
             .unwrap();
+        let well_known_client = ClientBuilder::new()
+            .user_agent(&*ua)
+            .use_rustls_tls()
+            .redirect(redirect::Policy::none())
+            .timeout(WELL_KNOWN_TIMEOUT)
+            .connect_timeout(WELL_KNOWN_TIMEOUT)
+            .build()
+            .unwrap();
 
         let master = Master::new(45).start();
+        let forges = Self::forge_registry(&client, &settings.sourcehut);
+        let resolver = Self::resolver(&settings.resolver);
 
         Arc::new(Self {
             client,
+            well_known_client,
             settings,
             master,
+            forges,
+            resolver,
+            crawl_errors: CrawlErrors::default(),
+            rate_limiters: RateLimiters::default(),
         })
     }
+
+    /// Supported forges, in the order in which they are probed during forge detection
+    fn forge_registry(client: &Client, sourcehut: &settings::SourceHut) -> ForgeRegistry {
+        let mut forges = ForgeRegistry::default();
+
+        let c = client.clone();
+        forges.register(Arc::new(move |url: &Url| -> Box<dyn SCForge> {
+            Box::new(Gitea::new(url.clone(), c.clone()))
+        }));
+
+        let c = client.clone();
+        forges.register(Arc::new(move |url: &Url| -> Box<dyn SCForge> {
+            Box::new(Gitea::forgejo(url.clone(), c.clone()))
+        }));
+
+        let c = client.clone();
+        forges.register(Arc::new(move |url: &Url| -> Box<dyn SCForge> {
+            Box::new(GitLab::new(url.clone(), c.clone()))
+        }));
+
+        let c = client.clone();
+        let sourcehut = sourcehut.clone();
+        forges.register(Arc::new(move |url: &Url| -> Box<dyn SCForge> {
+            Box::new(SourceHut::new(
+                url.clone(),
+                c.clone(),
+                sourcehut.access_token.clone(),
+                &sourcehut.users,
+            ))
+        }));
+
+        forges
+    }
+
+    /// DNS resolver used to look up verification challenges and spidering configuration
+    pub fn resolver(settings: &settings::Resolver) -> TokioAsyncResolver {
+        let protocols: &[Protocol] = match settings.protocol {
+            ResolverProtocol::System => {
+                return TokioAsyncResolver::tokio_from_system_conf()
+                    .expect("Couldn't read system DNS resolver configuration")
+            }
+            ResolverProtocol::Plain => &[Protocol::Udp, Protocol::Tcp],
+            ResolverProtocol::Tls => &[Protocol::Tls],
+            ResolverProtocol::Https => &[Protocol::Https],
+        };
+
+        let mut conf = ResolverConfig::new();
+        for socket_addr in settings.nameservers.iter() {
+            for protocol in protocols.iter() {
+                conf.add_name_server(NameServerConfig {
+                    socket_addr: *socket_addr,
+                    protocol: *protocol,
+                    tls_dns_name: settings.tls_dns_name.clone(),
+                    trust_nx_responses: true,
+                    tls_config: None,
+                    bind_addr: None,
+                });
+            }
+        }
+        TokioAsyncResolver::tokio(conf, ResolverOpts::default())
+            .expect("Couldn't create DNS resolver")
+    }
 }
@@ -14,11 +14,50 @@
  * You should have received a copy of the GNU Affero General Public License
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
-use crate::settings::Settings;
+use crate::settings::{DBType, Settings};
 use db_core::prelude::*;
 
 pub type BoxDB = Box<dyn SCDatabase>;
 
+/// Connect to the database selected in `settings.database.database_type`
+pub async fn get_data(settings: Option<Settings>) -> BoxDB {
+    let settings = settings.unwrap_or_else(|| Settings::new().unwrap());
+    match settings.database.database_type {
+        DBType::Sqlite => sqlite::get_data(Some(settings)).await,
+        DBType::Postgres => pg::get_data(Some(settings)).await,
+        DBType::Memory => memory::get_data(),
+    }
+}
+
+pub mod pg {
+    use super::*;
+    use db_sqlx_postgres::{ConnectionOptions, Fresh};
+    use sqlx::postgres::PgPoolOptions;
+
+    pub async fn get_data(settings: Option<Settings>) -> BoxDB {
+        let settings = settings.unwrap_or_else(|| Settings::new().unwrap());
+
+        let pool = settings.database.pool;
+        let pool_options = PgPoolOptions::new().max_connections(pool);
+        let connection_options = ConnectionOptions::Fresh(Fresh {
+            pool_options,
+            url: settings.database.url,
+        });
+
+        let db = connection_options.connect().await.unwrap();
+        db.migrate().await.unwrap();
+        Box::new(db)
+    }
+}
+
+pub mod memory {
+    use super::*;
+
+    pub fn get_data() -> BoxDB {
+        Box::new(db_memory::Database::new())
+    }
+}
+
 pub mod sqlite {
     use super::*;
     use db_sqlx_sqlite::{ConnectionOptions, Fresh};
@@ -15,68 +15,239 @@
  * You should have received a copy of the GNU Affero General Public License
  * along with this program.  If not, see <http://www.gnu.org/licenses/>.
  */
+use derive_more::{Display, Error};
 use serde::{Deserialize, Serialize};
+use trust_dns_resolver::{
+    error::{ResolveError, ResolveErrorKind},
+    TokioAsyncResolver,
+};
+use url::{Host, Url};
 
-#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize, Deserialize)]
+/// Forge admins publish spidering configuration in a TXT record at
+/// `{TXT_RECORD_PREFIX}.{forge hostname}`
+pub const TXT_RECORD_PREFIX: &str = "_starchart";
+
+/// Crawl intervals that forges ask for are clamped to at least this, in seconds
+pub const MIN_CRAWL_INTERVAL: u64 = 60 * 60;
+
+/// Crawl intervals that forges ask for are clamped to at most this, in seconds
+pub const MAX_CRAWL_INTERVAL: u64 = 60 * 60 * 24 * 365;
+
+#[derive(Debug, Display, Error)]
+pub enum ConfigurationError {
+    #[display(fmt = "Configuration entry {} is not a key=value pair", _0)]
+    NotAKeyValue(#[error(not(source))] String),
+    #[display(fmt = "Value {} is not a bool, can't set for spidering", _0)]
+    InvalidSpidering(#[error(not(source))] String),
+    #[display(fmt = "Value {} is not a number, can't set for rate", _0)]
+    InvalidRate(#[error(not(source))] String),
+    #[display(fmt = "Value {} is not a positive number, can't set for interval", _0)]
+    InvalidInterval(#[error(not(source))] String),
+    #[display(fmt = "Key {} is not implemented or supported", _0)]
+    UnknownKey(#[error(not(source))] String),
+    #[display(fmt = "Couldn't look up spidering configuration: {}", _0)]
+    Lookup(ResolveError),
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub struct Configuration {
     pub spidering: bool,
+    /// delay between API calls, in seconds
     pub rate: Option<u64>,
+    /// delay between crawls, in seconds. Clamped to [MIN_CRAWL_INTERVAL] and
+    /// [MAX_CRAWL_INTERVAL]
+    pub interval: Option<u64>,
+}
+
+impl Default for Configuration {
+    /// Forges that don't publish a configuration are spidered at the default rate
+    fn default() -> Self {
+        Self {
+            spidering: true,
+            rate: None,
+            interval: None,
+        }
+    }
 }
 
 impl Configuration {
-    pub fn parse(s: &str) -> Self {
-        fn parse_inner(config: &mut Configuration, s: &str) {
-            let mut inner = s.split('=');
-            let k = inner.next().unwrap().trim();
-            let v = inner.next().unwrap().trim();
-            println!("split inner: {:?}: {:?}", k, v);
-
-            if k == "spidering" {
-                if v == "false" {
-                    config.spidering = false;
-                } else if v == "true" {
-                    config.spidering = true;
-                } else {
-                    panic!("Value {k} is not bool, can't set for spidering");
+    pub fn parse(s: &str) -> Result<Self, ConfigurationError> {
+        let mut config = Self::default();
+        for entry in s.split(',') {
+            let entry = entry.trim();
+            if entry.is_empty() {
+                continue;
+            }
+            let (k, v) = match entry.split_once('=') {
+                Some((k, v)) if !v.contains('=') => (k.trim(), v.trim()),
+                _ => return Err(ConfigurationError::NotAKeyValue(entry.into())),
+            };
+
+            match k {
+                "spidering" => {
+                    config.spidering = v
+                        .parse()
+                        .map_err(|_| ConfigurationError::InvalidSpidering(v.into()))?
                 }
-            } else if k == "rate" {
-                let x: u64 = v.parse().unwrap();
-                config.rate = Some(x);
-            } else {
-                panic!("Key {k} and Value {v} is implemented or supported");
+                "rate" => {
+                    config.rate = Some(
+                        v.parse()
+                            .map_err(|_| ConfigurationError::InvalidRate(v.into()))?,
+                    )
+                }
+                "interval" => {
+                    let interval: u64 = v
+                        .parse()
+                        .map_err(|_| ConfigurationError::InvalidInterval(v.into()))?;
+                    if interval == 0 {
+                        return Err(ConfigurationError::InvalidInterval(v.into()));
+                    }
+                    config.interval = Some(interval.clamp(MIN_CRAWL_INTERVAL, MAX_CRAWL_INTERVAL));
+                }
+                _ => return Err(ConfigurationError::UnknownKey(k.into())),
             }
         }
-        let mut config = Self::default();
-        if s.contains(',') {
-            for spilt in s.split(',') {
-                println!("split: {:?}", spilt);
-                parse_inner(&mut config, spilt);
+        Ok(config)
+    }
+
+    /// name of the TXT record that holds the spidering configuration of a forge
+    pub fn txt_record_name(forge: &Url) -> String {
+        format!("{TXT_RECORD_PREFIX}.{}", forge.host_str().unwrap())
+    }
+
+    /// Look up spidering configuration published by the admin of a forge. Forges that
+    /// don't publish one get the default configuration, as do forges that are addressed by IP
+    /// address, since they have no domain to publish it under
+    pub async fn lookup(
+        resolver: &TokioAsyncResolver,
+        forge: &Url,
+    ) -> Result<Self, ConfigurationError> {
+        if !matches!(forge.host(), Some(Host::Domain(_))) {
+            return Ok(Self::default());
+        }
+        match resolver.txt_lookup(Self::txt_record_name(forge)).await {
+            Ok(res) => match res.iter().next() {
+                Some(record) => Self::parse(&record.to_string()),
+                None => Ok(Self::default()),
+            },
+            Err(e) if matches!(e.kind(), ResolveErrorKind::NoRecordsFound { .. }) => {
+                Ok(Self::default())
             }
-        } else {
-            parse_inner(&mut config, s);
+            Err(e) => Err(ConfigurationError::Lookup(e)),
         }
-        config
     }
 }
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::tests::mock_dns;
 
     #[test]
     fn dns_txt_parser_works() {
-        const REQ: &str = "spidering=false,rate=500";
+        const REQ: &str = "spidering=false,rate=500,interval=86400";
         const RES: Configuration = Configuration {
             spidering: false,
             rate: Some(500),
+            interval: Some(86400),
         };
 
         const REQ_2: &str = "spidering=true";
         const RES_2: Configuration = Configuration {
             spidering: true,
             rate: None,
+            interval: None,
         };
 
-        assert_eq!(Configuration::parse(REQ), RES);
-        assert_eq!(Configuration::parse(REQ_2), RES_2);
+        assert_eq!(Configuration::parse(REQ).unwrap(), RES);
+        assert_eq!(Configuration::parse(REQ_2).unwrap(), RES_2);
+        assert_eq!(
+            Configuration::parse(" rate = 10 ,").unwrap(),
+            Configuration {
+                spidering: true,
+                rate: Some(10),
+                interval: None,
+            }
+        );
+
+        assert!(matches!(
+            Configuration::parse("spidering"),
+            Err(ConfigurationError::NotAKeyValue(_))
+        ));
+        assert!(matches!(
+            Configuration::parse("spidering=false=true"),
+            Err(ConfigurationError::NotAKeyValue(_))
+        ));
+        assert!(matches!(
+            Configuration::parse("spidering=no"),
+            Err(ConfigurationError::InvalidSpidering(_))
+        ));
+        assert!(matches!(
+            Configuration::parse("rate=-1"),
+            Err(ConfigurationError::InvalidRate(_))
+        ));
+        assert!(matches!(
+            Configuration::parse("interval=daily"),
+            Err(ConfigurationError::InvalidInterval(_))
+        ));
+        assert!(matches!(
+            Configuration::parse("interval=0"),
+            Err(ConfigurationError::InvalidInterval(_))
+        ));
+        assert!(matches!(
+            Configuration::parse("interval=-1"),
+            Err(ConfigurationError::InvalidInterval(_))
+        ));
+        for (interval, clamped) in [
+            ("1", MIN_CRAWL_INTERVAL),
+            ("18446744073709551615", MAX_CRAWL_INTERVAL),
+        ] {
+            assert_eq!(
+                Configuration::parse(&format!("interval={interval}"))
+                    .unwrap()
+                    .interval,
+                Some(clamped)
+            );
+        }
+        assert!(matches!(
+            Configuration::parse("spidering=true,foo=bar"),
+            Err(ConfigurationError::UnknownKey(_))
+        ));
+    }
+
+    #[test]
+    fn txt_record_name_works() {
+        let forge = Url::parse("https://git.example.org/explore").unwrap();
+        assert_eq!(
+            Configuration::txt_record_name(&forge),
+            "_starchart.git.example.org"
+        );
+    }
+
+    #[actix_rt::test]
+    async fn lookup_works() {
+        let resolver = mock_dns::resolver(&[("_starchart.git.example.org", "spidering=false")]);
+
+        let forge = Url::parse("https://git.example.org").unwrap();
+        assert_eq!(
+            Configuration::lookup(&resolver, &forge).await.unwrap(),
+            Configuration {
+                spidering: false,
+                rate: None,
+                interval: None,
+            }
+        );
+
+        let forge = Url::parse("https://git.example.com").unwrap();
+        assert_eq!(
+            Configuration::lookup(&resolver, &forge).await.unwrap(),
+            Configuration::default()
+        );
+
+        let forge = Url::parse("http://127.0.0.1:3000").unwrap();
+        assert_eq!(
+            Configuration::lookup(&resolver, &forge).await.unwrap(),
+            Configuration::default()
+        );
     }
 }
@@ -25,10 +25,14 @@ use actix_web::{
 };
 use db_core::errors::DBError;
 use derive_more::{Display, Error};
+use forge_core::errors::ForgeError;
+use publiccodeyml::errors::FederateErorr;
 use serde::{Deserialize, Serialize};
 use url::ParseError;
 use validator::ValidationErrors;
 
+use crate::dns::ConfigurationError;
+
 #[derive(Debug, Display, Error)]
 pub struct DBErrorWrapper(DBError);
 
@@ -38,6 +42,33 @@ impl std::cmp::PartialEq for DBErrorWrapper {
     }
 }
 
+#[derive(Debug, Display, Error)]
+pub struct ForgeErrorWrapper(ForgeError);
+
+impl std::cmp::PartialEq for ForgeErrorWrapper {
+    fn eq(&self, other: &Self) -> bool {
+        format!("{}", self.0) == format!("{}", other.0)
+    }
+}
+
+#[derive(Debug, Display, Error)]
+pub struct FederateErrorWrapper(FederateErorr);
+
+impl std::cmp::PartialEq for FederateErrorWrapper {
+    fn eq(&self, other: &Self) -> bool {
+        format!("{}", self.0) == format!("{}", other.0)
+    }
+}
+
+#[derive(Debug, Display, Error)]
+pub struct DNSConfigurationErrorWrapper(ConfigurationError);
+
+impl std::cmp::PartialEq for DNSConfigurationErrorWrapper {
+    fn eq(&self, other: &Self) -> bool {
+        format!("{}", self.0) == format!("{}", other.0)
+    }
+}
+
 #[derive(Debug, Display, PartialEq, Error)]
 #[cfg(not(tarpaulin_include))]
 pub enum ServiceError {
@@ -57,6 +88,10 @@ pub enum ServiceError {
     #[display(fmt = "{}", _0)]
     DBError(DBErrorWrapper),
 
+    /// Crawled data couldn't be written for federation
+    #[display(fmt = "{}", _0)]
+    FederateError(FederateErrorWrapper),
+
     /// DNS challenge value is already taken
     #[display(fmt = "DNS challenge is already taken")]
     DuplicateChallengeText,
@@ -65,6 +100,14 @@ pub enum ServiceError {
     #[display(fmt = "DNS challenge hostname is already taken")]
     DuplicateChallengeHostname,
 
+    /// DNS challenge expired before it was solved
+    #[display(fmt = "DNS challenge expired, please solve the new challenge")]
+    ChallengeExpired,
+
+    /// Forge has no challenge, or it expired
+    #[display(fmt = "No challenge found for this forge, please add it again")]
+    ChallengeNotFound,
+
     /// Hostname is already taken
     #[display(fmt = "Hostname is already taken")]
     DuplicateHostname,
@@ -84,6 +127,22 @@ pub enum ServiceError {
     /// Repository link is already taken
     #[display(fmt = "Repository link is already taken")]
     DuplicateRepositoryLink,
+
+    /// Forge software couldn't be determined or isn't supported
+    #[display(fmt = "Forge software is unknown or unsupported")]
+    ForgeTypeUnknown,
+
+    /// Forge isn't crawled by this instance
+    #[display(fmt = "Forge isn't crawled by this Starchart instance")]
+    ForgeNotFound,
+
+    /// Forge couldn't be crawled
+    #[display(fmt = "{}", _0)]
+    ForgeError(ForgeErrorWrapper),
+
+    /// Spidering configuration published by the forge couldn't be read
+    #[display(fmt = "{}", _0)]
+    DNSConfigurationError(DNSConfigurationErrorWrapper),
 }
 
 #[derive(Serialize, Deserialize)]
@@ -113,16 +172,25 @@ impl ResponseError for ServiceError {
             ServiceError::InternalServerError => StatusCode::INTERNAL_SERVER_ERROR,
             ServiceError::NotAnEmail => StatusCode::BAD_REQUEST,
             ServiceError::NotAUrl => StatusCode::BAD_REQUEST,
-            ServiceError::DBError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            ServiceError::DBError(_) | ServiceError::FederateError(_) => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
             ServiceError::DuplicateChallengeHostname
+            | ServiceError::ChallengeExpired
             | ServiceError::DuplicateHostname
             | ServiceError::DuplicateUserLink
             | ServiceError::DuplicateTopic
-            | ServiceError::DuplicateRepositoryLink => StatusCode::BAD_REQUEST,
+            | ServiceError::DuplicateRepositoryLink
+            | ServiceError::ForgeTypeUnknown => StatusCode::BAD_REQUEST,
+
+            ServiceError::ForgeNotFound | ServiceError::ChallengeNotFound => StatusCode::NOT_FOUND,
 
             ServiceError::DuplicateChallengeText | ServiceError::DuplicateForgeType => {
                 StatusCode::INTERNAL_SERVER_ERROR
             }
+            ServiceError::ForgeError(_) | ServiceError::DNSConfigurationError(_) => {
+                StatusCode::BAD_GATEWAY
+            }
         }
     }
 }
@@ -139,6 +207,27 @@ impl From<DBError> for ServiceError {
     }
 }
 
+impl From<FederateErorr> for ServiceError {
+    #[cfg(not(tarpaulin_include))]
+    fn from(e: FederateErorr) -> ServiceError {
+        ServiceError::FederateError(FederateErrorWrapper(e))
+    }
+}
+
+impl From<ForgeError> for ServiceError {
+    #[cfg(not(tarpaulin_include))]
+    fn from(e: ForgeError) -> ServiceError {
+        ServiceError::ForgeError(ForgeErrorWrapper(e))
+    }
+}
+
+impl From<ConfigurationError> for ServiceError {
+    #[cfg(not(tarpaulin_include))]
+    fn from(e: ConfigurationError) -> ServiceError {
+        ServiceError::DNSConfigurationError(DNSConfigurationErrorWrapper(e))
+    }
+}
+
 impl From<ValidationErrors> for ServiceError {
     #[cfg(not(tarpaulin_include))]
     fn from(_: ValidationErrors) -> ServiceError {
@@ -45,7 +45,7 @@ pub mod verify;
 
 use crate::federate::{get_federate, ArcFederate};
 use ctx::Ctx;
-use db::{sqlite, BoxDB};
+use db::BoxDB;
 use settings::Settings;
 use static_assets::FileMap;
 
@@ -72,7 +72,7 @@ async fn main() {
     lazy_static::initialize(&pages::TEMPLATES);
 
     let ctx = Ctx::new(settings.clone()).await;
-    let db = WebDB::new(sqlite::get_data(Some(settings.clone())).await);
+    let db = WebDB::new(db::get_data(Some(settings.clone())).await);
     let federate = WebFederate::new(get_federate(Some(settings.clone())).await);
 
     let (kill_crawler, rx) = oneshot::channel();
@@ -25,6 +25,7 @@ use url::Url;
 
 use crate::pages::errors::*;
 use crate::settings::Settings;
+use crate::verify::{ChallengeMethod, TXTChallenge};
 use crate::*;
 
 pub use crate::pages::*;
@@ -46,6 +47,8 @@ impl CtxError for AddChallenge {
 #[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
 pub struct AddChallengePayload {
     pub hostname: Url,
+    #[serde(default)]
+    pub method: ChallengeMethod,
 }
 
 impl AddChallenge {
@@ -83,8 +86,18 @@ pub fn services(cfg: &mut web::ServiceConfig) {
 #[post(path = "PAGES.auth.add")]
 pub async fn add_submit(
     payload: web::Form<AddChallengePayload>,
+    ctx: WebCtx,
+    db: WebDB,
 ) -> PageResult<impl Responder, AddChallenge> {
-    let link = PAGES.auth.verify_get(payload.hostname.as_ref());
+    TXTChallenge::check_hostname(&payload.hostname)
+        .map_err(|e| PageError::new(AddChallenge::new(&ctx.settings, Some(&payload)), e))?;
+    TXTChallenge::get_or_create(&ctx, &db, &payload.hostname)
+        .await
+        .map_err(|e| PageError::new(AddChallenge::new(&ctx.settings, Some(&payload)), e))?;
+
+    let link = PAGES
+        .auth
+        .verify_get(payload.hostname.as_ref(), payload.method);
 
     Ok(HttpResponse::Found()
         .insert_header((http::header::LOCATION, link))
@@ -99,6 +112,7 @@ mod tests {
 
     use super::AddChallengePayload;
     use crate::errors::*;
+    use crate::verify::{ChallengeMethod, TXTChallenge};
 
     #[cfg(test)]
     mod isolated {
@@ -106,6 +120,7 @@ mod tests {
         use crate::pages::auth::add::{AddChallenge, AddChallengePayload, ReadableError};
         use crate::pages::errors::*;
         use crate::settings::Settings;
+        use crate::verify::ChallengeMethod;
 
         #[test]
         fn add_page_works() {
@@ -113,6 +128,7 @@ mod tests {
             AddChallenge::page(&settings);
             let payload = AddChallengePayload {
                 hostname: url::Url::parse("https://example.com").unwrap(),
+                method: ChallengeMethod::Dns,
             };
             let page = AddChallenge::new(&settings, Some(&payload));
             page.with_error(&ReadableError::new(&ServiceError::ClosedForRegistration));
@@ -129,12 +145,16 @@ mod tests {
         let (db, ctx, federate, _tmpdir) = sqlx_sqlite::get_ctx().await;
         let app = get_app!(ctx, db, federate).await;
 
-        let payload = AddChallengePayload {
+        let mut payload = AddChallengePayload {
             hostname: Url::parse(&format!("https://{BASE_DOMAIN}")).unwrap(),
+            method: ChallengeMethod::Dns,
         };
 
         println!("{}", payload.hostname);
 
+        let key = TXTChallenge::get_challenge_txt_key(&ctx, &payload.hostname).unwrap();
+        let _ = db.delete_dns_challenge(&key).await;
+
         let resp = test::call_service(
             &app,
             post_request!(&payload, PAGES.auth.add, FORM).to_request(),
@@ -145,6 +165,7 @@ mod tests {
             panic!("{}", resp_err.error);
         }
         assert_eq!(resp.status(), StatusCode::FOUND);
+        let challenge = db.get_dns_challenge(&key).await.unwrap();
 
         // replay config
         let resp = test::call_service(
@@ -154,5 +175,39 @@ mod tests {
         .await;
 
         assert_eq!(resp.status(), StatusCode::FOUND);
+        assert_eq!(challenge, db.get_dns_challenge(&key).await.unwrap());
+
+        payload.method = ChallengeMethod::Http;
+        let resp = test::call_service(
+            &app,
+            post_request!(&payload, PAGES.auth.add, FORM).to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::FOUND);
+        assert_eq!(
+            resp.headers()
+                .get(actix_web::http::header::LOCATION)
+                .unwrap(),
+            &PAGES
+                .auth
+                .verify_get(payload.hostname.as_ref(), ChallengeMethod::Http)
+        );
+
+        // hostnames are encoded in the verification page's query string
+        let location = resp.headers().get(actix_web::http::header::LOCATION);
+        assert!(location
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .contains("hostname=https%3A%2F%2F"));
+
+        // only HTTP(S) URLs can be added
+        payload.hostname = Url::parse("mailto:admin@example.org").unwrap();
+        let resp = test::call_service(
+            &app,
+            post_request!(&payload, PAGES.auth.add, FORM).to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
     }
 }
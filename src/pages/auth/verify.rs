@@ -25,7 +25,9 @@ use url::Url;
 
 use crate::pages::errors::*;
 use crate::settings::Settings;
-use crate::verify::{Challenge, TXTChallenge};
+use crate::spider::VERIFIED_FORGE_PRIORITY;
+use crate::utils::now_unix_time_stamp;
+use crate::verify::{Challenge, ChallengeMethod, TXTChallenge};
 use crate::*;
 
 pub use crate::pages::*;
@@ -48,15 +50,24 @@ impl CtxError for VerifyChallenge {
 #[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
 pub struct VerifyChallengePayload {
     pub hostname: Url,
+    #[serde(default)]
+    pub method: ChallengeMethod,
 }
 
 impl VerifyChallenge {
-    fn new(settings: &Settings, payload: &Challenge) -> Self {
+    fn new(
+        settings: &Settings,
+        payload: &Challenge,
+        method: ChallengeMethod,
+        well_known: &str,
+    ) -> Self {
         let ctx = RefCell::new(ctx(settings));
         ctx.borrow_mut().insert(TITLE_KEY, TITLE);
         ctx.borrow_mut().insert(PAYLOAD_KEY, payload);
+        ctx.borrow_mut().insert("method", &method);
+        ctx.borrow_mut().insert("well_known_url", well_known);
         ctx.borrow_mut()
-            .insert("form_url", &PAGES.auth.verify_get(&payload.key));
+            .insert("form_url", &PAGES.auth.verify_get(&payload.url, method));
         Self { ctx }
     }
 
@@ -66,25 +77,55 @@ impl VerifyChallenge {
             .unwrap()
     }
 
-    pub fn page(s: &Settings, payload: &Challenge) -> String {
-        let p = Self::new(s, payload);
+    pub fn page(
+        s: &Settings,
+        payload: &Challenge,
+        method: ChallengeMethod,
+        well_known: &str,
+    ) -> String {
+        let p = Self::new(s, payload, method, well_known);
         p.render()
     }
 }
 
+/// Key of the TXT record and URL of the file that prove ownership of the forge at `hostname`
+fn challenge_locations(ctx: &ArcCtx, hostname: &Url) -> ServiceResult<(String, Url)> {
+    Ok((
+        TXTChallenge::get_challenge_txt_key(ctx, hostname)?,
+        TXTChallenge::get_well_known_url(ctx, hostname)?,
+    ))
+}
+
 #[get(path = "PAGES.auth.verify")]
 pub async fn get_verify(
     ctx: WebCtx,
+    db: WebDB,
     query: web::Query<VerifyChallengePayload>,
 ) -> PageResult<impl Responder, VerifyChallenge> {
-    let challenge = TXTChallenge::new(&ctx, &query.hostname);
+    let page = |key: &str, value: &str, well_known: &str| {
+        let value = Challenge {
+            key: key.to_owned(),
+            value: value.to_owned(),
+            url: query.hostname.to_string(),
+        };
+        VerifyChallenge::new(&ctx.settings, &value, query.method, well_known)
+    };
+    let (key, well_known) = challenge_locations(&ctx, &query.hostname)
+        .map_err(|e| PageError::new(page("", "", ""), e))?;
+    // challenges are only created when forges are added, and replaced when they are submitted
+    // after expiring. Visiting the page doesn't create one
+    let challenge = TXTChallenge::get(&ctx, &db, &query.hostname)
+        .await
+        .and_then(|challenge| challenge.ok_or(ServiceError::ChallengeNotFound))
+        .map_err(|e| PageError::new(page(&key, "", well_known.as_str()), e))?;
+
     let value = Challenge {
         key: challenge.key,
         value: challenge.value,
         url: query.hostname.to_string(),
     };
 
-    let login = VerifyChallenge::page(&ctx.settings, &value);
+    let login = VerifyChallenge::page(&ctx.settings, &value, query.method, well_known.as_str());
     let html = ContentType::html();
     Ok(HttpResponse::Ok().content_type(html).body(login))
 }
@@ -99,21 +140,57 @@ pub async fn submit_verify(
     payload: web::Form<VerifyChallengePayload>,
     ctx: WebCtx,
     db: WebDB,
-    federate: WebFederate,
 ) -> PageResult<impl Responder, VerifyChallenge> {
     let payload = payload.into_inner();
-    let challenge = TXTChallenge::new(&ctx, &payload.hostname);
+    let page = |key: &str, value: &str, well_known: &str| {
+        let value = Challenge {
+            key: key.to_owned(),
+            value: value.to_owned(),
+            url: payload.hostname.to_string(),
+        };
+        VerifyChallenge::new(&ctx.settings, &value, payload.method, well_known)
+    };
+    let (key, well_known) = challenge_locations(&ctx, &payload.hostname)
+        .map_err(|e| PageError::new(page("", "", ""), e))?;
+    let page = |value: &str| page(&key, value, well_known.as_str());
+
+    let challenge = match TXTChallenge::get(&ctx, &db, &payload.hostname).await {
+        Ok(Some(challenge)) => challenge,
+        // expired challenges can't be solved, show the new one that replaces it
+        Ok(None) => {
+            let challenge = TXTChallenge::get_or_create(&ctx, &db, &payload.hostname)
+                .await
+                .map_err(|e| PageError::new(page(""), e))?;
+            return Err(PageError::new(
+                page(&challenge.value),
+                ServiceError::ChallengeExpired,
+            ));
+        }
+        Err(e) => return Err(PageError::new(page(""), e)),
+    };
 
-    match challenge.verify_txt().await {
+    let verified = match payload.method {
+        ChallengeMethod::Dns => challenge.verify_txt(&ctx.resolver).await,
+        ChallengeMethod::Http => {
+            challenge
+                .verify_well_known(&ctx.well_known_client, &well_known)
+                .await
+        }
+    };
+
+    match verified {
         Ok(true) => {
-            let ctx = ctx.clone();
-            let federate = federate.clone();
-            let db = db.clone();
-            let fut = async move {
-                ctx.crawl(&payload.hostname, &db, &federate).await;
-            };
-
-            tokio::spawn(fut);
+            // the crawler picks up the forge before those that are due for a recrawl
+            db.schedule_forge_crawl(
+                &payload.hostname,
+                now_unix_time_stamp(),
+                VERIFIED_FORGE_PRIORITY,
+            )
+            .await
+            .map_err(|e| PageError::new(page(&challenge.value), e.into()))?;
+            db.delete_dns_challenge(&challenge.key)
+                .await
+                .map_err(|e| PageError::new(page(&challenge.value), e.into()))?;
             Ok(HttpResponse::Found()
                 .insert_header((http::header::LOCATION, PAGES.home))
                 .finish())
@@ -121,96 +198,132 @@ pub async fn submit_verify(
         _ => Ok(HttpResponse::Found()
             .insert_header((
                 http::header::LOCATION,
-                PAGES.auth.verify_get(&challenge.key),
+                PAGES
+                    .auth
+                    .verify_get(payload.hostname.as_str(), payload.method),
             ))
             .finish()),
     }
 }
 
-//#[cfg(test)]
-//mod tests {
-//    use actix_web::http::StatusCode;
-//    use actix_web::test;
-//    use url::Url;
-//
-//    use super::VerifyChallenge;
-//    use super::VerifyChallengePayload;
-//    use super::TXTChallenge;
-//    use crate::errors::*;
-//    use crate::pages::errors::*;
-//    use crate::settings::Settings;
-//
-//    use db_core::prelude::*;
-//
-//    #[cfg(test)]
-//    mod isolated {
-//        use crate::errors::ServiceError;
-//        use crate::pages::auth::add::{VerifyChallenge, VerifyChallengePayload, ReadableError};
-//        use crate::pages::errors::*;
-//        use crate::settings::Settings;
-//
-//        #[test]
-//        fn add_page_works() {
-//            let settings = Settings::new().unwrap();
-//            VerifyChallenge::page(&settings);
-//            let payload = VerifyChallengePayload {
-//                hostname: "https://example.com".into(),
-//            };
-//            let page = VerifyChallenge::new(&settings, Some(&payload));
-//            page.with_error(&ReadableError::new(&ServiceError::ClosedForRegistration));
-//            page.render();
-//        }
-//    }
-//
-//    #[actix_rt::test]
-//    async fn add_routes_work() {
-//        use crate::tests::*;
-//        use crate::*;
-//        const BASE_DOMAIN: &str = "add_routes_work.example.org";
-//
-//        let (db, ctx, federate, _tmpdir) = sqlx_sqlite::get_ctx().await;
-//        let app = get_app!(ctx, db, federate).await;
-//
-//        let payload = VerifyChallengePayload {
-//            hostname: format!("https://{BASE_DOMAIN}"),
-//        };
-//
-//        println!("{}", payload.hostname);
-//
-//        let hostname = get_hostname(&Url::parse(&payload.hostname).unwrap());
-//        let key = TXTChallenge::get_challenge_txt_key(&ctx, &hostname);
-//
-//        db.delete_dns_challenge(&key).await.unwrap();
-//        assert!(!db.dns_challenge_exists(&key).await.unwrap());
-//
-//        let resp = test::call_service(
-//            &app,
-//            post_request!(&payload, PAGES.auth.add, FORM).to_request(),
-//        )
-//        .await;
-//        if resp.status() != StatusCode::FOUND {
-//            let resp_err: ErrorToResponse = test::read_body_json(resp).await;
-//            panic!("{}", resp_err.error);
-//        }
-//        assert_eq!(resp.status(), StatusCode::FOUND);
-//
-//        assert!(db.dns_challenge_exists(&key).await.unwrap());
-//
-//        let challenge = db.get_dns_challenge_solution(&key).await.unwrap();
-//
-//        // replay config
-//        let resp = test::call_service(
-//            &app,
-//            post_request!(&payload, PAGES.auth.add, FORM).to_request(),
-//        )
-//        .await;
-//
-//        assert_eq!(resp.status(), StatusCode::FOUND);
-//
-//        assert!(db.dns_challenge_exists(&key).await.unwrap());
-//        assert_eq!(
-//            challenge,
-//            db.get_dns_challenge_solution(&key).await.unwrap()
-//        );
-//    }
-//}
+#[cfg(test)]
+mod tests {
+    use actix_web::http::{header, StatusCode};
+    use actix_web::test;
+    use url::Url;
+
+    use db_core::CreateDNSChallenge;
+
+    use super::VerifyChallengePayload;
+    use crate::pages::auth::add::AddChallengePayload;
+    use crate::tests::*;
+    use crate::utils::now_unix_time_stamp;
+    use crate::verify::{ChallengeMethod, TXTChallenge, CHALLENGE_TTL};
+    use crate::*;
+
+    #[actix_rt::test]
+    async fn verify_routes_work() {
+        const BASE_DOMAIN: &str = "verify-routes-work.example.org";
+
+        let (db, ctx, federate, _tmpdir) = sqlx_sqlite::get_ctx().await;
+        let hostname = Url::parse(&format!("https://{BASE_DOMAIN}")).unwrap();
+        let key = TXTChallenge::get_challenge_txt_key(&ctx, &hostname).unwrap();
+        let _ = db.delete_dns_challenge(&key).await;
+        let with_records = |records: &[(&str, &str)]| {
+            let mut ctx = (*ctx).clone();
+            ctx.resolver = mock_dns::resolver(records);
+            Arc::new(ctx)
+        };
+
+        let app = get_app!(ctx, db, federate).await;
+        let add = AddChallengePayload {
+            hostname: hostname.clone(),
+            method: ChallengeMethod::Dns,
+        };
+        let resp =
+            test::call_service(&app, post_request!(&add, PAGES.auth.add, FORM).to_request()).await;
+        assert_eq!(resp.status(), StatusCode::FOUND);
+        let challenge = db.get_dns_challenge(&key).await.unwrap();
+
+        let verify_page = PAGES
+            .auth
+            .verify_get(hostname.as_str(), ChallengeMethod::Dns);
+        let resp = get_request!(app, &verify_page);
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+        assert!(body.contains(&challenge.value));
+
+        let payload = VerifyChallengePayload {
+            hostname: hostname.clone(),
+            method: ChallengeMethod::Dns,
+        };
+
+        // challenge isn't solved
+        let unsolved_ctx = with_records(&[(&key, "not the challenge value")]);
+        let app = get_app!(unsolved_ctx, db, federate).await;
+        let resp = test::call_service(
+            &app,
+            post_request!(&payload, PAGES.auth.verify, FORM).to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::FOUND);
+        assert_eq!(resp.headers().get(header::LOCATION).unwrap(), &verify_page);
+        assert!(db.dns_challenge_exists(&key).await.unwrap());
+
+        // challenge is solved
+        let solved_ctx = with_records(&[(&key, &challenge.value)]);
+        let app = get_app!(solved_ctx, db, federate).await;
+        let resp = test::call_service(
+            &app,
+            post_request!(&payload, PAGES.auth.verify, FORM).to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::FOUND);
+        assert_eq!(resp.headers().get(header::LOCATION).unwrap(), PAGES.home);
+        assert!(!db.dns_challenge_exists(&key).await.unwrap());
+        assert!(db
+            .get_forge_crawl_schedule(&hostname)
+            .await
+            .unwrap()
+            .is_some());
+
+        // expired challenges are not accepted, even when they are solved
+        let now = now_unix_time_stamp();
+        let msg = CreateDNSChallenge {
+            url: &hostname,
+            key: &key,
+            value: &challenge.value,
+            created: now - CHALLENGE_TTL,
+            expires_on: now,
+        };
+        db.create_dns_challenge(&msg).await.unwrap();
+        let resp = test::call_service(
+            &app,
+            post_request!(&payload, PAGES.auth.verify, FORM).to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let new_challenge = db.get_dns_challenge(&key).await.unwrap();
+        assert_ne!(new_challenge.value, challenge.value);
+        assert!(new_challenge.expires_on > now);
+
+        // visiting the page of a forge that wasn't added doesn't create a challenge
+        let other = Url::parse(&format!("https://other.{BASE_DOMAIN}")).unwrap();
+        let other_key = TXTChallenge::get_challenge_txt_key(&ctx, &other).unwrap();
+        let _ = db.delete_dns_challenge(&other_key).await;
+        let page = PAGES.auth.verify_get(other.as_str(), ChallengeMethod::Dns);
+        let resp = get_request!(app, &page);
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert!(!db.dns_challenge_exists(&other_key).await.unwrap());
+
+        // challenges of URLs that aren't forges are rejected
+        let page = PAGES
+            .auth
+            .verify_get("mailto:admin@example.org", ChallengeMethod::Dns);
+        let resp = get_request!(app, &page);
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        db.delete_dns_challenge(&key).await.unwrap();
+        db.delete_forge_instance(&hostname).await.unwrap();
+    }
+}
@@ -0,0 +1,152 @@
+/*
+ * ForgeFlux StarChart - A federated software forge spider
+ * Copyright © 2022 Aravinth Manivannan <realaravinth@batsense.net>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+use actix_web::http::header::ContentType;
+use actix_web::{HttpResponse, Responder};
+use actix_web_codegen_const_routes::get;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use tera::Context;
+
+use crate::api::{get_forge_crawls, ForgeCrawlsReq, ForgeCrawlsResp};
+use crate::pages::errors::*;
+use crate::settings::Settings;
+use crate::*;
+
+pub use crate::pages::*;
+
+pub const TITLE: &str = "Crawls";
+pub const CRAWLS: TemplateFile = TemplateFile::new("crawls", "pages/chart/crawls.html");
+
+pub struct CrawlsPage {
+    ctx: RefCell<Context>,
+}
+
+impl CtxError for CrawlsPage {
+    fn with_error(&self, e: &ReadableError) -> String {
+        self.ctx.borrow_mut().insert(ERROR_KEY, e);
+        self.render()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct CrawlsPagePayload {
+    pub url: String,
+    pub crawls: Option<ForgeCrawlsResp>,
+    pub next_page: String,
+    pub prev_page: String,
+}
+
+impl CrawlsPage {
+    fn new(settings: &Settings, payload: &CrawlsPagePayload) -> Self {
+        let ctx = RefCell::new(ctx(settings));
+        ctx.borrow_mut().insert(TITLE_KEY, TITLE);
+        ctx.borrow_mut().insert(PAYLOAD_KEY, payload);
+        Self { ctx }
+    }
+
+    pub fn render(&self) -> String {
+        TEMPLATES.render(CRAWLS.name, &self.ctx.borrow()).unwrap()
+    }
+
+    pub fn page(s: &Settings, payload: &CrawlsPagePayload) -> String {
+        let p = Self::new(s, payload);
+        p.render()
+    }
+}
+
+pub fn services(cfg: &mut web::ServiceConfig) {
+    cfg.service(crawls);
+}
+
+#[get(path = "PAGES.crawls")]
+pub async fn crawls(
+    q: web::Query<ForgeCrawlsReq>,
+    ctx: WebCtx,
+    db: WebDB,
+) -> PageResult<impl Responder, CrawlsPage> {
+    let q = q.into_inner();
+    let page = q.page.unwrap_or(1).max(1);
+    let mut payload = CrawlsPagePayload {
+        url: q.url.clone(),
+        crawls: None,
+        next_page: PAGES.crawls_next(&q.url, page + 1),
+        prev_page: PAGES.crawls_next(&q.url, (page - 1).max(1)),
+    };
+
+    let crawls = get_forge_crawls(&db, &q)
+        .await
+        .map_err(|e| PageError::new(CrawlsPage::new(&ctx.settings, &payload), e))?;
+    payload.crawls = Some(crawls);
+    let page = CrawlsPage::page(&ctx.settings, &payload);
+
+    let html = ContentType::html();
+    Ok(HttpResponse::Ok().content_type(html).body(page))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::StatusCode;
+    use actix_web::test;
+    use url::Url;
+
+    use db_core::prelude::*;
+
+    use super::PAGES;
+    use crate::tests::*;
+    use crate::*;
+
+    #[actix_rt::test]
+    async fn crawls_page_works() {
+        const URL: &str = "https://crawls-page-works.example.com";
+
+        let (db, ctx, federate, _tmpdir) = sqlx_sqlite::get_ctx().await;
+        let app = get_app!(ctx, db, federate).await;
+
+        let url = Url::parse(URL).unwrap();
+        let resp = get_request!(&app, &PAGES.crawls_next(URL, 1));
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        db.schedule_forge_crawl(&url, 0, 0).await.unwrap();
+        let crawl = FinishedCrawl {
+            started_on: 1_684_650_918,
+            finished_on: 1_684_651_918,
+            error: Some("forge is down"),
+            stats: CrawlStats::default(),
+            next_crawl_on: 1_685_083_918,
+        };
+        db.record_forge_crawl_run(&url, &crawl).await.unwrap();
+
+        let resp = get_request!(&app, &PAGES.crawls_next(URL, 1));
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+        assert!(body.contains("forge is down"));
+
+        let resp = get_request!(
+            &app,
+            &format!("{}?url={URL}", crate::api::ROUTES.forge_crawls)
+        );
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp: crate::api::ForgeCrawlsResp = test::read_body_json(resp).await;
+        assert_eq!(resp.schedule.consecutive_failures, 1);
+        assert_eq!(resp.crawls.len(), 1);
+        assert_eq!(
+            resp.crawls[0].outcome,
+            CrawlOutcome::Failed("forge is down".into())
+        );
+    }
+}
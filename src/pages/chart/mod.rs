@@ -16,8 +16,10 @@
  * along with this program.  If not, see <http://www.gnu.org/licenses/>.
  */
 
+pub mod crawls;
 pub mod home;
 pub mod search;
+pub use crawls::CRAWLS;
 pub use home::EXPLORE;
 pub use home::REPO_INFO;
 pub use home::SEARCH_BAR;
@@ -30,9 +32,11 @@ pub fn register_templates(t: &mut tera::Tera) {
     REPO_INFO.register(t).expect(REPO_INFO.name);
     SEARCH_BAR.register(t).expect(SEARCH_BAR.name);
     SEARCH_RESULTS.register(t).expect(SEARCH_RESULTS.name);
+    CRAWLS.register(t).expect(CRAWLS.name);
 }
 
 pub fn services(cfg: &mut actix_web::web::ServiceConfig) {
     home::services(cfg);
     search::services(cfg);
+    crawls::services(cfg);
 }
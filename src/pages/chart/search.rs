@@ -88,18 +88,23 @@ pub async fn search(
     async fn _search(
         ctx: &ArcCtx,
         db: &BoxDB,
-        query: String,
+        query: &SearchQuery,
+        page: Option<u32>,
     ) -> ServiceResult<Vec<db_core::Repository>> {
-        let responses = ctx.search_repository(db, query).await?;
+        let responses = ctx.search_repository(db, query, page).await?;
 
         Ok(responses)
     }
 
-    let query = payload.into_inner().query;
-    let repos = _search(&ctx, &db, query.clone()).await.map_err(|e| {
-        let x = SearchPagePayload::default();
-        PageError::new(SearchPage::new(&ctx.settings, &x, Some(&query)), e)
-    })?;
+    let payload = payload.into_inner();
+    let query = payload.query;
+    let search_query = SearchQuery::parse(&query);
+    let repos = _search(&ctx, &db, &search_query, payload.page)
+        .await
+        .map_err(|e| {
+            let x = SearchPagePayload::default();
+            PageError::new(SearchPage::new(&ctx.settings, &x, Some(&query)), e)
+        })?;
 
     let payload = SearchPagePayload { repos };
     let page = SearchPage::page(&ctx.settings, &payload, Some(&query));
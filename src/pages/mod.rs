@@ -144,6 +144,7 @@ mod tests {
             auth::AUTH_CHALLENGE,
             auth::AUTH_ADD,
             chart::EXPLORE,
+            chart::CRAWLS,
             //            auth::AUTH_BASE,
             //            auth::login::LOGIN,
             //            auth::register::REGISTER,
@@ -17,6 +17,8 @@
  */
 use serde::Serialize;
 
+use crate::verify::ChallengeMethod;
+
 /// constant [Pages](Pages) instance
 pub const PAGES: Pages = Pages::new();
 
@@ -27,6 +29,8 @@ pub struct Pages {
     pub home: &'static str,
     pub explore: &'static str,
     pub search: &'static str,
+    /// crawl history of a forge
+    pub crawls: &'static str,
     /// auth routes
     pub auth: Auth,
 }
@@ -37,18 +41,28 @@ impl Pages {
         let explore = "/";
         let home = explore;
         let search = "/search";
+        let crawls = "/crawls";
         let auth = Auth::new();
         Pages {
             home,
             auth,
             explore,
             search,
+            crawls,
         }
     }
 
     pub fn explore_next(&self, page: u32) -> String {
         format!("{}?page={page}", self.explore)
     }
+
+    pub fn crawls_next(&self, url: &str, page: u32) -> String {
+        format!(
+            "{}?url={}&page={page}",
+            self.crawls,
+            urlencoding::encode(url)
+        )
+    }
 }
 
 #[derive(Serialize)]
@@ -76,8 +90,13 @@ impl Auth {
         }
     }
 
-    pub fn verify_get(&self, hostname: &str) -> String {
-        format!("{}?hostname={hostname}", self.verify)
+    pub fn verify_get(&self, hostname: &str, method: ChallengeMethod) -> String {
+        format!(
+            "{}?hostname={}&method={}",
+            self.verify,
+            urlencoding::encode(hostname),
+            method.to_str()
+        )
     }
 }
 
@@ -29,6 +29,9 @@ use crate::WebDB;
 
 pub use crate::api::{SearchRepositoryReq, ROUTES};
 
+/// Number of repositories in a page of search results
+const LIMIT: u32 = 50;
+
 impl Ctx {
     async fn client_federated_search(
         &self,
@@ -51,20 +54,26 @@ impl Ctx {
     pub async fn search_repository(
         &self,
         db: &Box<dyn SCDatabase>,
-        query: String,
+        query: &SearchQuery,
+        page: Option<u32>,
     ) -> ServiceResult<Vec<Repository>> {
-        let query = if query.contains('*') {
-            query
-        } else {
-            format!("*{}*", query)
-        };
         let federated_search_payload = SearchRepositoryReq {
-            query: query.clone(),
+            query: query.to_string(),
+            page,
+            structured: Some(query.clone()),
         };
-        let local_resp = db.search_repository(&query).await?;
+        let offset = page.unwrap_or(1).saturating_sub(1) * LIMIT;
+        let local_resp = db.query_repositories(query, offset, LIMIT).await?;
         let mut federated_resp = Vec::default();
 
-        for starchart in db.search_mini_index(&query).await?.iter() {
+        // peers are picked by the words of the query that their mini-indexes hold
+        let words = query.mini_index_words();
+        if words.is_empty() {
+            return Ok(local_resp);
+        }
+        let mini_index_query = format!("*{}*", words.join(" "));
+
+        for starchart in db.search_mini_index(&mini_index_query).await?.iter() {
             if db.is_starchart_imported(&Url::parse(starchart)?).await? {
                 log::debug!("{starchart} is imported");
                 continue;
@@ -107,9 +116,14 @@ pub async fn search_repository(
     ctx: WebCtx,
     db: WebDB,
 ) -> ServiceResult<impl Responder> {
-    let resp = ctx
-        .search_repository(&db, payload.into_inner().query)
-        .await?;
+    let payload = payload.into_inner();
+    // structured queries come from peers and are normalized like text queries, so that terms
+    // and filters reach the database in the same shape
+    let query = match payload.structured {
+        Some(structured) => SearchQuery::parse(&structured.to_string()),
+        None => SearchQuery::parse(&payload.query),
+    };
+    let resp = ctx.search_repository(&db, &query, payload.page).await?;
     Ok(HttpResponse::Ok().json(resp))
 }
 
@@ -166,6 +180,7 @@ mod tests {
             description: None,
             url,
             import: false,
+            metadata: RepositoryMetadata::default(),
         };
 
         let _ = db.delete_forge_instance(&create_forge_msg.url).await;
@@ -189,6 +204,8 @@ mod tests {
 
         let payload = SearchRepositoryReq {
             query: REPO_NAME[0..REPO_NAME.len() - 4].to_string(),
+            page: None,
+            structured: None,
         };
         let search_res_resp = test::call_service(
             &app,
@@ -201,6 +218,64 @@ mod tests {
         assert!(!search_res.is_empty());
         assert_eq!(search_res.first().as_ref().unwrap().name, REPO_NAME);
 
+        let payload = SearchRepositoryReq {
+            query: REPO_NAME.into(),
+            page: Some(2),
+            structured: None,
+        };
+        let search_res_resp = test::call_service(
+            &app,
+            post_request!(&payload, ROUTES.search.repository).to_request(),
+        )
+        .await;
+        assert_eq!(search_res_resp.status(), StatusCode::OK);
+        let search_res: Vec<Repository> = test::read_body_json(search_res_resp).await;
+        assert!(search_res.iter().all(|r| r.name != REPO_NAME));
+
+        // filters are parsed from the query, structured queries from peers are normalized
+        let payloads = [
+            (
+                format!("{REPO_NAME} topic:spider owner:{USERNAME}"),
+                None,
+                true,
+            ),
+            (format!("{REPO_NAME} topic:nomatch"), None, false),
+            (
+                "ignored".into(),
+                Some(SearchQuery {
+                    topics: vec!["starchart".into()],
+                    owner: Some(USERNAME.into()),
+                    archived: Some(false),
+                    ..Default::default()
+                }),
+                true,
+            ),
+            (
+                "ignored".into(),
+                Some(SearchQuery {
+                    terms: vec![format!("\"{}*", REPO_NAME.to_uppercase())],
+                    topics: vec!["Spider".into()],
+                    ..Default::default()
+                }),
+                true,
+            ),
+        ];
+        for (query, structured, found) in payloads {
+            let payload = SearchRepositoryReq {
+                query,
+                page: None,
+                structured,
+            };
+            let search_res_resp = test::call_service(
+                &app,
+                post_request!(&payload, ROUTES.search.repository).to_request(),
+            )
+            .await;
+            assert_eq!(search_res_resp.status(), StatusCode::OK);
+            let search_res: Vec<Repository> = test::read_body_json(search_res_resp).await;
+            assert_eq!(search_res.iter().any(|r| r.name == REPO_NAME), found);
+        }
+
         let mini_index_resp = get_request!(&app, ROUTES.introducer.get_mini_index);
         assert_eq!(mini_index_resp.status(), StatusCode::OK);
         let mini_index: api_routes::MiniIndex = test::read_body_json(mini_index_resp).await;
@@ -209,4 +284,60 @@ mod tests {
 
         // test ends
     }
+
+    #[actix_rt::test]
+    async fn filter_only_queries_are_federated() {
+        use std::net::TcpListener;
+
+        use actix_web::{App, HttpServer};
+
+        const TOPIC: &str = "filteronlyfederated";
+
+        let (db, ctx, _federate, _tmpdir) = sqlx_sqlite::get_ctx().await;
+
+        // peer Starchart instance that only knows of one repository
+        let peer_repo = Repository {
+            html_url: "https://peer-search-test.example.com/user1/peer".into(),
+            tags: Some(vec![TOPIC.into()]),
+            url: "https://peer-search-test.example.com/".into(),
+            name: "peer".into(),
+            username: "user1".into(),
+            description: None,
+            website: None,
+            import: false,
+            metadata: RepositoryMetadata::default(),
+        };
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let peer_url = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+        let repos = vec![peer_repo.clone()];
+        let server = HttpServer::new(move || {
+            let repos = repos.clone();
+            App::new().route(
+                ROUTES.search.repository,
+                web::post().to(move || {
+                    let repos = repos.clone();
+                    async move { HttpResponse::Ok().json(repos) }
+                }),
+            )
+        })
+        .workers(1)
+        .disable_signals()
+        .shutdown_timeout(0)
+        .listen(listener)
+        .unwrap()
+        .run();
+        let handle = server.handle();
+        actix_web::rt::spawn(server);
+
+        db.add_starchart_to_introducer(&peer_url).await.unwrap();
+        db.import_mini_index(&peer_url, TOPIC).await.unwrap();
+
+        let query = SearchQuery::parse(&format!("topic:{TOPIC}"));
+        assert!(query.terms.is_empty() && query.owner.is_none());
+        let res = ctx.search_repository(&db, &query, None).await.unwrap();
+        assert!(res.contains(&peer_repo));
+
+        db.rm_imported_mini_index(&peer_url).await.unwrap();
+        handle.stop(true).await;
+    }
 }
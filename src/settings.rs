@@ -15,6 +15,7 @@
  * You should have received a copy of the GNU Affero General Public License
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
+use std::net::SocketAddr;
 use std::path::Path;
 use std::{env, fs};
 
@@ -113,6 +114,9 @@ pub enum DBType {
     Postgres,
     #[display(fmt = "sqlite")]
     Sqlite,
+    /// nothing is persisted, for demos and tests. `database.url` is ignored
+    #[display(fmt = "memory")]
+    Memory,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -128,6 +132,45 @@ pub struct Crawler {
     pub client_timeout: u64,
     pub items_per_api_call: u64,
     pub wait_before_next_api_call: u64,
+    pub full_crawl_interval: u64,
+    /// number of forges that are crawled at once
+    pub workers: usize,
+    /// forges are no longer crawled after this many consecutive failed crawls. 0 = never
+    pub max_consecutive_failures: u32,
+}
+
+#[derive(Debug, Display, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResolverProtocol {
+    /// nameservers configured on the host, from /etc/resolv.conf
+    #[display(fmt = "system")]
+    System,
+    /// plain DNS over UDP and TCP
+    #[display(fmt = "plain")]
+    Plain,
+    /// DNS over TLS
+    #[display(fmt = "tls")]
+    Tls,
+    /// DNS over HTTPS
+    #[display(fmt = "https")]
+    Https,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Resolver {
+    pub protocol: ResolverProtocol,
+    /// `ip:port` of nameservers. Unused when `protocol` is `system`
+    pub nameservers: Vec<SocketAddr>,
+    /// name on the TLS certificates of the nameservers. Required for `tls` and `https`
+    pub tls_dns_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceHut {
+    /// OAuth 2.0 personal access token with the `git.sr.ht/REPOSITORIES:RO` grant
+    pub access_token: Option<String>,
+    /// profile links of users whose repositories are crawled
+    pub users: Vec<Url>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -150,6 +193,9 @@ pub struct Settings {
     pub admin_email: String,
     pub crawler: Crawler,
     pub introducer: Introducer,
+    pub resolver: Resolver,
+    #[serde(default)]
+    pub sourcehut: SourceHut,
 }
 
 #[cfg(not(tarpaulin_include))]
@@ -203,7 +249,9 @@ impl Settings {
                 .separator(SEPARATOR)
                 .list_separator(",")
                 .try_parsing(true)
-                .with_list_parse_key("introducer.nodes"),
+                .with_list_parse_key("introducer.nodes")
+                .with_list_parse_key("resolver.nameservers")
+                .with_list_parse_key("sourcehut.users"),
         );
         s = set_separator_field(s);
 
@@ -223,6 +271,7 @@ impl Settings {
 
         let mut settings = s.build()?.try_deserialize::<Settings>()?;
         settings.check_url();
+        settings.check_resolver();
 
         settings.log.set_log_level();
         settings.repository.create_root_dir();
@@ -237,6 +286,30 @@ impl Settings {
     fn check_url(&self) {
         Url::parse(&self.source_code).expect("Please enter a URL for source_code in settings");
     }
+
+    #[cfg(not(tarpaulin_include))]
+    fn check_resolver(&self) {
+        let resolver = &self.resolver;
+        if resolver.protocol == ResolverProtocol::System {
+            return;
+        }
+        if resolver.nameservers.is_empty() {
+            panic!(
+                "Please enter nameservers for resolver protocol {}",
+                resolver.protocol
+            );
+        }
+        if matches!(
+            resolver.protocol,
+            ResolverProtocol::Tls | ResolverProtocol::Https
+        ) && resolver.tls_dns_name.is_none()
+        {
+            panic!(
+                "Please enter tls_dns_name for resolver protocol {}",
+                resolver.protocol
+            );
+        }
+    }
 }
 
 #[cfg(not(tarpaulin_include))]
@@ -309,6 +382,32 @@ fn set_separator_field(mut s: ConfigBuilder<DefaultState>) -> ConfigBuilder<Defa
         &format!("{PREFIX}{SEPARATOR}CRAWLER{SEPARATOR}WAIT_BEFORE_NEXT_API_CALL"),
         "crawler.wait_before_next_api_call",
     );
+    s = from_env(
+        s,
+        &format!("{PREFIX}{SEPARATOR}CRAWLER{SEPARATOR}FULL_CRAWL_INTERVAL"),
+        "crawler.full_crawl_interval",
+    );
+    s = from_env(
+        s,
+        &format!("{PREFIX}{SEPARATOR}CRAWLER{SEPARATOR}WORKERS"),
+        "crawler.workers",
+    );
+    s = from_env(
+        s,
+        &format!("{PREFIX}{SEPARATOR}CRAWLER{SEPARATOR}MAX_CONSECUTIVE_FAILURES"),
+        "crawler.max_consecutive_failures",
+    );
+
+    s = from_env(
+        s,
+        &format!("{PREFIX}{SEPARATOR}RESOLVER{SEPARATOR}PROTOCOL"),
+        "resolver.protocol",
+    );
+    s = from_env(
+        s,
+        &format!("{PREFIX}{SEPARATOR}RESOLVER{SEPARATOR}TLS_DNS_NAME"),
+        "resolver.tls_dns_name",
+    );
     s
 }
 
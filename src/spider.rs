@@ -15,35 +15,211 @@
  * You should have received a copy of the GNU Affero General Public License
  * along with this program.  If not, see <http://www.gnu.org/licenses/>.
  */
+use std::collections::{HashMap, HashSet};
 use std::future::Future;
 use std::sync::Arc;
-use std::sync::RwLock;
+use std::sync::{Mutex, RwLock};
+use std::time::Duration;
 
-use log::info;
+use log::{error, info};
 use tokio::sync::oneshot::{error::TryRecvError, Receiver};
+use tokio::sync::Semaphore;
 use url::Url;
 
 use db_core::prelude::*;
-use forge_core::prelude::*;
-use gitea::Gitea;
+use forge_core::errors::ForgeError;
+use forge_core::ratelimit::RateLimiter;
 
 use crate::ctx::Ctx;
 use crate::db::BoxDB;
+use crate::dns::{Configuration, ConfigurationError};
+use crate::errors::*;
 use crate::federate::ArcFederate;
+use crate::utils::now_unix_time_stamp;
 use crate::ArcCtx;
 
+/// Error of the last crawl of each forge, keyed by forge URL. Cleared when a crawl succeeds.
+#[derive(Clone, Default)]
+pub struct CrawlErrors(Arc<RwLock<HashMap<String, String>>>);
+
+impl CrawlErrors {
+    /// Get error of the last crawl of a forge, if it failed
+    pub fn get(&self, url: &Url) -> Option<String> {
+        self.0.read().unwrap().get(url.as_str()).cloned()
+    }
+
+    fn record(&self, url: &Url, e: &ServiceError) {
+        self.0
+            .write()
+            .unwrap()
+            .insert(url.to_string(), e.to_string());
+    }
+
+    fn clear(&self, url: &Url) {
+        self.0.write().unwrap().remove(url.as_str());
+    }
+}
+
+/// Rate limiters of forges, keyed by forge URL. Shared by all crawls, so that a forge is never
+/// called faster than its rate limit allows.
+#[derive(Clone, Default)]
+pub struct RateLimiters(Arc<RwLock<HashMap<String, Arc<RateLimiter>>>>);
+
+impl RateLimiters {
+    fn get(&self, url: &Url) -> Option<Arc<RateLimiter>> {
+        self.0.read().unwrap().get(url.as_str()).cloned()
+    }
+
+    fn insert(&self, url: &Url, rate_limiter: RateLimiter) -> Arc<RateLimiter> {
+        self.0
+            .write()
+            .unwrap()
+            .entry(url.to_string())
+            .or_insert_with(|| Arc::new(rate_limiter))
+            .clone()
+    }
+}
+
+/// Priority of forges that were just verified, so that they are crawled before forges that are
+/// due for a recrawl
+pub const VERIFIED_FORGE_PRIORITY: u32 = 1;
+
+/// Time of the next crawl of a forge whose last crawl finished on `finished_on`
+fn next_crawl_on(finished_on: i64, interval: u64) -> i64 {
+    finished_on.saturating_add(i64::try_from(interval).unwrap_or(i64::MAX))
+}
+
 impl Ctx {
-    pub async fn crawl(&self, instance_url: &Url, db: &BoxDB, federate: &ArcFederate) {
+    /// Crawl forge instance. Errors are logged and recorded in [Ctx::crawl_errors]. Crawls of
+    /// scheduled forges are recorded in their crawl history, along with the time of the next
+    /// crawl. Forges that failed `crawler.max_consecutive_failures` crawls in a row are disabled.
+    pub async fn crawl(
+        &self,
+        instance_url: &Url,
+        db: &BoxDB,
+        federate: &ArcFederate,
+    ) -> ServiceResult<()> {
+        let started_on = now_unix_time_stamp();
+        let mut stats = CrawlStats::default();
+        let res = self
+            .crawl_forge(instance_url, db, federate, &mut stats)
+            .await;
+        match &res {
+            Ok(_) => self.crawl_errors.clear(instance_url),
+            Err(e) => {
+                error!("[crawl][{instance_url}] Crawl failed: {e}");
+                self.crawl_errors.record(instance_url, e);
+            }
+        }
+
+        let error = res.as_ref().err().map(|e| e.to_string());
+        self.record_scheduled_crawl(instance_url, started_on, error.as_deref(), stats, db)
+            .await;
+        res
+    }
+
+    /// Record crawl of a forge in its crawl history, along with the time of the next crawl, if
+    /// the forge is scheduled
+    async fn record_scheduled_crawl(
+        &self,
+        url: &Url,
+        started_on: i64,
+        error: Option<&str>,
+        stats: CrawlStats,
+        db: &BoxDB,
+    ) {
+        if let Ok(Some(schedule)) = db.get_forge_crawl_schedule(url).await {
+            let finished_on = now_unix_time_stamp();
+            let interval = schedule.interval.unwrap_or(self.settings.crawler.ttl);
+            let crawl = FinishedCrawl {
+                started_on,
+                finished_on,
+                error,
+                stats,
+                next_crawl_on: next_crawl_on(finished_on, interval),
+            };
+            if let Err(e) = self.record_crawl_run(url, &crawl, db).await {
+                error!("[crawl][{url}] Couldn't record crawl: {e}");
+            }
+        }
+    }
+
+    async fn record_crawl_run(
+        &self,
+        url: &Url,
+        crawl: &FinishedCrawl<'_>,
+        db: &BoxDB,
+    ) -> ServiceResult<()> {
+        db.record_forge_crawl_run(url, crawl).await?;
+        let max_failures = self.settings.crawler.max_consecutive_failures;
+        if max_failures == 0 {
+            return Ok(());
+        }
+        if let Some(schedule) = db.get_forge_crawl_schedule(url).await? {
+            if !schedule.disabled && schedule.consecutive_failures >= max_failures {
+                info!(
+                    "[crawl][{url}] Disabling crawls after {} consecutive failures",
+                    schedule.consecutive_failures
+                );
+                db.set_forge_crawl_disabled(url, true).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn crawl_forge(
+        &self,
+        instance_url: &Url,
+        db: &BoxDB,
+        federate: &ArcFederate,
+        stats: &mut CrawlStats,
+    ) -> ServiceResult<()> {
         info!("[crawl][{instance_url}] Init crawling");
-        let forge: Box<dyn SCForge> =
-            Box::new(Gitea::new(instance_url.clone(), self.client.clone()));
-        if !forge.is_forge().await {
-            unimplemented!("Forge type unimplemented");
+        // forges that opted out of spidering aren't called at all, not even to detect their type
+        let config = match Configuration::lookup(&self.resolver, instance_url).await {
+            Ok(config) => config,
+            Err(e @ ConfigurationError::Lookup(_)) => return Err(e.into()),
+            // admins that meant to opt out of spidering, but made a typo, aren't crawled
+            Err(e @ ConfigurationError::InvalidSpidering(_)) => {
+                info!("[crawl][{instance_url}] Can't read spidering configuration: {e}, skipping");
+                return Ok(());
+            }
+            // mistakes in the configuration mustn't get the forge disabled
+            Err(e) => {
+                error!("[crawl][{instance_url}] Ignoring spidering configuration: {e}");
+                Configuration::default()
+            }
+        };
+        if let Some(schedule) = db.get_forge_crawl_schedule(instance_url).await? {
+            if schedule.interval != config.interval {
+                info!(
+                    "[crawl][{instance_url}] Crawl interval set by forge admin: {:?}",
+                    config.interval
+                );
+                db.set_forge_crawl_interval(instance_url, config.interval)
+                    .await?;
+            }
+        }
+        if !config.spidering {
+            info!("[crawl][{instance_url}] Spidering disabled by forge admin, skipping");
+            return Ok(());
         }
+        let rate = config
+            .rate
+            .unwrap_or(self.settings.crawler.wait_before_next_api_call);
+
+        let forge = match self.forges.detect(instance_url).await? {
+            Some(forge) => forge,
+            None => return Err(ServiceError::ForgeTypeUnknown),
+        };
+        info!(
+            "[crawl][{instance_url}] Detected forge type: {}",
+            forge.forge_type().to_str()
+        );
 
         let mut page = 1;
         let url = forge.get_url();
-        if !db.forge_exists(url).await.unwrap() {
+        if !db.forge_exists(url).await? {
             info!("[crawl][{url}] Creating forge");
             let msg = CreateForge {
                 url: url.clone(),
@@ -51,75 +227,238 @@ impl Ctx {
                 starchart_url: None,
             };
 
-            db.create_forge_instance(&msg).await.unwrap();
-        } else if !federate.forge_exists(url).await.unwrap() {
-            let forge = db.get_forge(url).await.unwrap();
+            db.create_forge_instance(&msg).await?;
+        } else if !federate.forge_exists(url).await? {
+            let forge = db.get_forge(url).await?;
             let msg = CreateForge {
                 url: url.clone(),
                 forge_type: forge.forge_type,
                 starchart_url: None,
             };
-            federate.create_forge_instance(&msg).await.unwrap();
+            federate.create_forge_instance(&msg).await?;
         }
 
+        let rate_limiter = self.rate_limiter(url, rate, db).await?;
+
+        // crawls that were interrupted, by a restart or a failure, resume from the last page
+        // that was stored
+        let (mut started_on, mut since, mut token) = match db.get_crawl_checkpoint(url).await? {
+            Some(checkpoint) => {
+                info!(
+                    "[crawl][{url}] Resuming crawl started on {} from page {}",
+                    checkpoint.started_on, checkpoint.page
+                );
+                page = checkpoint.page;
+                (
+                    checkpoint.started_on,
+                    checkpoint.since,
+                    Some(checkpoint.token),
+                )
+            }
+            None => {
+                let started_on = now_unix_time_stamp();
+                (
+                    started_on,
+                    self.crawl_since(&db.get_forge(url).await?, started_on),
+                    None,
+                )
+            }
+        };
+        match since {
+            Some(since) => info!("[crawl][{url}] Crawling repositories updated since {since}"),
+            None => info!("[crawl][{url}] Crawling all repositories"),
+        }
+        let mut resumed = token.is_some();
+
         loop {
             info!("[crawl][{url}] Crawling. page: {page}");
             let res = forge
                 .crawl(
                     self.settings.crawler.items_per_api_call,
-                    page,
-                    self.settings.crawler.wait_before_next_api_call,
+                    token.take(),
+                    since,
+                    &rate_limiter,
                 )
                 .await;
-            if res.repos.is_empty() {
-                info!("[crawl][{url}] Finished crawling. pages: {}", page - 1);
-                break;
-            }
+            db.set_forge_rate_limit(url, &rate_limiter.state()).await?;
+            let res = match res {
+                // tokens of checkpoints can go stale, when the forge is upgraded for instance
+                Err(e @ (ForgeError::InvalidToken(_) | ForgeError::Decode(_))) if resumed => {
+                    info!("[crawl][{url}] Couldn't resume crawl: {e}. Restarting crawl");
+                    db.rm_crawl_checkpoint(url).await?;
+                    resumed = false;
+                    page = 1;
+                    started_on = now_unix_time_stamp();
+                    since = self.crawl_since(&db.get_forge(url).await?, started_on);
+                    continue;
+                }
+                res => res?,
+            };
+            resumed = false;
+            stats.pages += 1;
 
             for (username, u) in res.users.iter() {
-                if !db
-                    .user_exists(username, Some(forge.get_url()))
-                    .await
-                    .unwrap()
-                {
+                let msg = u.as_ref().into();
+                if db.user_exists(username, Some(forge.get_url())).await? {
+                    db.update_user(&msg).await?;
+                    stats.users_updated += 1;
+                } else {
                     info!("[crawl][{url}] Creating user: {username}");
-                    let msg = u.as_ref().into();
-                    db.add_user(&msg).await.unwrap();
-                    federate.create_user(&msg).await.unwrap();
+                    db.add_user(&msg).await?;
+                    stats.users_added += 1;
                 }
-                if !federate
-                    .user_exists(username, forge.get_url())
-                    .await
-                    .unwrap()
-                {
-                    let msg = u.as_ref().into();
-                    federate.create_user(&msg).await.unwrap();
+                if federate.user_exists(username, forge.get_url()).await? {
+                    federate.update_user(&msg).await?;
+                } else {
+                    federate.create_user(&msg).await?;
                 }
             }
 
             for r in res.repos.iter() {
-                if !db
+                let msg = r.into();
+                if db
                     .repository_exists(&r.name, &r.owner.username, &r.url)
-                    .await
-                    .unwrap()
+                    .await?
                 {
+                    db.update_repository(&msg).await?;
+                    stats.repositories_updated += 1;
+                } else {
                     info!("[crawl][{url}] Creating repository: {}", r.name);
-                    let msg = r.into();
-                    db.create_repository(&msg).await.unwrap();
-                    federate.create_repository(&msg).await.unwrap();
+                    db.create_repository(&msg).await?;
+                    stats.repositories_added += 1;
                 }
-                if !federate
+                if federate
                     .repository_exists(&r.name, &r.owner.username, &r.url)
-                    .await
-                    .unwrap()
+                    .await?
                 {
-                    let msg = r.into();
-                    federate.create_repository(&msg).await.unwrap();
+                    federate.update_repository(&msg).await?;
+                } else {
+                    federate.create_repository(&msg).await?;
                 }
             }
 
+            match (res.has_more, res.next_token) {
+                (true, Some(next)) => {
+                    let checkpoint = CrawlCheckpoint {
+                        token: next.clone(),
+                        page: page + 1,
+                        started_on,
+                        since,
+                    };
+                    db.set_crawl_checkpoint(url, &checkpoint).await?;
+                    token = Some(next);
+                }
+                _ => {
+                    info!("[crawl][{url}] Finished crawling. pages: {page}");
+                    break;
+                }
+            }
             page += 1;
         }
+
+        if since.is_none() {
+            self.remove_unseen(url, started_on, db, federate, stats)
+                .await?;
+        }
+
+        db.record_forge_crawl(url, started_on, since.is_none())
+            .await?;
+        db.rm_crawl_checkpoint(url).await?;
+        Ok(())
+    }
+
+    /// Get rate limiter of a forge. Rate limiters that aren't in use are restored from the
+    /// state persisted by previous crawls.
+    async fn rate_limiter(
+        &self,
+        url: &Url,
+        min_delay: u64,
+        db: &BoxDB,
+    ) -> ServiceResult<Arc<RateLimiter>> {
+        let min_delay = Duration::from_secs(min_delay);
+        if let Some(rate_limiter) = self.rate_limiters.get(url) {
+            rate_limiter.set_min_delay(min_delay);
+            return Ok(rate_limiter);
+        }
+        let rate_limiter = match db.get_forge_rate_limit(url).await? {
+            Some(state) => RateLimiter::with_state(min_delay, &state),
+            None => RateLimiter::new(min_delay),
+        };
+        Ok(self.rate_limiters.insert(url, rate_limiter))
+    }
+
+    /// Remove repositories and users that a full crawl, started at `started_on`, didn't see. They
+    /// were deleted or made private on the forge.
+    async fn remove_unseen(
+        &self,
+        url: &Url,
+        started_on: i64,
+        db: &BoxDB,
+        federate: &ArcFederate,
+        stats: &mut CrawlStats,
+    ) -> ServiceResult<()> {
+        for r in db.get_unseen_repositories(url, started_on).await? {
+            info!("[crawl][{url}] Removing repository: {}", r.name);
+            db.delete_repository(&r.username, &r.name, url).await?;
+            federate
+                .delete_repository(&r.username, &r.name, url)
+                .await?;
+            stats.repositories_removed += 1;
+        }
+
+        for u in db.get_unseen_users(url, started_on).await? {
+            info!("[crawl][{url}] Removing user: {}", u.username);
+            db.delete_user(&u.username, url).await?;
+            federate.delete_user(&u.username, url).await?;
+            stats.users_removed += 1;
+        }
+        Ok(())
+    }
+
+    /// Repositories updated before the returned timestamp were crawled by the last crawl. Full
+    /// crawls are done every `crawler.full_crawl_interval` seconds and when the forge hasn't been
+    /// crawled before.
+    fn crawl_since(&self, forge: &Forge, now: i64) -> Option<i64> {
+        match (forge.last_crawl_on, forge.last_full_crawl_on) {
+            (Some(last_crawl_on), Some(last_full_crawl_on))
+                if now - last_full_crawl_on < self.settings.crawler.full_crawl_interval as i64 =>
+            {
+                Some(last_crawl_on)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// How often the crawler checks for forges that are due when it is idle
+const SCHEDULER_POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Forges that are being crawled, keyed by forge URL
+type RunningCrawls = Arc<Mutex<HashSet<String>>>;
+
+/// Marks a forge as being crawled until it is dropped, so that forges are released even when
+/// their crawl panics
+struct RunningCrawl {
+    running: RunningCrawls,
+    url: String,
+}
+
+impl RunningCrawl {
+    fn start(running: &RunningCrawls, url: &str) -> Self {
+        running.lock().unwrap().insert(url.to_owned());
+        Self {
+            running: running.clone(),
+            url: url.to_owned(),
+        }
+    }
+}
+
+impl Drop for RunningCrawl {
+    fn drop(&mut self) {
+        self.running
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&self.url);
     }
 }
 
@@ -179,44 +518,95 @@ impl Crawler {
         }
 
         let fut = async move {
-            const LIMIT: u32 = 2;
-            let mut page = 0;
+            let workers = c.ctx.settings.crawler.workers.max(1);
+            let idle_workers = Arc::new(Semaphore::new(workers));
+            // forges that are being crawled stay due until their crawl is recorded, they must
+            // not be picked up by another worker
+            let running = RunningCrawls::default();
+            let mut crawled = false;
+            info!("Running crawling job");
             loop {
-                info!("Running crawling job");
-                let offset = page * LIMIT;
                 if c.shutdown() {
                     break;
                 }
 
-                let forges = c.db.get_all_forges(false, offset, LIMIT).await.unwrap();
-                if forges.is_empty() {
-                    c.federate.tar().await.unwrap();
-                    page = 0;
-                    tokio::time::sleep(std::time::Duration::new(c.ctx.settings.crawler.ttl, 0))
-                        .await;
-                    if c.shutdown() {
-                        info!("Stopping crawling job");
-                        break;
+                let due = match c
+                    .db
+                    .get_due_forge_crawls(now_unix_time_stamp(), (workers * 2) as u32)
+                    .await
+                {
+                    Ok(due) => due,
+                    Err(e) => {
+                        error!("Couldn't get forges that are due for a crawl: {e}");
+                        tokio::time::sleep(SCHEDULER_POLL_INTERVAL).await;
+                        continue;
                     }
+                };
+                let due: Vec<_> = {
+                    let running = running.lock().unwrap();
+                    due.into_iter()
+                        .filter(|s| !running.contains(&s.url))
+                        .collect()
+                };
 
+                if due.is_empty() {
+                    if crawled && idle_workers.available_permits() == workers {
+                        // failures are retried on the next poll
+                        match c.federate.tar().await {
+                            Ok(_) => crawled = false,
+                            Err(e) => error!("Couldn't archive federated data: {e}"),
+                        }
+                    }
+                    tokio::time::sleep(SCHEDULER_POLL_INTERVAL).await;
                     continue;
                 }
-                for forge in forges.iter() {
+
+                for schedule in due {
                     if c.shutdown() {
-                        info!("Stopping crawling job");
                         break;
                     }
-                    c.ctx
-                        .crawl(&Url::parse(&forge.url).unwrap(), &c.db, &c.federate)
-                        .await;
-                    page += 1;
-                }
+                    let worker = idle_workers.clone().acquire_owned().await.unwrap();
+                    let running = RunningCrawl::start(&running, &schedule.url);
+                    crawled = true;
 
-                if c.shutdown() {
-                    info!("Stopping crawling job");
-                    break;
+                    let c = c.clone();
+                    tokio::spawn(async move {
+                        let _worker = worker;
+                        let _running = running;
+                        let url = match Url::parse(&schedule.url) {
+                            Ok(url) => url,
+                            Err(e) => {
+                                error!("[crawl][{}] Invalid forge URL: {e}", schedule.url);
+                                return;
+                            }
+                        };
+                        let started_on = now_unix_time_stamp();
+                        let crawl = {
+                            let c = c.clone();
+                            let url = url.clone();
+                            tokio::spawn(async move { c.ctx.crawl(&url, &c.db, &c.federate).await })
+                        };
+                        // failures are logged and recorded by Ctx::crawl, panics are recorded
+                        // here so that the forge isn't picked up again right away
+                        if let Err(e) = crawl.await {
+                            error!("[crawl][{url}] Crawl panicked: {e}");
+                            c.ctx
+                                .record_scheduled_crawl(
+                                    &url,
+                                    started_on,
+                                    Some("Crawl panicked"),
+                                    CrawlStats::default(),
+                                    &c.db,
+                                )
+                                .await;
+                        }
+                    });
                 }
             }
+
+            // forges that are being crawled are finished before shutting down
+            let _ = idle_workers.acquire_many(workers as u32).await;
+            info!("Stopping crawling job");
         };
 
         tokio::spawn(fut)
@@ -225,7 +615,14 @@ impl Crawler {
 
 #[cfg(test)]
 mod tests {
-    use crate::tests::sqlx_sqlite;
+    use std::sync::Arc;
+
+    use db_core::prelude::*;
+    use mock_gitea::{Fixtures, MockGitea};
+
+    use super::VERIFIED_FORGE_PRIORITY;
+    use crate::errors::ServiceError;
+    use crate::tests::{mock_dns, sqlx_sqlite};
 
     use url::Url;
 
@@ -234,10 +631,10 @@ mod tests {
 
     #[actix_rt::test]
     async fn crawl_gitea() {
+        let gitea = MockGitea::start(Fixtures::generate(GITEA_USERNAME, 100));
         let (db, ctx, federate, _tmp_dir) = sqlx_sqlite::get_ctx().await;
-        let url = Url::parse(GITEA_HOST).unwrap();
-        ctx.crawl(&url, &db, &federate).await;
-        //        let hostname = get_hostname(&Url::parse(GITEA_HOST).unwrap());
+        let url = gitea.url().clone();
+        ctx.crawl(&url, &db, &federate).await.unwrap();
         assert!(db.forge_exists(&url).await.unwrap());
         assert!(db.user_exists(GITEA_USERNAME, Some(&url)).await.unwrap());
         assert!(db.user_exists(GITEA_USERNAME, None).await.unwrap());
@@ -249,6 +646,250 @@ mod tests {
                 .unwrap())
         }
         assert!(db.forge_exists(&url).await.unwrap());
+        gitea.stop().await;
+    }
+
+    #[actix_rt::test]
+    async fn stale_checkpoints_restart_crawls() {
+        let gitea = MockGitea::start(Fixtures::generate(GITEA_USERNAME, 30));
+        let (db, ctx, federate, _tmp_dir) = sqlx_sqlite::get_ctx().await;
+        let url = gitea.url().clone();
+        ctx.crawl(&url, &db, &federate).await.unwrap();
+
+        let checkpoint = CrawlCheckpoint {
+            token: "not-a-page".into(),
+            page: 3,
+            started_on: crate::utils::now_unix_time_stamp(),
+            since: None,
+        };
+        db.set_crawl_checkpoint(&url, &checkpoint).await.unwrap();
+        ctx.crawl(&url, &db, &federate).await.unwrap();
+        assert!(ctx.crawl_errors.get(&url).is_none());
+        assert!(db.get_crawl_checkpoint(&url).await.unwrap().is_none());
+        for i in 0..30 {
+            let repo = format!("repository_{i}");
+            assert!(db
+                .repository_exists(&repo, GITEA_USERNAME, &url)
+                .await
+                .unwrap())
+        }
+        gitea.stop().await;
+    }
+
+    #[actix_rt::test]
+    async fn crawl_since_works() {
+        const NOW: i64 = 1_681_635_173;
+
+        let (_db, ctx, _federate, _tmp_dir) = sqlx_sqlite::get_ctx().await;
+        let interval = ctx.settings.crawler.full_crawl_interval as i64;
+        let mut forge = Forge {
+            url: GITEA_HOST.into(),
+            forge_type: ForgeImplementation::Gitea,
+            starchart_url: None,
+            last_crawl_on: None,
+            last_full_crawl_on: None,
+        };
+        assert_eq!(ctx.crawl_since(&forge, NOW), None);
+
+        forge.last_crawl_on = Some(NOW - 10);
+        forge.last_full_crawl_on = Some(NOW - 20);
+        assert_eq!(ctx.crawl_since(&forge, NOW), Some(NOW - 10));
+
+        forge.last_full_crawl_on = Some(NOW - interval);
+        assert_eq!(ctx.crawl_since(&forge, NOW), None);
+    }
+
+    #[actix_rt::test]
+    async fn remove_unseen_works() {
+        let (db, ctx, federate, _tmp_dir) = sqlx_sqlite::get_ctx().await;
+        let url = Url::parse("https://remove-unseen.example.org").unwrap();
+        let create_forge_msg = CreateForge {
+            url: url.clone(),
+            forge_type: ForgeImplementation::Gitea,
+            starchart_url: None,
+        };
+        let add_user_msg = AddUser {
+            url: url.clone(),
+            username: "gone",
+            html_link: "https://remove-unseen.example.org/gone",
+            profile_photo: None,
+            import: false,
+        };
+        let add_repo_msg = AddRepository {
+            html_link: "https://remove-unseen.example.org/gone/repo",
+            tags: None,
+            url: url.clone(),
+            name: "repo",
+            owner: "gone",
+            description: None,
+            website: None,
+            import: false,
+            metadata: RepositoryMetadata::default(),
+        };
+        db.create_forge_instance(&create_forge_msg).await.unwrap();
+        db.add_user(&add_user_msg).await.unwrap();
+        db.create_repository(&add_repo_msg).await.unwrap();
+        federate
+            .create_forge_instance(&create_forge_msg)
+            .await
+            .unwrap();
+        federate.create_user(&add_user_msg).await.unwrap();
+        federate.create_repository(&add_repo_msg).await.unwrap();
+
+        // seen by the crawl
+        let mut stats = CrawlStats::default();
+        ctx.remove_unseen(&url, 0, &db, &federate, &mut stats)
+            .await
+            .unwrap();
+        assert!(db.repository_exists("repo", "gone", &url).await.unwrap());
+        assert_eq!(stats, CrawlStats::default());
+
+        // not seen by the crawl
+        ctx.remove_unseen(&url, i64::MAX, &db, &federate, &mut stats)
+            .await
+            .unwrap();
+        assert_eq!(stats.repositories_removed, 1);
+        assert_eq!(stats.users_removed, 1);
+        assert!(!db.repository_exists("repo", "gone", &url).await.unwrap());
+        assert!(!db.user_exists("gone", Some(&url)).await.unwrap());
+        assert!(!federate
+            .repository_exists("repo", "gone", &url)
+            .await
+            .unwrap());
+        assert!(!federate.user_exists("gone", &url).await.unwrap());
+    }
+
+    #[actix_rt::test]
+    async fn rate_limiter_is_restored() {
+        let (db, ctx, _federate, _tmp_dir) = sqlx_sqlite::get_ctx().await;
+        let url = Url::parse("https://rate-limited.example.org").unwrap();
+        db.create_forge_instance(&CreateForge {
+            url: url.clone(),
+            forge_type: ForgeImplementation::Gitea,
+            starchart_url: None,
+        })
+        .await
+        .unwrap();
+        let state = ForgeRateLimit {
+            delay: 8000,
+            blocked_until: None,
+        };
+        db.set_forge_rate_limit(&url, &state).await.unwrap();
+
+        let rate_limiter = ctx.rate_limiter(&url, 2, &db).await.unwrap();
+        assert_eq!(rate_limiter.state(), state);
+        // rate limiters are shared by crawls of a forge
+        assert!(Arc::ptr_eq(
+            &rate_limiter,
+            &ctx.rate_limiter(&url, 2, &db).await.unwrap()
+        ));
+    }
+
+    #[actix_rt::test]
+    async fn crawl_unreachable_forge_fails() {
+        let (db, ctx, federate, _tmp_dir) = sqlx_sqlite::get_ctx().await;
+        // nothing listens here, so the forge can't be probed
+        let url = Url::parse("http://localhost:1").unwrap();
+        assert!(matches!(
+            ctx.crawl(&url, &db, &federate).await,
+            Err(ServiceError::ForgeError(_))
+        ));
+        assert!(ctx.crawl_errors.get(&url).is_some());
+        assert!(!db.forge_exists(&url).await.unwrap());
+    }
+
+    #[actix_rt::test]
+    async fn bad_spidering_configurations_dont_fail_crawls() {
+        let gitea = MockGitea::start(Fixtures::generate(GITEA_USERNAME, 1));
+        let (db, ctx, federate, _tmp_dir) = sqlx_sqlite::get_ctx().await;
+        let mut url = gitea.url().clone();
+        url.set_host(Some("localhost")).unwrap();
+        let with_record = |record: &str| {
+            let mut ctx = (*ctx).clone();
+            ctx.resolver = mock_dns::resolver(&[("_starchart.localhost", record)]);
+            ctx
+        };
+
+        // unreadable opt-outs are respected
+        with_record("spidering=nope")
+            .crawl(&url, &db, &federate)
+            .await
+            .unwrap();
+        assert!(!db.forge_exists(&url).await.unwrap());
+
+        // other mistakes are ignored
+        with_record("rate=fast")
+            .crawl(&url, &db, &federate)
+            .await
+            .unwrap();
+        assert!(db.forge_exists(&url).await.unwrap());
+        gitea.stop().await;
+    }
+
+    #[test]
+    fn next_crawl_on_works() {
+        assert_eq!(super::next_crawl_on(100, 60), 160);
+        assert_eq!(super::next_crawl_on(100, u64::MAX), i64::MAX);
+        assert_eq!(super::next_crawl_on(i64::MAX - 1, 60), i64::MAX);
+    }
+
+    #[actix_rt::test]
+    async fn scheduled_crawls_are_recorded() {
+        let (db, ctx, federate, _tmp_dir) = sqlx_sqlite::get_ctx().await;
+        // forge admins set the crawl interval in their spidering configuration
+        let mut ctx = (*ctx).clone();
+        ctx.resolver = mock_dns::resolver(&[("_starchart.localhost", "interval=7200")]);
+        let url = Url::parse("http://localhost:2").unwrap();
+        let now = crate::utils::now_unix_time_stamp();
+
+        db.schedule_forge_crawl(&url, now, VERIFIED_FORGE_PRIORITY)
+            .await
+            .unwrap();
+        let due = db.get_due_forge_crawls(now, 10).await.unwrap();
+        assert_eq!(due[0].url, db_core::clean_url(&url));
+        assert_eq!(due[0].priority, VERIFIED_FORGE_PRIORITY);
+
+        assert!(ctx.crawl(&url, &db, &federate).await.is_err());
+        let schedule = db.get_forge_crawl_schedule(&url).await.unwrap().unwrap();
+        assert_eq!(schedule.interval, Some(7200));
+        assert!(matches!(
+            schedule.last_outcome,
+            Some(CrawlOutcome::Failed(_))
+        ));
+        assert!(schedule.last_started_on.unwrap() >= now);
+        assert_eq!(
+            schedule.next_crawl_on,
+            schedule.last_finished_on.unwrap() + 7200
+        );
+        assert_eq!(schedule.priority, 0);
+        assert!(!schedule.disabled);
+
+        // forges that keep failing are disabled
+        let max_failures = ctx.settings.crawler.max_consecutive_failures;
+        for _ in 1..max_failures {
+            assert!(ctx.crawl(&url, &db, &federate).await.is_err());
+        }
+        let schedule = db.get_forge_crawl_schedule(&url).await.unwrap().unwrap();
+        assert_eq!(schedule.consecutive_failures, max_failures);
+        assert!(schedule.disabled);
+        let runs = db.get_forge_crawl_runs(&url, 0, 100).await.unwrap();
+        assert_eq!(runs.len() as u32, max_failures);
+        assert!(runs
+            .iter()
+            .all(|r| matches!(r.outcome, CrawlOutcome::Failed(_))));
+    }
+
+    #[actix_rt::test]
+    async fn panicking_crawls_release_forges() {
+        let running = super::RunningCrawls::default();
+        let guard = super::RunningCrawl::start(&running, GITEA_HOST);
+        assert!(running.lock().unwrap().contains(GITEA_HOST));
+        let crawl = tokio::spawn(async move {
+            let _running = guard;
+            panic!("crawl panicked");
+        });
+        assert!(crawl.await.is_err());
+        assert!(running.lock().unwrap().is_empty());
     }
 
     //    #[actix_rt::test]
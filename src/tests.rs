@@ -70,6 +70,74 @@ pub mod sqlx_sqlite {
     }
 }
 
+/// Stand-in DNS server, so that tests don't depend on records published in production
+pub mod mock_dns {
+    use std::collections::HashMap;
+    use std::net::{SocketAddr, UdpSocket};
+    use std::thread;
+
+    use trust_dns_proto::op::{Message, MessageType, ResponseCode};
+    use trust_dns_proto::rr::{rdata::TXT, RData, Record, RecordType};
+    use trust_dns_resolver::TokioAsyncResolver;
+
+    use crate::ctx::Ctx;
+    use crate::settings::{Resolver, ResolverProtocol};
+
+    /// Serve TXT `records`, given as (name, value) pairs, on a random local port. All other
+    /// names are answered with NXDOMAIN
+    pub fn spawn(records: &[(&str, &str)]) -> SocketAddr {
+        let records: HashMap<String, String> = records
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = socket.local_addr().unwrap();
+
+        thread::spawn(move || {
+            let mut buf = [0; 512];
+            loop {
+                let (len, src) = socket.recv_from(&mut buf).unwrap();
+                let query = match Message::from_vec(&buf[..len]) {
+                    Ok(query) => query,
+                    Err(_) => continue,
+                };
+                let mut resp = Message::new();
+                resp.set_id(query.id())
+                    .set_message_type(MessageType::Response)
+                    .set_op_code(query.op_code())
+                    .set_recursion_desired(query.recursion_desired())
+                    .set_recursion_available(true)
+                    .set_response_code(ResponseCode::NXDomain);
+
+                for q in query.queries() {
+                    resp.add_query(q.clone());
+                    let name = q.name().to_string();
+                    if q.query_type() != RecordType::TXT {
+                        continue;
+                    }
+                    if let Some(value) = records.get(name.trim_end_matches('.')) {
+                        let rdata = RData::TXT(TXT::new(vec![value.clone()]));
+                        resp.add_answer(Record::from_rdata(q.name().clone(), 60, rdata))
+                            .set_response_code(ResponseCode::NoError);
+                    }
+                }
+                socket.send_to(&resp.to_vec().unwrap(), src).unwrap();
+            }
+        });
+        addr
+    }
+
+    /// Resolver that looks up names on a stand-in DNS server serving `records`
+    pub fn resolver(records: &[(&str, &str)]) -> TokioAsyncResolver {
+        let settings = Resolver {
+            protocol: ResolverProtocol::Plain,
+            nameservers: vec![spawn(records)],
+            tls_dns_name: None,
+        };
+        Ctx::resolver(&settings)
+    }
+}
+
 #[macro_export]
 macro_rules! get_cookie {
     ($resp:expr) => {
@@ -28,3 +28,13 @@ pub(crate) fn get_random(len: usize) -> String {
         .take(len)
         .collect::<String>()
 }
+
+/// Current time as a unix timestamp
+pub(crate) fn now_unix_time_stamp() -> i64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64
+}
@@ -15,15 +15,56 @@
  * You should have received a copy of the GNU Affero General Public License
  * along with this program.  If not, see <http://www.gnu.org/licenses/>.
  */
+use std::time::Duration;
+
+use reqwest::Client;
 use serde::{Deserialize, Serialize};
-use trust_dns_resolver::{
-    config::{ResolverConfig, ResolverOpts},
-    AsyncResolver,
-};
+use trust_dns_resolver::TokioAsyncResolver;
 use url::Url;
 
+use db_core::prelude::*;
+
+use crate::db::BoxDB;
+use crate::errors::*;
+use crate::utils::{get_random, now_unix_time_stamp};
 use crate::ArcCtx;
 
+/// Challenges can be solved for this long after they are created, in seconds
+pub const CHALLENGE_TTL: i64 = 60 * 60 * 24 * 3;
+
+/// Length of random challenge values
+const CHALLENGE_VALUE_LEN: usize = 32;
+
+/// Forges serve HTTP challenges at `{WELL_KNOWN_PREFIX}/{{ starchart instance's hostname }}`
+pub const WELL_KNOWN_PREFIX: &str = "/.well-known/starchart";
+
+/// Forges must serve HTTP challenges within this long
+pub const WELL_KNOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// HTTP challenges longer than this, in bytes, are rejected without being read
+const MAX_WELL_KNOWN_LEN: usize = 1024;
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+/// ways in which forge admins can prove ownership of their forge
+pub enum ChallengeMethod {
+    /// TXT record on the forge's DNS
+    #[default]
+    Dns,
+    /// file served by the forge, for admins that can't change DNS records
+    Http,
+}
+
+impl ChallengeMethod {
+    /// Convert [ChallengeMethod] to [str]
+    pub const fn to_str(&self) -> &'static str {
+        match self {
+            ChallengeMethod::Dns => "dns",
+            ChallengeMethod::Http => "http",
+        }
+    }
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 /// represents a DNS challenge
 pub struct Challenge {
@@ -47,59 +88,291 @@ impl TXTChallenge {
         format!("starchart-{}", &ctx.settings.server.domain)
     }
 
-    pub fn get_challenge_txt_key(ctx: &ArcCtx, hostname: &Url) -> String {
-        format!(
+    /// Forges are identified by their HTTP(S) URL, challenges can't be created for other URLs
+    pub fn check_hostname(hostname: &Url) -> ServiceResult<()> {
+        if matches!(hostname.scheme(), "http" | "https") && hostname.host_str().is_some() {
+            Ok(())
+        } else {
+            Err(ServiceError::NotAUrl)
+        }
+    }
+
+    pub fn get_challenge_txt_key(ctx: &ArcCtx, hostname: &Url) -> ServiceResult<String> {
+        Self::check_hostname(hostname)?;
+        Ok(format!(
             "{}.{}",
             Self::get_challenge_txt_key_prefix(ctx),
-            hostname.host_str().unwrap()
-        )
+            hostname.host_str().ok_or(ServiceError::NotAUrl)?
+        ))
     }
 
-    pub fn new(ctx: &ArcCtx, hostname: &Url) -> Self {
-        let key = Self::get_challenge_txt_key(ctx, hostname);
-        let value = ctx.settings.server.domain.clone();
-        Self { key, value }
+    /// URL at which the forge must serve the challenge value, when proving ownership over HTTP
+    pub fn get_well_known_url(ctx: &ArcCtx, hostname: &Url) -> ServiceResult<Url> {
+        Self::check_hostname(hostname)?;
+        Ok(hostname.join(&format!(
+            "{WELL_KNOWN_PREFIX}/{}",
+            &ctx.settings.server.domain
+        ))?)
     }
 
-    pub async fn verify_txt(&self) -> Result<bool, Box<dyn std::error::Error>> {
-        let conf = ResolverConfig::cloudflare_tls();
-        let opts = ResolverOpts::default();
-        let resolver = AsyncResolver::tokio(conf, opts)?;
+    /// Challenge of the forge at `hostname`, if it has one that hasn't expired
+    pub async fn get(ctx: &ArcCtx, db: &BoxDB, hostname: &Url) -> ServiceResult<Option<Self>> {
+        let key = Self::get_challenge_txt_key(ctx, hostname)?;
+        if !db.dns_challenge_exists(&key).await? {
+            return Ok(None);
+        }
+        let challenge = db.get_dns_challenge(&key).await?;
+        if challenge.expires_on <= now_unix_time_stamp() {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            key: challenge.key,
+            value: challenge.value,
+        }))
+    }
+
+    /// Challenge of the forge at `hostname`. Forges that don't have one, or whose challenge
+    /// expired, get a new challenge with a random value
+    pub async fn get_or_create(ctx: &ArcCtx, db: &BoxDB, hostname: &Url) -> ServiceResult<Self> {
+        let now = now_unix_time_stamp();
+        db.rm_expired_dns_challenges(now).await?;
+        if let Some(challenge) = Self::get(ctx, db, hostname).await? {
+            return Ok(challenge);
+        }
+
+        let key = Self::get_challenge_txt_key(ctx, hostname)?;
+        let value = get_random(CHALLENGE_VALUE_LEN);
+        let msg = CreateDNSChallenge {
+            url: hostname,
+            key: &key,
+            value: &value,
+            created: now,
+            expires_on: now + CHALLENGE_TTL,
+        };
+        db.create_dns_challenge(&msg).await?;
+        Ok(Self { key, value })
+    }
+
+    pub async fn verify_txt(
+        &self,
+        resolver: &TokioAsyncResolver,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
         let res = resolver.txt_lookup(&self.key).await?;
         Ok(res.iter().any(|r| r.to_string() == self.value))
     }
+
+    /// Check the challenge served by the forge at `url`. `client` shouldn't follow redirects,
+    /// see [Ctx::well_known_client](crate::ctx::Ctx::well_known_client)
+    pub async fn verify_well_known(
+        &self,
+        client: &Client,
+        url: &Url,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        let mut res = client.get(url.clone()).send().await?;
+        if !res.status().is_success()
+            || res
+                .content_length()
+                .is_some_and(|len| len > MAX_WELL_KNOWN_LEN as u64)
+        {
+            return Ok(false);
+        }
+        let mut body = Vec::new();
+        while let Some(chunk) = res.chunk().await? {
+            body.extend_from_slice(&chunk);
+            if body.len() > MAX_WELL_KNOWN_LEN {
+                return Ok(false);
+            }
+        }
+        Ok(String::from_utf8_lossy(&body).trim() == self.value)
+    }
 }
 
 #[cfg(test)]
 pub mod tests {
     use super::*;
-    use crate::tests::sqlx_sqlite;
+    use std::net::TcpListener;
+
+    use crate::tests::{mock_dns, sqlx_sqlite};
+    use actix_web::{http::header, web, App, HttpResponse, HttpServer};
+    use mock_gitea::{Fixtures, MockGitea};
     pub const BASE_DOMAIN: &str = "https://forge.forgeflux.org";
     pub const VALUE: &str = "ifthisvalueisretrievedbyforgefluxstarchartthenthetestshouldpass";
 
     #[actix_rt::test]
     async fn verify_txt_works() {
-        // please note that this DNS record is in prod
-
         let (_db, ctx, _federate, _tmp_dir) = sqlx_sqlite::get_ctx().await;
 
         let base_hostname = Url::parse(BASE_DOMAIN).unwrap();
 
-        let key = TXTChallenge::get_challenge_txt_key(&ctx, &base_hostname);
+        let key = TXTChallenge::get_challenge_txt_key(&ctx, &base_hostname).unwrap();
+        let resolver = mock_dns::resolver(&[(&key, VALUE)]);
         let mut txt_challenge = TXTChallenge {
             value: VALUE.to_string(),
             key: key.clone(),
         };
         assert_eq!(
-            TXTChallenge::get_challenge_txt_key(&ctx, &base_hostname),
+            TXTChallenge::get_challenge_txt_key(&ctx, &base_hostname).unwrap(),
             key,
         );
 
         assert!(
-            txt_challenge.verify_txt().await.unwrap(),
+            txt_challenge.verify_txt(&resolver).await.unwrap(),
             "TXT Challenge verification test"
         );
         txt_challenge.value = key;
-        assert!(!txt_challenge.verify_txt().await.unwrap());
+        assert!(!txt_challenge.verify_txt(&resolver).await.unwrap());
+    }
+
+    #[actix_rt::test]
+    async fn verify_well_known_works() {
+        let (_db, ctx, _federate, _tmp_dir) = sqlx_sqlite::get_ctx().await;
+
+        let path = format!("starchart/{}", ctx.settings.server.domain);
+        let mut fixtures = Fixtures::generate("verify_well_known_works", 0);
+        fixtures.well_known.insert(path, VALUE.into());
+        fixtures.well_known.insert(
+            "starchart/oversized".into(),
+            format!("{VALUE}{}", " ".repeat(MAX_WELL_KNOWN_LEN)),
+        );
+        let forge = MockGitea::start(fixtures);
+
+        let url = TXTChallenge::get_well_known_url(&ctx, forge.url()).unwrap();
+        assert_eq!(
+            url.path(),
+            format!("{WELL_KNOWN_PREFIX}/{}", ctx.settings.server.domain)
+        );
+
+        let mut challenge = TXTChallenge {
+            key: TXTChallenge::get_challenge_txt_key(&ctx, forge.url()).unwrap(),
+            value: VALUE.to_string(),
+        };
+        assert!(challenge
+            .verify_well_known(&ctx.well_known_client, &url)
+            .await
+            .unwrap());
+        challenge.value = challenge.key.clone();
+        assert!(!challenge
+            .verify_well_known(&ctx.well_known_client, &url)
+            .await
+            .unwrap());
+
+        let mut url = url;
+        url.set_path(&format!("{WELL_KNOWN_PREFIX}/example.org"));
+        assert!(!challenge
+            .verify_well_known(&ctx.well_known_client, &url)
+            .await
+            .unwrap());
+
+        // oversized challenges aren't read
+        challenge.value = VALUE.into();
+        url.set_path(&format!("{WELL_KNOWN_PREFIX}/oversized"));
+        assert!(!challenge
+            .verify_well_known(&ctx.well_known_client, &url)
+            .await
+            .unwrap());
+
+        // challenges are only read from the forge itself
+        let target = TXTChallenge::get_well_known_url(&ctx, forge.url())
+            .unwrap()
+            .to_string();
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let redirect = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+        let server = HttpServer::new(move || {
+            let target = target.clone();
+            App::new().default_service(web::to(move || {
+                let target = target.clone();
+                async move {
+                    HttpResponse::Found()
+                        .insert_header((header::LOCATION, target))
+                        .finish()
+                }
+            }))
+        })
+        .workers(1)
+        .disable_signals()
+        .shutdown_timeout(0)
+        .listen(listener)
+        .unwrap()
+        .run();
+        let handle = server.handle();
+        actix_web::rt::spawn(server);
+        assert!(!challenge
+            .verify_well_known(&ctx.well_known_client, &redirect)
+            .await
+            .unwrap());
+        handle.stop(true).await;
+
+        forge.stop().await;
+    }
+
+    #[actix_rt::test]
+    async fn challenges_need_http_urls() {
+        let (db, ctx, _federate, _tmp_dir) = sqlx_sqlite::get_ctx().await;
+
+        for hostname in [
+            "mailto:admin@example.org",
+            "data:text/plain,forge",
+            "file:///forge",
+        ] {
+            let hostname = Url::parse(hostname).unwrap();
+            assert_eq!(
+                TXTChallenge::check_hostname(&hostname),
+                Err(ServiceError::NotAUrl)
+            );
+            assert!(TXTChallenge::get_challenge_txt_key(&ctx, &hostname).is_err());
+            assert!(TXTChallenge::get_well_known_url(&ctx, &hostname).is_err());
+            assert_eq!(
+                TXTChallenge::get_or_create(&ctx, &db, &hostname).await,
+                Err(ServiceError::NotAUrl)
+            );
+        }
+        let hostname = Url::parse(BASE_DOMAIN).unwrap();
+        assert!(TXTChallenge::check_hostname(&hostname).is_ok());
+    }
+
+    #[actix_rt::test]
+    async fn challenge_lifecycle_works() {
+        let (db, ctx, _federate, _tmp_dir) = sqlx_sqlite::get_ctx().await;
+
+        let hostname = Url::parse("https://challenge-lifecycle-works.example.org").unwrap();
+        let key = TXTChallenge::get_challenge_txt_key(&ctx, &hostname).unwrap();
+        let _ = db.delete_dns_challenge(&key).await;
+        assert_eq!(TXTChallenge::get(&ctx, &db, &hostname).await.unwrap(), None);
+
+        let challenge = TXTChallenge::get_or_create(&ctx, &db, &hostname)
+            .await
+            .unwrap();
+        assert_eq!(challenge.key, key);
+        assert_eq!(challenge.value.len(), CHALLENGE_VALUE_LEN);
+        assert_eq!(
+            TXTChallenge::get_or_create(&ctx, &db, &hostname)
+                .await
+                .unwrap(),
+            challenge
+        );
+        assert_eq!(
+            TXTChallenge::get(&ctx, &db, &hostname).await.unwrap(),
+            Some(challenge.clone())
+        );
+
+        // expired challenges are replaced
+        db.delete_dns_challenge(&key).await.unwrap();
+        let now = now_unix_time_stamp();
+        let msg = CreateDNSChallenge {
+            url: &hostname,
+            key: &key,
+            value: &challenge.value,
+            created: now - CHALLENGE_TTL,
+            expires_on: now,
+        };
+        db.create_dns_challenge(&msg).await.unwrap();
+        assert_eq!(TXTChallenge::get(&ctx, &db, &hostname).await.unwrap(), None);
+        let new_challenge = TXTChallenge::get_or_create(&ctx, &db, &hostname)
+            .await
+            .unwrap();
+        assert_ne!(new_challenge.value, challenge.value);
+        assert!(db.get_dns_challenge(&key).await.unwrap().expires_on > now);
+
+        db.delete_dns_challenge(&key).await.unwrap();
     }
 }